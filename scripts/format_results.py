@@ -8,16 +8,20 @@
 import argparse
 import json
 from pathlib import Path
-from typing import List, Dict, Any
+from typing import List, Dict, Any, Tuple
 
 
-def load_results(path: Path) -> List[Dict[str, Any]]:
+def load_results(path: Path) -> Tuple[List[Dict[str, Any]], str]:
     with path.open("r", encoding="utf-8") as f:
-        return json.load(f)
+        data = json.load(f)
+    # 新格式为 {"metadata": ..., "results": [...]}，旧格式直接是结果数组且单位固定为 ms
+    if isinstance(data, dict):
+        return data["results"], data.get("metadata", {}).get("unit", "ms")
+    return data, "ms"
 
 
-def to_markdown(rows: List[Dict[str, Any]]) -> str:
-    headers = ["scenario", "ops", "throughput_ops", "p50_ms", "p95_ms", "p99_ms"]
+def to_markdown(rows: List[Dict[str, Any]], unit: str) -> str:
+    headers = ["scenario", "ops", "throughput_ops", f"p50_{unit}", f"p95_{unit}", f"p99_{unit}"]
     lines = ["| " + " | ".join(headers) + " |", "|" + "|".join([" --- "] * len(headers)) + "|"]
     for row in rows:
         lines.append(
@@ -27,9 +31,9 @@ def to_markdown(rows: List[Dict[str, Any]]) -> str:
                     str(row.get("scenario", "")),
                     str(row.get("ops", "")),
                     f"{row.get('throughput_ops', 0):.2f}",
-                    f"{row.get('p50_ms', 0):.3f}",
-                    f"{row.get('p95_ms', 0):.3f}",
-                    f"{row.get('p99_ms', 0):.3f}",
+                    f"{row.get(f'p50_{unit}', 0):.3f}",
+                    f"{row.get(f'p95_{unit}', 0):.3f}",
+                    f"{row.get(f'p99_{unit}', 0):.3f}",
                 ]
             )
             + " |"
@@ -46,8 +50,8 @@ def main() -> None:
     input_path = Path(args.input)
     output_path = Path(args.output)
 
-    rows = load_results(input_path)
-    md = to_markdown(rows)
+    rows, unit = load_results(input_path)
+    md = to_markdown(rows, unit)
     output_path.write_text(md, encoding="utf-8")
     print(f"Wrote markdown table to {output_path}")
 
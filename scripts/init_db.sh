@@ -2,17 +2,36 @@
 set -euo pipefail
 
 # 初始化数据库 schema。支持 mysql 或 postgres（默认 mysql）。
+# 第二个参数为主键策略：auto-increment（默认）、uuid、random-int；
+# 非自增策略下 id 由 load 生成，需要给 load 传相同的 --pk。
 
 DB_TYPE="${1:-mysql}"
+PK_STRATEGY="${2:-auto-increment}"
+
+# 按主键策略替换 schema 中的 id 列定义
+pk_filter() {
+  local db="$1"
+  case "$db:$PK_STRATEGY" in
+    *:auto-increment) cat ;;
+    mysql:uuid) sed 's/^  id BIGINT PRIMARY KEY AUTO_INCREMENT,/  id BINARY(16) PRIMARY KEY,/' ;;
+    mysql:random-int) sed 's/^  id BIGINT PRIMARY KEY AUTO_INCREMENT,/  id BIGINT PRIMARY KEY,/' ;;
+    postgres:uuid) sed 's/^  id BIGSERIAL PRIMARY KEY,/  id UUID PRIMARY KEY,/' ;;
+    postgres:random-int) sed 's/^  id BIGSERIAL PRIMARY KEY,/  id BIGINT PRIMARY KEY,/' ;;
+    *)
+      echo "未知主键策略: $PK_STRATEGY (支持 auto-increment|uuid|random-int)" >&2
+      exit 1
+      ;;
+  esac
+}
 
 case "$DB_TYPE" in
   mysql)
-    echo "初始化 MySQL schema..."
-    docker compose exec -T mysql mysql -uroot -proot < init/mysql/01_schema.sql
+    echo "初始化 MySQL schema（主键策略 $PK_STRATEGY）..."
+    pk_filter mysql < init/mysql/01_schema.sql | docker compose exec -T mysql mysql -uroot -proot
     ;;
   postgres|pg)
-    echo "初始化 Postgres schema..."
-    docker compose exec -T postgres psql -U perf -d perf -f /docker-entrypoint-initdb.d/01_schema.sql
+    echo "初始化 Postgres schema（主键策略 $PK_STRATEGY）..."
+    pk_filter postgres < init/postgres/01_schema.sql | docker compose exec -T postgres psql -v ON_ERROR_STOP=1 -U perf -d perf -f -
     ;;
   *)
     echo "未知 DB_TYPE: $DB_TYPE (支持 mysql|postgres)"
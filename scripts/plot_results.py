@@ -12,9 +12,26 @@ from pathlib import Path
 from typing import Dict, List, Any
 
 
+# 汇总表和图表统一用 ms；新格式可能按 metadata.unit 输出其他单位
+UNIT_TO_MS = {"ns": 1e-6, "us": 1e-3, "ms": 1.0}
+
+
 def load_bench(path: Path) -> List[Dict[str, Any]]:
     with path.open("r", encoding="utf-8") as f:
-        return json.load(f)
+        data = json.load(f)
+    # 新格式为 {"metadata": ..., "results": [...]}，旧格式直接是结果数组
+    if not isinstance(data, dict):
+        return data
+    unit = data.get("metadata", {}).get("unit", "ms")
+    rows = data["results"]
+    if unit != "ms":
+        factor = UNIT_TO_MS[unit]
+        for row in rows:
+            for name in ("avg", "min", "max", "stddev", "p50", "p95", "p99"):
+                key = f"{name}_{unit}"
+                if key in row:
+                    row[f"{name}_ms"] = row.pop(key) * factor
+    return rows
 
 
 def collect(results_dir: Path, db: str) -> Dict[str, Dict[str, Any]]:
@@ -12,6 +12,7 @@ RESULTS_DIR="results"
 BENCH_CONCURRENCY=8
 BENCH_SEED=42
 COLD_BENCH=false
+PK_STRATEGY="auto-increment"
 
 die() { echo "[ERROR] $*" >&2; exit 1; }
 
@@ -24,6 +25,7 @@ usage() {
   --bench-concurrency N    基准并发 worker 数，默认 8
   --batch-size N           装载批大小，默认 10000
   --indexes on|off         是否保留二级索引，默认 on
+  --pk STRATEGY            主键策略 auto-increment|uuid|random-int，默认 auto-increment
   --warmup-ops N           基准预热次数，默认 1000
   --sample-ops N           基准采样次数，默认 5000
   --bench-seed N           基准随机种子，默认 42
@@ -44,6 +46,7 @@ parse_args() {
       --bench-concurrency) BENCH_CONCURRENCY="$2"; shift 2;;
       --batch-size) BATCH_SIZE="$2"; shift 2;;
       --indexes) INDEXES="$2"; shift 2;;
+      --pk) PK_STRATEGY="$2"; shift 2;;
       --warmup-ops) WARMUP_OPS="$2"; shift 2;;
       --sample-ops) SAMPLE_OPS="$2"; shift 2;;
       --bench-seed) BENCH_SEED="$2"; shift 2;;
@@ -86,9 +89,9 @@ wait_postgres() {
 
 init_schema() {
   if [[ "$DB_TYPE" == "mysql" ]]; then
-    ./scripts/init_db.sh mysql
+    ./scripts/init_db.sh mysql "$PK_STRATEGY"
   else
-    ./scripts/init_db.sh postgres
+    ./scripts/init_db.sh postgres "$PK_STRATEGY"
   fi
 }
 
@@ -100,7 +103,7 @@ run_scale() {
   echo "=== 装载 ${scale} 行 (${DB_TYPE}) ==="
   cargo run --release -- --db "$DB_TYPE" load --scale "$scale" \
     --concurrency "$CONCURRENCY" --batch-size "$BATCH_SIZE" --indexes "$INDEXES" \
-    2>&1 | tee "$out_dir/load.log"
+    --pk "$PK_STRATEGY" 2>&1 | tee "$out_dir/load.log"
 
   if $COLD_BENCH; then
     echo "=== 冷缓存模式：重启 ${DB_TYPE} 容器，准备基准 ==="
@@ -1,372 +1,4213 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Duration as ChronoDuration, NaiveDateTime, Timelike, Utc};
+use futures_util::future::try_join_all;
+use futures_util::{pin_mut, TryStreamExt};
 use rand::rngs::StdRng;
 use rand::Rng;
 use rand::SeedableRng;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tokio::task::JoinSet;
 use tokio::time::Instant;
+use tokio_postgres::types::{FromSql, ToSql, Type as PgType};
 use tokio_postgres::Client as PgClient;
+use tracing::Instrument;
+use uuid::Uuid;
 use mysql_async::prelude::Queryable;
+use mysql_async::{Params as MyParams, Value as MyValue};
 
-use crate::config::{DbConfig, DbKind};
-use crate::load::fetch_mysql_max_id;
-use crate::load::fetch_postgres_max_id;
+use crate::config::{
+    AmountRules, ConcurrencyRamp, ConnectOptions, DbConfig, DbKind, Distribution, Isolation, LatencyUnit, MixSpec, OutputFormat,
+    PayloadModel, PayloadType, Percentiles, PkStrategy, Pooler, STATUS_VALUES,
+};
+use crate::connect;
+use crate::generator::{EventGenerator, EventRow, GeneratorConfig, TimeModel};
+use crate::load::{fetch_mysql_max_id, fetch_mysql_max_user_id};
+use crate::load::{fetch_postgres_max_id, fetch_postgres_max_user_id};
+use crate::load::{inline_mysql_params, mysql_amount, mysql_datetime};
+use crate::metrics::{percentile_name, undersampled_percentiles, LatencyHistogram, LatencySummary};
+use crate::pause::PauseGate;
+use crate::plan::{self, PlanBaseline, ScenarioPlans};
+use crate::progress_bar;
+use crate::resources::{ResourceSampler, ResourceSummary};
+use crate::retry::{classify_error, is_connection_error, is_transient_error, ErrorKind, RetryPolicy};
+use crate::schema;
+use crate::server::{self, ServerCounters};
+use crate::shutdown;
+use crate::timeseries::{self, SeriesLabel, SeriesRecorder};
+use crate::userdist::{UserDistSpec, UserSampler, DEFAULT_USERS};
 
 pub struct BenchConfig {
     pub warmup_ops: u64,
     pub sample_ops: u64,
+    /// 每个场景按时长采样，覆盖 sample_ops；计时从各 worker 预热结束开始，暂停时间不计入
+    pub duration: Option<Duration>,
     pub concurrency: usize,
+    /// 每个场景的目标总吞吐（ops/s），所有 worker 共享；不指定则不限速
+    pub target_qps: Option<f64>,
     pub output: Option<PathBuf>,
     pub seed: u64,
+    pub compare_prepared: bool,
+    /// 每次都把 SQL 文本交给服务端解析/规划，不在连接上预先 prepare
+    pub no_prepare: bool,
+    pub resource_stats: bool,
+    /// 每个场景前后各查一次服务端计数器，结果中附上差值
+    pub server_metrics: bool,
+    pub ignore_warmup_errors: bool,
+    /// 结果中附带预热阶段的延迟分布
+    pub report_warmup: bool,
+    /// 执行计划指纹基线文件：不存在时写入，存在时比对
+    pub plan_baseline: Option<PathBuf>,
+    pub abort_on_plan_regression: bool,
+    /// 每个场景运行前 EXPLAIN 一次，执行计划附在结果里
+    pub explain: bool,
+    /// user_lookup 等场景的 user_id 分布；未指定时沿用装载记录在表注释里的分布，没有记录则均匀
+    pub lookup_dist: Option<UserDistSpec>,
+    /// user_id 采样范围 1..=users；未指定时按表内最大 user_id 推断
+    pub users: Option<u64>,
+    /// 报告中的延迟单位
+    pub unit: LatencyUnit,
+    /// 报告中输出的分位点
+    pub percentiles: Percentiles,
+    /// 允许运行会修改数据的场景（hot_update 等）
+    pub allow_writes: bool,
+    /// 允许运行 delete_pk：会永久删除随机行，需同时开启 allow_writes
+    pub allow_deletes: bool,
+    /// hot_update 争用的热点行数：只更新 id 最小的这些行
+    pub hot_rows: u64,
+    /// 数据中 created_at 覆盖的天数，range_small/range_large 的时间窗口与 time_list 的取值范围按它缩放
+    pub time_span_days: u64,
+    /// payload 列的类型：JSON 列时写场景生成 JSON 文档，并加入查询 JSON 字段的 json_event 场景
+    pub payload_type: PayloadType,
+    /// 按表大小的比例换算 sample_ops，并让 pk_hit 不重复地探测这部分主键
+    pub sample_fraction: Option<f64>,
+    /// 工作连接上的服务端语句超时（毫秒）：Postgres statement_timeout，MySQL max_execution_time
+    pub statement_timeout_ms: Option<u64>,
+    /// 写场景的每次操作包在一个该隔离级别的显式事务里（BEGIN ... COMMIT）；不指定时为自动提交
+    pub isolation: Option<Isolation>,
+    /// stdout 与 --output 的格式
+    pub format: OutputFormat,
+    /// 另以 Prometheus 文本格式写入该文件（node_exporter textfile collector 可直接读取）
+    pub prometheus: Option<PathBuf>,
+    /// 每秒追加一行 JSONL：场景、距开始的秒数、已完成的采样次数与这一秒的吞吐
+    pub timeseries: Option<PathBuf>,
+    /// 另把同样的指标推送到 Pushgateway
+    pub pushgateway: Option<String>,
+    /// 每次往返发送的语句条数：MySQL 拼成多语句，Postgres 走管线
+    pub queries_per_roundtrip: usize,
+    /// 用户给本次运行打的标签，原样写入报告
+    pub tag: Option<String>,
+    /// 采样期间连接断开后的重连次数上限，0 表示不重连（连接断开即失败）
+    pub reconnect_attempts: u32,
+    /// 首次重连前的等待，之后每次翻倍
+    pub reconnect_backoff_ms: u64,
+    /// 采样期间死锁、序列化失败等瞬时错误的重试策略
+    pub retry: RetryPolicy,
+    /// 采样期间操作失败时计数并继续，而不是让整个运行失败
+    pub continue_on_error: bool,
+    /// 只读副本连接串；与 allow_writes 同时指定时运行 replica_lag
+    pub read_url: Option<String>,
+    /// replica_lag 单次等待副本出现新行的上限
+    pub replica_lag_timeout_ms: u64,
+    /// --scenarios 文件中的自定义场景，指定时替换内置场景
+    pub scenarios: Option<Vec<Scenario>>,
+    /// --scenario 指定的场景名，非空时只运行这些场景
+    pub include: Vec<String>,
+    /// --exclude 指定的场景名，不运行
+    pub exclude: Vec<String>,
+    /// 混合负载：每次操作按权重随机选一个场景，整体作为一个结果输出
+    pub mix: Option<MixSpec>,
+    /// 每个场景依次在这些并发数下各跑一轮，每级输出一条结果；替代 concurrency
+    pub ramp: Option<ConcurrencyRamp>,
+    /// 每个场景（每级并发、每种语句模式）重复运行的次数，第 i 次（从 0 计）的 seed 为 seed + i（溢出时回绕）
+    pub repeat: usize,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// 未指定 --sample-ops 时每个场景的采样次数
+pub const DEFAULT_SAMPLE_OPS: u64 = 10_000;
+
+// IN 列表场景每次携带的值个数
+const IN_LIST_SIZE: usize = 10;
+// insert 场景写入行的标记：生成器的 category 取值为 0..=5000，结束后按 id 范围与它清理
+const INSERT_MARKER_CATEGORY: i32 = -1;
+// 与 load 默认的 --payload-size 一致
+const INSERT_PAYLOAD_SIZE: usize = 200;
+
+/// 自定义场景文件中写作 `param = "pk_hit"`，列表参数写作 `param = { pk_list = 10 }`
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
 enum ParamKind {
+    #[default]
     None,
     PkHit,
+    /// 热点集合内的主键，所有 worker 反复命中同一小批行
+    HotPk,
     UserHit,
+    /// 生成器取值范围内的随机 status（0..STATUS_VALUES），命中 idx_status
+    Status,
+    /// 生成器取值范围内的随机 category（0..=5000），category 上没有索引
+    Category,
+    /// 一组随机主键：Postgres 绑定为单个 int8[]（`= ANY($1)`），MySQL 展开为定长 IN 列表
+    PkList(usize),
+    /// 一组随机时间点（秒精度）：Postgres 绑定为单个 timestamp[]，MySQL 同样展开为 IN 列表
+    TimeList(usize),
+    /// 生成器产生的一整行（除 id 外的 6 列）；category 固定为 INSERT_MARKER_CATEGORY，场景结束后清理。
+    /// 自定义场景不支持
+    #[serde(skip)]
+    Insert,
+    /// 新的 amount 与一个随机主键，依次绑定为两个参数；自定义场景不支持
+    #[serde(skip)]
+    AmountPk,
+    /// 主键加一整行（共 7 列）：一半取已有主键走更新分支，一半取表外的新主键走插入分支；
+    /// 新行的 category 同样为 INSERT_MARKER_CATEGORY，场景结束后清理。自定义场景不支持
+    #[serde(skip)]
+    Upsert,
 }
 
+/// 按 ParamKind 采样得到的一次执行参数。
+///
+/// 数组参数在 Postgres 侧依赖 tokio-postgres 的 `ToSql` 实现：`Vec<i64>` 对应 `int8[]`，
+/// `Vec<NaiveDateTime>` 对应 `timestamp[]`（需要 `with-chrono-0_4` 特性）。整个数组作为一个参数绑定，
+/// 语句文本与值个数无关，预处理语句可以复用；元素类型必须与列类型一致，否则服务端会报类型不匹配。
 #[derive(Debug, Clone)]
-struct Scenario {
-    name: &'static str,
-    mysql_sql: &'static str,
-    postgres_sql: &'static str,
-    param: ParamKind,
+enum ParamValue {
+    None,
+    Int(i64),
+    IntList(Vec<i64>),
+    TimeList(Vec<NaiveDateTime>),
+    Row(Box<EventRow>),
+    AmountPk(f64, i64),
+    /// 主键为空时由数据库分配（MySQL 自增，Postgres 取序列）
+    IdRow(Option<i64>, Box<EventRow>),
 }
 
-#[derive(Serialize)]
-struct BenchResult {
-    scenario: String,
-    ops: u64,
-    throughput_ops: f64,
-    avg_ms: f64,
-    p50_ms: f64,
-    p95_ms: f64,
-    p99_ms: f64,
+/// 场景读取结果的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Fetch {
+    /// 至多一行，只取第一行
+    First,
+    /// 流式读完全部行，同时记录首行到达时间
+    All,
+    /// 写语句，不返回结果行
+    None,
 }
 
-#[derive(Debug)]
-struct Stats {
-    avg: f64,
-    p50: f64,
-    p95: f64,
-    p99: f64,
+/// 采样参数的取值范围：主键上界取自表内 max(id)，user_id 按 --user-dist 采样
+struct ParamSpace {
+    max_id: u64,
+    hot_rows: u64,
+    users: UserSampler,
+    /// time_list 与 insert 生成的 created_at 落在最近这么多秒内
+    time_span_secs: i64,
+    /// 开启 --sample-fraction 时 pk_hit 按它不重复地遍历主键
+    coverage: Option<PkCoverage>,
+    /// insert 场景的生成器参数，与 load 的默认参数一致
+    rows: GeneratorConfig,
 }
 
-pub async fn run_bench(db: DbConfig, cfg: BenchConfig) -> Result<()> {
-    let results = match db.kind {
-        DbKind::Mysql => bench_mysql(&db.url, &cfg).await?,
-        DbKind::Postgres => bench_postgres(&db.url, &cfg).await?,
-    };
-
-    let json = serde_json::to_string_pretty(&results)?;
-    println!("{}", json);
+/// 把第 g 次操作映射到 1 + (offset + g * step) mod max_id。step 与 max_id 互质，
+/// 所以前 max_id 次操作取到的主键两两不同，且均匀散布在整张表上
+struct PkCoverage {
+    max_id: u64,
+    step: u64,
+    offset: u64,
+    next: AtomicU64,
+}
 
-    if let Some(path) = &cfg.output {
-        tokio::fs::write(path, json).await?;
-        tracing::info!("基准结果已写入 {:?}", path);
+impl PkCoverage {
+    fn new(max_id: u64, seed: u64) -> Self {
+        // 从黄金分割比例附近找一个与 max_id 互质的步长，相邻操作的主键相距较远
+        let mut step = ((max_id as f64 * 0.618_033_988_75) as u64).max(1);
+        while gcd(step, max_id) != 1 {
+            step += 1;
+        }
+        Self {
+            max_id,
+            step,
+            offset: seed % max_id,
+            next: AtomicU64::new(0),
+        }
     }
 
-    Ok(())
-}
+    fn next_id(&self) -> i64 {
+        let g = self.next.fetch_add(1, Ordering::Relaxed) % self.max_id;
+        let pos = (self.offset as u128 + g as u128 * self.step as u128) % self.max_id as u128;
+        pos as i64 + 1
+    }
 
-async fn bench_mysql(url: &str, cfg: &BenchConfig) -> Result<Vec<BenchResult>> {
-    let pool = mysql_async::Pool::new(mysql_async::Opts::from_url(url)?);
-    let max_id = fetch_mysql_max_id(&pool).await?;
-    if max_id == 0 {
-        return Err(anyhow!("events 表为空，无法基准测试"));
+    /// 每个场景开始时从头遍历
+    fn reset(&self) {
+        self.next.store(0, Ordering::Relaxed);
     }
+}
 
-    let scenarios = scenarios();
-    let mut results = Vec::with_capacity(scenarios.len());
-    for sc in scenarios {
-        let res = run_mysql_scenario(&pool, &sc, cfg, max_id).await?;
-        results.push(res);
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
     }
-    pool.disconnect().await?;
-    Ok(results)
+    a
 }
 
-async fn bench_postgres(url: &str, cfg: &BenchConfig) -> Result<Vec<BenchResult>> {
-    let (client, connection) = tokio_postgres::connect(url, tokio_postgres::NoTls).await?;
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            tracing::error!("Postgres 连接任务出错: {}", e);
+/// user_id 的采样范围：优先用 --users；未指定时表内最大 user_id 小于默认基数则收窄到它，
+/// 避免大量查询落在不存在的用户上
+fn bench_users(users: Option<u64>, max_user_id: u64) -> u64 {
+    if let Some(users) = users {
+        if max_user_id > users {
+            tracing::warn!("表内最大 user_id 为 {}，大于 --users {}，超出部分的用户不会被查询", max_user_id, users);
         }
-    });
-    let max_id = fetch_postgres_max_id(&client).await?;
-    if max_id == 0 {
-        return Err(anyhow!("events 表为空，无法基准测试"));
+        return users;
     }
+    if (1..DEFAULT_USERS).contains(&max_user_id) {
+        tracing::info!(
+            "表内最大 user_id 为 {}，小于默认基数 {}，user_id 采样范围收窄为 1..={}",
+            max_user_id,
+            DEFAULT_USERS,
+            max_user_id
+        );
+        max_user_id
+    } else {
+        DEFAULT_USERS
+    }
+}
 
-    let scenarios = scenarios();
-    let mut results = Vec::with_capacity(scenarios.len());
-    for sc in scenarios {
-        let res = run_postgres_scenario(url, &sc, cfg, max_id).await?;
-        results.push(res);
+/// --sample-fraction 对应的采样次数，至少 1 次
+fn fraction_ops(fraction: f64, max_id: u64) -> u64 {
+    ((max_id as f64 * fraction).ceil() as u64).clamp(1, max_id)
+}
+
+impl ParamSpace {
+    /// max_id 必须至少为 1（调用方已排除空表）；users 为 user_id 的采样范围 1..=users
+    fn new(
+        max_id: u64,
+        users: u64,
+        hot_rows: u64,
+        time_span_days: u64,
+        payload_type: PayloadType,
+        user_dist: &UserDistSpec,
+    ) -> Result<Self> {
+        let time_span_secs = time_span_days as i64 * 24 * 3600;
+        Ok(Self {
+            max_id,
+            hot_rows: hot_rows.min(max_id).max(1),
+            users: UserSampler::new(user_dist, users)?,
+            time_span_secs,
+            coverage: None,
+            rows: GeneratorConfig {
+                user_dist: user_dist.clone(),
+                users,
+                payload_size: INSERT_PAYLOAD_SIZE,
+                payload_model: match payload_type {
+                    PayloadType::Text => PayloadModel::Random,
+                    PayloadType::Json => PayloadModel::Json,
+                },
+                payload_seeded_by_user: false,
+                payload_templates: None,
+                payload_suffix: 0,
+                payload_corpus: None,
+                pk: PkStrategy::AutoIncrement,
+                null_rate: 0.0,
+                amount_rules: AmountRules::default(),
+                time: TimeModel::Random { span_secs: time_span_secs, half_life_secs: None },
+                user_histogram: None,
+            },
+        })
     }
-    Ok(results)
 }
 
-fn scenarios() -> Vec<Scenario> {
-    vec![
-        Scenario {
-            name: "pk_hit",
-            mysql_sql: "SELECT id FROM events WHERE id = ?",
-            postgres_sql: "SELECT id FROM events WHERE id = $1",
-            param: ParamKind::PkHit,
-        },
-        Scenario {
-            name: "user_lookup",
-            mysql_sql: "SELECT id FROM events WHERE user_id = ? ORDER BY created_at DESC LIMIT 1",
-            postgres_sql: "SELECT id FROM events WHERE user_id = $1 ORDER BY created_at DESC LIMIT 1",
-            param: ParamKind::UserHit,
-        },
-        Scenario {
-            name: "range_small",
-            mysql_sql: "SELECT id FROM events WHERE created_at BETWEEN DATE_SUB(NOW(), INTERVAL 1 DAY) AND NOW() ORDER BY created_at DESC LIMIT 50",
-            postgres_sql: "SELECT id FROM events WHERE created_at BETWEEN (NOW() - INTERVAL '1 day') AND NOW() ORDER BY created_at DESC LIMIT 50",
-            param: ParamKind::None,
-        },
-        Scenario {
-            name: "range_large",
-            mysql_sql: "SELECT id FROM events WHERE created_at BETWEEN DATE_SUB(NOW(), INTERVAL 30 DAY) AND NOW() ORDER BY created_at DESC LIMIT 200",
-            postgres_sql: "SELECT id FROM events WHERE created_at BETWEEN (NOW() - INTERVAL '30 day') AND NOW() ORDER BY created_at DESC LIMIT 200",
-            param: ParamKind::None,
-        },
-        Scenario {
-            name: "order_page",
-            mysql_sql: "SELECT id FROM events ORDER BY created_at DESC LIMIT 50 OFFSET 100",
-            postgres_sql: "SELECT id FROM events ORDER BY created_at DESC LIMIT 50 OFFSET 100",
-            param: ParamKind::None,
-        },
-    ]
+/// 一个基准场景；--scenarios 文件中的 `[[scenario]]` 条目按同样的字段反序列化
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    name: String,
+    mysql_sql: String,
+    postgres_sql: String,
+    #[serde(default)]
+    param: ParamKind,
+    /// 未指定时读完全部结果行，对任意 SELECT 都成立
+    #[serde(default = "default_fetch")]
+    fetch: Fetch,
+    /// 写场景每次操作影响的行数，用于换算 rows/s；读场景为 None
+    rows_per_op: Option<u64>,
 }
 
-async fn run_mysql_scenario(
-    pool: &mysql_async::Pool,
-    sc: &Scenario,
-    cfg: &BenchConfig,
-    max_id: u64,
-) -> Result<BenchResult> {
-    let workers = cfg.concurrency.max(1) as u64;
-    let warm_base = cfg.warmup_ops / workers;
-    let warm_rem = cfg.warmup_ops % workers;
-    let sample_base = cfg.sample_ops / workers;
-    let sample_rem = cfg.sample_ops % workers;
+impl Scenario {
+    fn is_write(&self) -> bool {
+        self.fetch == Fetch::None
+    }
+}
 
-    let mut tasks = JoinSet::new();
-    let mut durations: Vec<f64> = Vec::with_capacity(cfg.sample_ops as usize);
-    let durations_shared = Arc::new(tokio::sync::Mutex::new(Vec::with_capacity(
-        cfg.sample_ops as usize,
-    )));
-    let progress = Arc::new(AtomicU64::new(0));
+impl BenchConfig {
+    /// 每个场景依次运行的并发数：--ramp 的各级，否则只有 --concurrency
+    fn concurrency_levels(&self) -> Vec<usize> {
+        match &self.ramp {
+            Some(ramp) => ramp.0.clone(),
+            None => vec![self.concurrency],
+        }
+    }
 
-    let scenario_start = Instant::now();
-    for worker_id in 0..workers {
-        let warm = warm_base + if worker_id < warm_rem { 1 } else { 0 };
-        let sample = sample_base + if worker_id < sample_rem { 1 } else { 0 };
-        let pool = pool.clone();
-        let sc = sc.clone();
-        let max_id = max_id;
-        let durations_shared = durations_shared.clone();
-        let progress = progress.clone();
-        let seed = cfg.seed;
-        tasks.spawn(async move {
-            let mut conn = pool.get_conn().await?;
-            let mut rng = StdRng::seed_from_u64(seed + worker_id);
-            // warmup
-            for _ in 0..warm {
-                exec_mysql(&mut conn, &sc, &mut rng, max_id).await?;
-            }
+    /// 不指定 --compare-prepared 时的语句模式
+    fn base_mode(&self) -> StatementMode {
+        if self.no_prepare {
+            StatementMode::Text
+        } else {
+            StatementMode::Default
+        }
+    }
 
-            for _ in 0..sample {
-                let start = Instant::now();
-                exec_mysql(&mut conn, &sc, &mut rng, max_id).await?;
-                let elapsed = start.elapsed().as_secs_f64() * 1000.0;
-                let mut guard = durations_shared.lock().await;
-                guard.push(elapsed);
-                let done = progress.fetch_add(1, Ordering::Relaxed) + 1;
-                if done % 500 == 0 {
-                    let rps = done as f64 / scenario_start.elapsed().as_secs_f64().max(0.001);
-                    tracing::info!("scenario={} mysql 已完成 {} 次采样, {:.2} ops/s", sc.name, done, rps);
-                }
+    /// 一个场景在某种语句模式下依次运行的各轮：并发数递增，同一并发数连续重复 --repeat 次
+    fn rounds(&self, mode: StatementMode) -> Vec<Round> {
+        let mut rounds = Vec::new();
+        for concurrency in self.concurrency_levels() {
+            for i in 0..self.repeat {
+                rounds.push(Round {
+                    mode,
+                    concurrency,
+                    seed: self.seed.wrapping_add(i as u64),
+                    repeat: (self.repeat > 1).then_some(i + 1),
+                });
             }
+        }
+        rounds
+    }
 
-            conn.disconnect().await?;
-            Ok::<(), anyhow::Error>(())
-        });
+    /// 检查 clap 无法单独表达的参数约束
+    pub fn validate(&self) -> Result<()> {
+        if self.concurrency == 0 {
+            bail!("--concurrency 必须大于 0");
+        }
+        if self.sample_ops == 0 {
+            bail!("--sample-ops 必须大于 0");
+        }
+        if let Some(q) = self.target_qps
+            && !(q.is_finite() && q > 0.0)
+        {
+            bail!("--target-qps 必须大于 0，得到 {}", q);
+        }
+        if self.duration.is_some_and(|d| d.is_zero()) {
+            bail!("--duration 必须大于 0");
+        }
+        if self.abort_on_plan_regression && self.plan_baseline.is_none() {
+            bail!("--abort-on-plan-regression 需要同时指定 --plan-baseline");
+        }
+        if let Some(f) = self.sample_fraction
+            && !(f > 0.0 && f <= 1.0)
+        {
+            bail!("--sample-fraction 必须在 (0, 1] 之间，得到 {}", f);
+        }
+        if self.statement_timeout_ms == Some(0) {
+            bail!("--statement-timeout 必须大于 0（不指定即沿用服务端默认值）");
+        }
+        if self.queries_per_roundtrip == 0 {
+            bail!("--queries-per-roundtrip 必须大于 0");
+        }
+        if self.queries_per_roundtrip > 1 && self.compare_prepared {
+            bail!("--queries-per-roundtrip 大于 1 时不支持 --compare-prepared");
+        }
+        if self.hot_rows == 0 {
+            bail!("--hot-rows 必须大于 0");
+        }
+        if self.allow_deletes && !self.allow_writes {
+            bail!("--allow-deletes 需要同时指定 --allow-writes");
+        }
+        if self.isolation.is_some() && !self.allow_writes && self.scenarios.is_none() {
+            bail!("--isolation 只作用于写场景，需要同时指定 --allow-writes 或用 --scenarios 提供写场景");
+        }
+        if self.read_url.is_some() && !self.allow_writes {
+            bail!("--read-url 用于 replica_lag 场景，会向主库写入数据，需要同时指定 --allow-writes");
+        }
+        if self.replica_lag_timeout_ms == 0 {
+            bail!("--replica-lag-timeout-ms 必须大于 0");
+        }
+        if self.reconnect_attempts > 0 && self.reconnect_backoff_ms == 0 {
+            bail!("--reconnect-backoff-ms 必须大于 0");
+        }
+        self.retry.validate()?;
+        check_unique_names(&runnable_names(self))?;
+        check_scenario_filter(self)?;
+        if let Some(mix) = &self.mix {
+            if self.compare_prepared {
+                bail!("--mix 不支持 --compare-prepared");
+            }
+            if self.queries_per_roundtrip > 1 {
+                bail!("--mix 不支持 --queries-per-roundtrip");
+            }
+            resolve_mix(mix, bench_scenarios(self))?;
+        }
+        if let Some(spec) = &self.lookup_dist {
+            UserSampler::new(spec, self.users.unwrap_or(DEFAULT_USERS))?;
+        }
+        Ok(())
     }
+}
 
-    while let Some(res) = tasks.join_next().await {
-        res??;
+/// 场景语句的执行方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum StatementMode {
+    /// 每个连接 prepare 一次后复用；经事务级连接池时不留服务端语句，改走单次往返的文本/typed 执行
+    Default,
+    /// --no-prepare：每次都传 SQL 文本，由服务端重新解析/规划（MySQL 走 text 协议、内联参数）
+    Text,
+    /// 每个连接显式 prepare 一次，之后复用同一语句
+    Prepared,
+    /// 每次在 SQL 末尾追加唯一注释，绕过语句缓存，强制服务端重新解析/规划
+    Reparse,
+}
+
+impl StatementMode {
+    /// 与序列化后的名字一致
+    fn as_str(self) -> &'static str {
+        match self {
+            StatementMode::Default => "default",
+            StatementMode::Text => "text",
+            StatementMode::Prepared => "prepared",
+            StatementMode::Reparse => "reparse",
+        }
     }
+}
 
-    let mut guard = durations_shared.lock().await;
-    durations.append(&mut guard);
-    let stats = calc_stats(&mut durations);
-    let wall = scenario_start.elapsed().as_secs_f64();
-    let throughput = cfg.sample_ops as f64 / wall.max(0.001);
+/// 写到 stdout / --output 的完整报告
+#[derive(Serialize)]
+struct BenchReport {
+    metadata: BenchMetadata,
+    results: Vec<BenchResult>,
+    /// --repeat 大于 1 时每组重复结果的汇总；各次的完整结果仍在 results 中
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    repeats: Vec<RepeatSummary>,
+}
 
-    Ok(BenchResult {
-        scenario: sc.name.to_string(),
-        ops: cfg.sample_ops,
-        throughput_ops: throughput,
-        avg_ms: stats.avg,
-        p50_ms: stats.p50,
-        p95_ms: stats.p95,
-        p99_ms: stats.p99,
-    })
+/// 同一场景、语句模式、并发数的多次重复
+#[derive(Serialize)]
+struct RepeatSummary {
+    scenario: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    statement_mode: Option<StatementMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    concurrency: Option<usize>,
+    /// 实际完成的重复次数，中断时可能少于 --repeat
+    repeats: usize,
+    throughput_ops: Spread,
+    /// avg 与各分位点，键与结果中的延迟字段相同，如 p99_ms
+    #[serde(flatten)]
+    latency: SpreadMap,
 }
 
-async fn run_postgres_scenario(
-    url: &str,
-    sc: &Scenario,
-    cfg: &BenchConfig,
-    max_id: u64,
-) -> Result<BenchResult> {
-    let workers = cfg.concurrency.max(1) as u64;
-    let warm_base = cfg.warmup_ops / workers;
-    let warm_rem = cfg.warmup_ops % workers;
-    let sample_base = cfg.sample_ops / workers;
-    let sample_rem = cfg.sample_ops % workers;
+/// 各次重复的取值及其均值、样本标准差
+#[derive(Serialize)]
+struct Spread {
+    mean: f64,
+    stddev: f64,
+    min: f64,
+    max: f64,
+    values: Vec<f64>,
+}
 
-    let mut tasks = JoinSet::new();
-    let mut durations: Vec<f64> = Vec::with_capacity(cfg.sample_ops as usize);
-    let durations_shared = Arc::new(tokio::sync::Mutex::new(Vec::with_capacity(
-        cfg.sample_ops as usize,
-    )));
-    let progress = Arc::new(AtomicU64::new(0));
-    let scenario_start = Instant::now();
+impl Spread {
+    fn new(values: Vec<f64>) -> Self {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = if values.len() > 1 {
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+        Self {
+            mean,
+            stddev: variance.sqrt(),
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            values,
+        }
+    }
 
-    for worker_id in 0..workers {
-        let warm = warm_base + if worker_id < warm_rem { 1 } else { 0 };
-        let sample = sample_base + if worker_id < sample_rem { 1 } else { 0 };
-        let url = url.to_string();
-        let sc = sc.clone();
-        let durations_shared = durations_shared.clone();
-        let progress = progress.clone();
-        let seed = cfg.seed;
-        tasks.spawn(async move {
-            let (client, connection) = tokio_postgres::connect(&url, tokio_postgres::NoTls).await?;
-            tokio::spawn(async move {
-                if let Err(e) = connection.await {
-                    tracing::error!("Postgres worker 连接任务出错: {}", e);
-                }
-            });
-            let mut rng = StdRng::seed_from_u64(seed + worker_id);
+    /// markdown 中的 `均值 ± 标准差`
+    fn display(&self, precision: usize) -> String {
+        format!("{:.*} ± {:.*}", precision, self.mean, precision, self.stddev)
+    }
+}
 
-            for _ in 0..warm {
-                exec_postgres(&client, &sc, &mut rng, max_id).await?;
-            }
+struct SpreadMap(Vec<(String, Spread)>);
 
-            for _ in 0..sample {
-                let start = Instant::now();
-                exec_postgres(&client, &sc, &mut rng, max_id).await?;
-                let elapsed = start.elapsed().as_secs_f64() * 1000.0;
-                let mut guard = durations_shared.lock().await;
-                guard.push(elapsed);
-                let done = progress.fetch_add(1, Ordering::Relaxed) + 1;
-                if done % 500 == 0 {
-                    let rps = done as f64 / scenario_start.elapsed().as_secs_f64().max(0.001);
-                    tracing::info!("scenario={} postgres 已完成 {} 次采样, {:.2} ops/s", sc.name, done, rps);
-                }
+impl Serialize for SpreadMap {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, spread) in &self.0 {
+            map.serialize_entry(name, spread)?;
+        }
+        map.end()
+    }
+}
+
+/// 把带 repeat 的结果按 (场景, 语句模式, 并发数) 分组汇总，保持结果中的先后顺序
+fn summarize_repeats(results: &[BenchResult]) -> Vec<RepeatSummary> {
+    let mut groups: Vec<Vec<&BenchResult>> = Vec::new();
+    for r in results.iter().filter(|r| r.repeat.is_some()) {
+        let same = |g: &&mut Vec<&BenchResult>| {
+            let first = g[0];
+            first.scenario == r.scenario && first.statement_mode == r.statement_mode && first.concurrency == r.concurrency
+        };
+        match groups.iter_mut().find(same) {
+            Some(group) => group.push(r),
+            None => groups.push(vec![r]),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|group| {
+            let first = group[0];
+            let unit = first.latency.unit;
+            let mut latency = vec![(
+                format!("avg_{}", unit.suffix()),
+                Spread::new(group.iter().map(|r| unit.convert_nanos(r.latency.stats.avg)).collect()),
+            )];
+            for (i, &(p, _)) in first.latency.stats.percentiles.iter().enumerate() {
+                let values = group
+                    .iter()
+                    .map(|r| unit.convert_nanos(r.latency.stats.percentiles[i].1))
+                    .collect();
+                latency.push((format!("{}_{}", percentile_name(p), unit.suffix()), Spread::new(values)));
+            }
+            RepeatSummary {
+                scenario: first.scenario.clone(),
+                statement_mode: first.statement_mode,
+                concurrency: first.concurrency,
+                repeats: group.len(),
+                throughput_ops: Spread::new(group.iter().map(|r| r.throughput_ops).collect()),
+                latency: SpreadMap(latency),
             }
+        })
+        .collect()
+}
 
-            Ok::<(), anyhow::Error>(())
-        });
+#[derive(Serialize)]
+struct BenchMetadata {
+    /// 每次运行自动生成的 UUID，用于区分/关联结果文件
+    run_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    db: DbKind,
+    concurrency: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_qps: Option<f64>,
+    warmup_ops: u64,
+    sample_ops: u64,
+    /// 按时长采样时每个场景的采样时长，此时 sample_ops 不生效
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_s: Option<f64>,
+    seed: u64,
+    queries_per_roundtrip: usize,
+    /// --no-prepare：语句没有预先 prepare，延迟包含每次的解析/规划
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    no_prepare: bool,
+    /// --isolation：写场景每次操作所在事务的隔离级别
+    #[serde(skip_serializing_if = "Option::is_none")]
+    isolation: Option<Isolation>,
+    user_dist: String,
+    /// --mix 的配置，结果中只有一个 mix 场景
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mix: Option<String>,
+    /// --ramp 的并发数序列；此时 concurrency 不生效，以各结果的 concurrency 为准
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ramp: Option<String>,
+    /// --repeat 大于 1 时的重复次数，汇总见 repeats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repeat: Option<usize>,
+    unit: LatencyUnit,
+    /// 样本数不足、数值不可靠的分位点
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    low_confidence_percentiles: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resources: Option<ResourceSummary>,
+    /// 被 Ctrl-C 中断：results 只含中断前已有样本的场景，最后一个场景的样本数少于计划
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    interrupted: bool,
+}
+
+#[derive(Serialize)]
+struct BenchResult {
+    scenario: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    statement_mode: Option<StatementMode>,
+    /// --ramp 时这一轮的并发数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    concurrency: Option<usize>,
+    /// --repeat 大于 1 时为第几次重复（从 1 计）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repeat: Option<usize>,
+    ops: u64,
+    throughput_ops: f64,
+    /// 写场景的 rows/s，便于与装载路径对比；单行写入时与 throughput_ops 相等
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rows_per_sec: Option<f64>,
+    /// 多行场景每秒读取的结果数据量（按列值的编码长度估算，不含协议开销）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes_per_sec: Option<f64>,
+    #[serde(flatten)]
+    latency: Latency,
+    /// 多行场景收到第一行的延迟；外层的 avg/p50/... 是全部行读完的延迟
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_row: Option<Latency>,
+    /// --target-qps 时不含排队的执行时间；外层延迟从计划发出时刻算起
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service: Option<Latency>,
+    /// --queries-per-roundtrip 大于 1 时摊到每条语句的延迟；外层为整个往返的延迟
+    #[serde(skip_serializing_if = "Option::is_none")]
+    per_query: Option<Latency>,
+    /// --report-warmup 时预热阶段的延迟；预热的操作不计入 ops 与外层延迟
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warmup: Option<Latency>,
+    /// replica_lag 中等到超时仍未在副本上看到的写入次数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeouts: Option<u64>,
+    /// 开启重连时才输出
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    connection_loss: Option<ConnectionLoss>,
+    /// --max-retries 大于 0 时输出：瞬时错误重试的总次数，重试与退避耗时计入该次操作的延迟
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retries: Option<u64>,
+    /// --continue-on-error 时输出：采样期间失败的操作数，不计入 ops 与延迟
+    #[serde(skip_serializing_if = "Option::is_none")]
+    errors: Option<u64>,
+    /// errors 中因死锁、序列化失败被服务端回滚的次数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rollbacks: Option<u64>,
+    /// errors / (ops + errors)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_rate: Option<f64>,
+    /// --mix 时各场景的次数与延迟；外层为全部操作合在一起的延迟
+    #[serde(skip_serializing_if = "Option::is_none")]
+    breakdown: Option<Vec<MixPart>>,
+    /// --explain 时用代表性参数得到的 EXPLAIN JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plan: Option<serde_json::Value>,
+    /// --server-metrics 时这一轮（含预热）前后服务端计数器之差
+    #[serde(skip_serializing_if = "Option::is_none")]
+    server: Option<ServerCounters>,
+}
+
+#[derive(Serialize)]
+struct MixPart {
+    scenario: String,
+    /// 配置的权重占比
+    weight: f64,
+    ops: u64,
+    #[serde(flatten)]
+    latency: Latency,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plan: Option<serde_json::Value>,
+}
+
+/// 采样期间因连接断开丢失的操作与重连次数；丢失的操作不计入 ops 与延迟
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+struct ConnectionLoss {
+    lost_ops: u64,
+    reconnects: u64,
+}
+
+/// 按 --unit 输出的延迟字段：avg/min/max/stddev_<unit> 与 --percentiles 指定的各分位点，如 p50_<unit>、p999_<unit>
+struct Latency {
+    stats: LatencySummary,
+    unit: LatencyUnit,
+}
+
+impl Serialize for Latency {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let suffix = self.unit.suffix();
+        let mut map = serializer.serialize_map(Some(4 + self.stats.percentiles.len()))?;
+        map.serialize_entry(&format!("avg_{}", suffix), &self.unit.convert_nanos(self.stats.avg))?;
+        map.serialize_entry(&format!("min_{}", suffix), &self.unit.convert_nanos(self.stats.min))?;
+        map.serialize_entry(&format!("max_{}", suffix), &self.unit.convert_nanos(self.stats.max))?;
+        map.serialize_entry(&format!("stddev_{}", suffix), &self.unit.convert_nanos(self.stats.stddev))?;
+        for &(p, nanos) in &self.stats.percentiles {
+            map.serialize_entry(
+                &format!("{}_{}", percentile_name(p), suffix),
+                &self.unit.convert_nanos(nanos),
+            )?;
+        }
+        map.end()
     }
+}
 
-    while let Some(res) = tasks.join_next().await {
-        res??;
+pub async fn run_bench(db: DbConfig, mut cfg: BenchConfig) -> Result<()> {
+    let gate = PauseGate::install();
+    shutdown::install();
+    if let Some(path) = &cfg.timeseries {
+        timeseries::create(path)?;
+    }
+    let report = collect_report(&db, &mut cfg, &gate).await?;
+    let text = match cfg.format {
+        OutputFormat::Json => serde_json::to_string_pretty(&report)?,
+        OutputFormat::Markdown => render_markdown(&report),
+        OutputFormat::Csv => render_csv(&report),
+    };
+    write_output(&cfg, text).await?;
+
+    if cfg.prometheus.is_some() || cfg.pushgateway.is_some() {
+        let metrics = render_prometheus(&report);
+        if let Some(path) = &cfg.prometheus {
+            tokio::fs::write(path, &metrics).await?;
+            tracing::info!("Prometheus 指标已写入 {:?}", path);
+        }
+        if let Some(url) = &cfg.pushgateway {
+            push_metrics(url, metrics).await?;
+        }
     }
 
-    let mut guard = durations_shared.lock().await;
-    durations.append(&mut guard);
-    let stats = calc_stats(&mut durations);
-    let wall = scenario_start.elapsed().as_secs_f64();
-    let throughput = cfg.sample_ops as f64 / wall.max(0.001);
+    if report.metadata.interrupted {
+        return Err(anyhow!("基准被 Ctrl-C 中断，以上为中断前已完成的部分结果"));
+    }
+    Ok(())
+}
 
-    Ok(BenchResult {
-        scenario: sc.name.to_string(),
-        ops: cfg.sample_ops,
-        throughput_ops: throughput,
-        avg_ms: stats.avg,
-        p50_ms: stats.p50,
-        p95_ms: stats.p95,
-        p99_ms: stats.p99,
-    })
+/// 打印到 stdout，指定了 --output 时另写入文件
+async fn write_output(cfg: &BenchConfig, text: String) -> Result<()> {
+    println!("{}", text);
+    if let Some(path) = &cfg.output {
+        tokio::fs::write(path, text).await?;
+        tracing::info!("基准结果已写入 {:?}", path);
+    }
+    Ok(())
 }
 
-async fn exec_mysql(
-    conn: &mut mysql_async::Conn,
-    sc: &Scenario,
-    rng: &mut StdRng,
-    max_id: u64,
-) -> Result<()> {
-    match sc.param {
-        ParamKind::None => {
-            let _: Option<(i64,)> = conn.exec_first(sc.mysql_sql, ()).await?;
+/// user_id 查询的采样分布与范围
+struct Lookup {
+    dist: UserDistSpec,
+    /// 未指定 --users 时取装载记录的基数，仍为空则按表内最大 user_id 推断
+    users: Option<u64>,
+}
+
+/// --lookup-dist 优先；否则沿用装载记录在表注释里的分布与基数，使查询与数据一样偏向热点用户。
+/// compare 时每个库各自读取
+async fn resolve_lookup(db: &DbConfig, cfg: &BenchConfig) -> Result<Lookup> {
+    if let Some(spec) = &cfg.lookup_dist {
+        return Ok(Lookup {
+            dist: spec.clone(),
+            users: cfg.users,
+        });
+    }
+    match schema::recorded_user_dist(db).await? {
+        Some(recorded) => {
+            tracing::info!(
+                "沿用装载时记录的 user_id 分布 {}（users={}），可用 --lookup-dist 覆盖",
+                recorded.spec,
+                recorded.users
+            );
+            Ok(Lookup {
+                dist: recorded.spec,
+                users: cfg.users.or(Some(recorded.users)),
+            })
+        }
+        None => Ok(Lookup {
+            dist: UserDistSpec::single(Distribution::Uniform),
+            users: cfg.users,
+        }),
+    }
+}
+
+/// 对一个库跑完全部场景，汇总成报告；不输出
+async fn collect_report(db: &DbConfig, cfg: &mut BenchConfig, gate: &PauseGate) -> Result<BenchReport> {
+    if db.pooler == Pooler::Transaction {
+        tracing::info!("事务级连接池模式：场景语句改为不依赖服务端预处理语句的方式执行");
+        if cfg.compare_prepared {
+            tracing::warn!(
+                "--compare-prepared 的 prepared 模式依赖服务端预处理语句，经事务级连接池执行时语句可能落到其他后端而报错，结果不可信"
+            );
         }
-        ParamKind::PkHit => {
-            let id = rng.gen_range(1..=max_id as i64);
-            let _: Option<(i64,)> = conn.exec_first(sc.mysql_sql, (id,)).await?;
+    }
+
+    if let Some(ms) = cfg.statement_timeout_ms {
+        tracing::info!("工作连接的服务端语句超时设为 {}ms，超时的语句按错误处理", ms);
+        if db.kind == DbKind::Mysql {
+            tracing::info!("MySQL 的 max_execution_time 只对只读 SELECT 生效，写场景不受限制");
         }
-        ParamKind::UserHit => {
-            let user_id = rng.gen_range(1..=1_000_000_i64);
-            let _: Option<(i64,)> = conn.exec_first(sc.mysql_sql, (user_id,)).await?;
+        if db.pooler == Pooler::Transaction {
+            tracing::warn!("事务级连接池下会话级 SET 可能只作用于某个后端连接，语句超时不一定生效");
         }
     }
-    Ok(())
+
+    let sampler = if cfg.resource_stats {
+        ResourceSampler::start()
+    } else {
+        None
+    };
+    let lookup = resolve_lookup(db, cfg).await?;
+    let mut results = match db.kind {
+        DbKind::Mysql => bench_mysql(db, cfg, gate, &lookup).await?,
+        DbKind::Postgres => bench_postgres(db, cfg, gate, &lookup).await?,
+    };
+    let interrupted = shutdown::requested();
+    if interrupted {
+        // 预热阶段就被中断的场景没有样本，不输出
+        results.retain(|r| r.ops > 0);
+    }
+    let resources = match sampler {
+        Some(sampler) => sampler.finish().await,
+        None => None,
+    };
+    if let Some(r) = &resources {
+        tracing::info!(
+            "客户端资源: CPU 平均 {:.1}% 峰值 {:.1}%，RSS 平均 {:.1}MB 峰值 {:.1}MB",
+            r.cpu_avg_pct,
+            r.cpu_peak_pct,
+            r.rss_avg_mb,
+            r.rss_peak_mb
+        );
+    }
+
+    // 按次数采样时每个场景的样本数都是 sample_ops；按时长采样或中断时以样本最少的场景为准
+    let sampled = sampled_ops(&results, cfg.sample_ops, cfg.duration.is_some() || interrupted);
+    let undersampled = undersampled_percentiles(sampled, &cfg.percentiles.0);
+    for (name, needed) in &undersampled {
+        tracing::warn!(
+            "采样次数 {} 不足以可靠估计 {}（建议至少 {} 次），该分位数仅供参考",
+            sampled,
+            name,
+            needed
+        );
+    }
+
+    let repeats = summarize_repeats(&results);
+    Ok(BenchReport {
+        metadata: BenchMetadata {
+            run_id: Uuid::new_v4().to_string(),
+            tag: cfg.tag.clone(),
+            db: db.kind,
+            concurrency: cfg.concurrency,
+            target_qps: cfg.target_qps,
+            warmup_ops: cfg.warmup_ops,
+            sample_ops: cfg.sample_ops,
+            duration_s: cfg.duration.map(|d| d.as_secs_f64()),
+            seed: cfg.seed,
+            queries_per_roundtrip: cfg.queries_per_roundtrip,
+            no_prepare: cfg.no_prepare,
+            isolation: cfg.isolation,
+            user_dist: lookup.dist.to_string(),
+            mix: cfg.mix.as_ref().map(|m| m.to_string()),
+            ramp: cfg.ramp.as_ref().map(|r| r.to_string()),
+            repeat: (cfg.repeat > 1).then_some(cfg.repeat),
+            unit: cfg.unit,
+            low_confidence_percentiles: undersampled.into_iter().map(|(name, _)| name).collect(),
+            resources,
+            interrupted,
+        },
+        results,
+        repeats,
+    })
 }
 
-async fn exec_postgres(
-    client: &PgClient,
-    sc: &Scenario,
-    rng: &mut StdRng,
-    max_id: u64,
-) -> Result<()> {
-    match sc.param {
-        ParamKind::None => {
-            let _ = client.query(sc.postgres_sql, &[]).await?;
+/// 判断分位数是否可靠时使用的样本数
+fn sampled_ops(results: &[BenchResult], sample_ops: u64, timed: bool) -> u64 {
+    if timed {
+        results.iter().map(|r| r.ops).min().unwrap_or(0)
+    } else {
+        sample_ops
+    }
+}
+
+/// 渲染为 Markdown 表格；开启 --compare-prepared 时多一列 statement_mode
+fn render_markdown(report: &BenchReport) -> String {
+    let unit = report.metadata.unit.suffix();
+    let with_mode = report.results.iter().any(|r| r.statement_mode.is_some());
+    let with_concurrency = report.results.iter().any(|r| r.concurrency.is_some());
+    let with_repeat = !report.repeats.is_empty();
+    let mut headers = vec!["scenario".to_string()];
+    if with_mode {
+        headers.push("statement_mode".to_string());
+    }
+    if with_concurrency {
+        headers.push("concurrency".to_string());
+    }
+    if with_repeat {
+        headers.push("repeat".to_string());
+    }
+    headers.push("ops".to_string());
+    headers.push("throughput_ops".to_string());
+    // 所有场景的分位点相同，取第一个结果的即可
+    if let Some(r) = report.results.first() {
+        for &(p, _) in &r.latency.stats.percentiles {
+            headers.push(format!("{}_{}", percentile_name(p), unit));
+        }
+    }
+
+    let mut title = format!("run_id `{}`", report.metadata.run_id);
+    if let Some(tag) = &report.metadata.tag {
+        title.push_str(&format!("，tag `{}`", tag));
+    }
+    if !report.metadata.low_confidence_percentiles.is_empty() {
+        let meta = &report.metadata;
+        title.push_str(&format!(
+            "；采样次数 {} 偏少，{} 仅供参考",
+            sampled_ops(&report.results, meta.sample_ops, meta.duration_s.is_some() || meta.interrupted),
+            report.metadata.low_confidence_percentiles.join("/")
+        ));
+    }
+    if report.metadata.interrupted {
+        title.push_str("；已被 Ctrl-C 中断，只含中断前的结果");
+    }
+    let mut lines = vec![
+        title,
+        String::new(),
+        format!("| {} |", headers.join(" | ")),
+        format!("|{}|", vec![" --- "; headers.len()].join("|")),
+    ];
+    for r in &report.results {
+        let mut cells = vec![r.scenario.clone()];
+        if with_mode {
+            cells.push(r.statement_mode.map(StatementMode::as_str).unwrap_or_default().to_string());
+        }
+        if with_concurrency {
+            cells.push(r.concurrency.map(|c| c.to_string()).unwrap_or_default());
         }
-        ParamKind::PkHit => {
-            let id = rng.gen_range(1..=max_id as i64);
-            let _ = client.query_opt(sc.postgres_sql, &[&id]).await?;
+        if with_repeat {
+            cells.push(r.repeat.map(|i| i.to_string()).unwrap_or_default());
         }
-        ParamKind::UserHit => {
-            let user_id = rng.gen_range(1..=1_000_000_i64);
-            let _ = client.query_opt(sc.postgres_sql, &[&user_id]).await?;
+        cells.push(r.ops.to_string());
+        cells.push(format!("{:.2}", r.throughput_ops));
+        let stats = &r.latency.stats;
+        for &(_, nanos) in &stats.percentiles {
+            cells.push(format!("{:.3}", r.latency.unit.convert_nanos(nanos)));
+        }
+        lines.push(format!("| {} |", cells.join(" | ")));
+        // 混合负载另起几行列出各场景，吞吐只统计整体
+        for part in r.breakdown.iter().flatten() {
+            let mut cells = vec![format!("{}:{}", r.scenario, part.scenario)];
+            if with_mode {
+                cells.push(String::new());
+            }
+            if with_concurrency {
+                cells.push(r.concurrency.map(|c| c.to_string()).unwrap_or_default());
+            }
+            if with_repeat {
+                cells.push(r.repeat.map(|i| i.to_string()).unwrap_or_default());
+            }
+            cells.push(part.ops.to_string());
+            cells.push("-".to_string());
+            for &(_, nanos) in &part.latency.stats.percentiles {
+                cells.push(format!("{:.3}", part.latency.unit.convert_nanos(nanos)));
+            }
+            lines.push(format!("| {} |", cells.join(" | ")));
         }
     }
-    Ok(())
+    if with_repeat {
+        lines.push(String::new());
+        lines.extend(render_repeat_markdown(report, with_mode, with_concurrency));
+    }
+    lines.join("\n")
 }
 
-fn calc_stats(durations_ms: &mut Vec<f64>) -> Stats {
-    if durations_ms.is_empty() {
-        return Stats {
-            avg: 0.0,
-            p50: 0.0,
-            p95: 0.0,
-            p99: 0.0,
-        };
+/// 各组重复的汇总表：吞吐与各分位点的均值 ± 样本标准差
+fn render_repeat_markdown(report: &BenchReport, with_mode: bool, with_concurrency: bool) -> Vec<String> {
+    let mut headers = vec!["scenario".to_string()];
+    if with_mode {
+        headers.push("statement_mode".to_string());
     }
-    durations_ms.sort_by(|a, b| a.partial_cmp(b).unwrap());
-    let sum: f64 = durations_ms.iter().sum();
-    let avg = sum / durations_ms.len() as f64;
-    let idx = |p: f64| -> usize {
-        let pos = (p * durations_ms.len() as f64).ceil() as usize;
-        durations_ms.len().saturating_sub(1).min(pos.saturating_sub(1))
-    };
-    Stats {
-        avg,
-        p50: durations_ms[idx(0.50)],
-        p95: durations_ms[idx(0.95)],
-        p99: durations_ms[idx(0.99)],
+    if with_concurrency {
+        headers.push("concurrency".to_string());
+    }
+    headers.push("repeats".to_string());
+    headers.push("throughput_ops".to_string());
+    // 与上表一致只列分位点，跳过 avg
+    if let Some(r) = report.repeats.first() {
+        headers.extend(r.latency.0.iter().skip(1).map(|(name, _)| name.clone()));
+    }
+    let mut lines = vec![
+        format!("重复 {} 次的汇总（均值 ± 样本标准差）", report.metadata.repeat.unwrap_or_default()),
+        String::new(),
+        format!("| {} |", headers.join(" | ")),
+        format!("|{}|", vec![" --- "; headers.len()].join("|")),
+    ];
+    for r in &report.repeats {
+        let mut cells = vec![r.scenario.clone()];
+        if with_mode {
+            cells.push(r.statement_mode.map(StatementMode::as_str).unwrap_or_default().to_string());
+        }
+        if with_concurrency {
+            cells.push(r.concurrency.map(|c| c.to_string()).unwrap_or_default());
+        }
+        cells.push(r.repeats.to_string());
+        cells.push(r.throughput_ops.display(2));
+        cells.extend(r.latency.0.iter().skip(1).map(|(_, spread)| spread.display(3)));
+        lines.push(format!("| {} |", cells.join(" | ")));
+    }
+    lines
+}
+
+/// 列与 markdown 表格一致，另加 avg，每行开头带 run_id 与 tag，便于把多次运行的 CSV 拼在一起；
+/// --mix 的各场景以 mix:<场景> 行给出，吞吐留空
+fn render_csv(report: &BenchReport) -> String {
+    let unit = report.metadata.unit.suffix();
+    let with_mode = report.results.iter().any(|r| r.statement_mode.is_some());
+    let with_concurrency = report.results.iter().any(|r| r.concurrency.is_some());
+    let with_repeat = !report.repeats.is_empty();
+    let run_cells = run_csv_cells(&report.metadata.run_id, report.metadata.tag.as_deref());
+    let mut headers = vec!["run_id".to_string(), "tag".to_string(), "scenario".to_string()];
+    if with_mode {
+        headers.push("statement_mode".to_string());
+    }
+    if with_concurrency {
+        headers.push("concurrency".to_string());
+    }
+    if with_repeat {
+        headers.push("repeat".to_string());
+    }
+    headers.push("ops".to_string());
+    headers.push("throughput_ops".to_string());
+    headers.push(format!("avg_{}", unit));
+    headers.push(format!("min_{}", unit));
+    headers.push(format!("max_{}", unit));
+    headers.push(format!("stddev_{}", unit));
+    if let Some(r) = report.results.first() {
+        for &(p, _) in &r.latency.stats.percentiles {
+            headers.push(format!("{}_{}", percentile_name(p), unit));
+        }
+    }
+
+    let mut lines = vec![headers.join(",")];
+    let mut push_row = |scenario: String, r: &BenchResult, ops: u64, throughput: Option<f64>, latency: &Latency| {
+        let mut cells = run_cells.clone();
+        cells.push(csv_field(&scenario));
+        if with_mode {
+            cells.push(r.statement_mode.map(StatementMode::as_str).unwrap_or_default().to_string());
+        }
+        if with_concurrency {
+            cells.push(r.concurrency.map(|c| c.to_string()).unwrap_or_default());
+        }
+        if with_repeat {
+            cells.push(r.repeat.map(|i| i.to_string()).unwrap_or_default());
+        }
+        cells.push(ops.to_string());
+        cells.push(throughput.map(|t| format!("{:.2}", t)).unwrap_or_default());
+        for nanos in [latency.stats.avg, latency.stats.min, latency.stats.max, latency.stats.stddev] {
+            cells.push(format!("{:.3}", latency.unit.convert_nanos(nanos)));
+        }
+        for &(_, nanos) in &latency.stats.percentiles {
+            cells.push(format!("{:.3}", latency.unit.convert_nanos(nanos)));
+        }
+        lines.push(cells.join(","));
+    };
+    for r in &report.results {
+        push_row(r.scenario.clone(), r, r.ops, Some(r.throughput_ops), &r.latency);
+        for part in r.breakdown.iter().flatten() {
+            let name = format!("{}:{}", r.scenario, part.scenario);
+            push_row(name, r, part.ops, None, &part.latency);
+        }
+    }
+    lines.join("\n")
+}
+
+/// Prometheus 文本格式：延迟按 summary 输出分位点与 _sum/_count，吞吐为 gauge。
+/// 指标名带 --unit 后缀，如 db_bench_latency_ms；--mix 的各场景以 scenario="mix:<场景>" 输出
+fn render_prometheus(report: &BenchReport) -> String {
+    let unit = report.metadata.unit.suffix();
+    let latency_name = format!("db_bench_latency_{}", unit);
+    let mut latency = vec![
+        format!("# HELP {} Operation latency in {}.", latency_name, unit),
+        format!("# TYPE {} summary", latency_name),
+    ];
+    let mut throughput = vec![
+        "# HELP db_bench_throughput_ops Completed operations per second.".to_string(),
+        "# TYPE db_bench_throughput_ops gauge".to_string(),
+    ];
+    let meta = &report.metadata;
+    // --ramp 时各结果的并发数不同，标签取结果自己的并发数
+    let labels = |scenario: &str, r: &BenchResult| {
+        let mut labels = format!(
+            "scenario=\"{}\",db=\"{}\",concurrency=\"{}\"",
+            prometheus_label(scenario),
+            meta.db.as_str(),
+            r.concurrency.unwrap_or(meta.concurrency)
+        );
+        if let Some(mode) = r.statement_mode {
+            labels.push_str(&format!(",statement_mode=\"{}\"", mode.as_str()));
+        }
+        // --repeat 时同一场景有多条结果，以 repeat 区分序列
+        if let Some(i) = r.repeat {
+            labels.push_str(&format!(",repeat=\"{}\"", i));
+        }
+        if let Some(tag) = &meta.tag {
+            labels.push_str(&format!(",tag=\"{}\"", prometheus_label(tag)));
+        }
+        labels
+    };
+    let mut push_summary = |labels: &str, stats: &LatencySummary| {
+        for &(p, nanos) in &stats.percentiles {
+            latency.push(format!(
+                "{}{{{},quantile=\"{}\"}} {}",
+                latency_name,
+                labels,
+                prometheus_quantile(p),
+                meta.unit.convert_nanos(nanos)
+            ));
+        }
+        let sum = meta.unit.convert_nanos(stats.avg) * stats.count as f64;
+        latency.push(format!("{}_sum{{{}}} {}", latency_name, labels, sum));
+        latency.push(format!("{}_count{{{}}} {}", latency_name, labels, stats.count));
+    };
+    for r in &report.results {
+        let base = labels(&r.scenario, r);
+        push_summary(&base, &r.latency.stats);
+        throughput.push(format!("db_bench_throughput_ops{{{}}} {}", base, r.throughput_ops));
+        for part in r.breakdown.iter().flatten() {
+            let part_labels = labels(&format!("{}:{}", r.scenario, part.scenario), r);
+            push_summary(&part_labels, &part.latency.stats);
+        }
+    }
+    latency.extend(throughput);
+    // run_id 每次都不同，不放进各序列的标签里，以免每次运行都产生一批新序列
+    let mut info = format!("run_id=\"{}\",db=\"{}\"", meta.run_id, meta.db.as_str());
+    if let Some(tag) = &meta.tag {
+        info.push_str(&format!(",tag=\"{}\"", prometheus_label(tag)));
+    }
+    latency.push("# HELP db_bench_run_info Identifies the run that produced these metrics.".to_string());
+    latency.push("# TYPE db_bench_run_info gauge".to_string());
+    latency.push(format!("db_bench_run_info{{{}}} 1", info));
+    latency.push(String::new());
+    latency.join("\n")
+}
+
+/// 99.9 -> "0.999"；直接除以 100 会得到 0.9990000000000001
+fn prometheus_quantile(p: f64) -> String {
+    let q = format!("{:.6}", p / 100.0);
+    q.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn prometheus_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// url 未带 /metrics/job/ 路径时推送到 job="db_bench"
+async fn push_metrics(url: &str, metrics: String) -> Result<()> {
+    let url = if url.contains("/metrics/job/") {
+        url.to_string()
+    } else {
+        format!("{}/metrics/job/db_bench", url.trim_end_matches('/'))
+    };
+    let resp = reqwest::Client::new()
+        .post(&url)
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(metrics)
+        .send()
+        .await
+        .with_context(|| format!("推送指标到 Pushgateway 失败: {}", connect::redact_url(&url)))?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        bail!("Pushgateway 返回 {}: {}", status, body.trim());
+    }
+    tracing::info!("指标已推送到 {}", connect::redact_url(&url));
+    Ok(())
+}
+
+/// compare 子命令的报告：同一组参数（含 seed，参数序列相同）先后在 MySQL 与 Postgres 上各跑一遍
+#[derive(Serialize)]
+struct CompareReport {
+    metadata: CompareMetadata,
+    /// 两边都有的结果按场景对齐，ratio 均为 postgres / mysql
+    deltas: Vec<ScenarioDelta>,
+    mysql: BenchReport,
+    /// MySQL 阶段就被 Ctrl-C 中断时不再运行 Postgres
+    #[serde(skip_serializing_if = "Option::is_none")]
+    postgres: Option<BenchReport>,
+}
+
+#[derive(Serialize)]
+struct CompareMetadata {
+    run_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    seed: u64,
+    unit: LatencyUnit,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    interrupted: bool,
+}
+
+/// 场景、语句模式、并发数都相同的一对结果
+struct ScenarioDelta {
+    scenario: String,
+    statement_mode: Option<StatementMode>,
+    concurrency: Option<usize>,
+    repeat: Option<usize>,
+    mysql_throughput_ops: f64,
+    postgres_throughput_ops: f64,
+    /// (指标名, mysql, postgres)：avg 与各分位点，已按 --unit 换算
+    latency: Vec<(String, f64, f64)>,
+    unit: LatencyUnit,
+}
+
+impl Serialize for ScenarioDelta {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let suffix = self.unit.suffix();
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("scenario", &self.scenario)?;
+        if let Some(mode) = self.statement_mode {
+            map.serialize_entry("statement_mode", &mode)?;
+        }
+        if let Some(concurrency) = self.concurrency {
+            map.serialize_entry("concurrency", &concurrency)?;
+        }
+        if let Some(repeat) = self.repeat {
+            map.serialize_entry("repeat", &repeat)?;
+        }
+        map.serialize_entry("mysql_throughput_ops", &self.mysql_throughput_ops)?;
+        map.serialize_entry("postgres_throughput_ops", &self.postgres_throughput_ops)?;
+        map.serialize_entry(
+            "throughput_ratio",
+            &ratio(self.postgres_throughput_ops, self.mysql_throughput_ops),
+        )?;
+        for (name, mysql, postgres) in &self.latency {
+            map.serialize_entry(&format!("mysql_{}_{}", name, suffix), mysql)?;
+            map.serialize_entry(&format!("postgres_{}_{}", name, suffix), postgres)?;
+            map.serialize_entry(&format!("{}_ratio", name), &ratio(*postgres, *mysql))?;
+        }
+        map.end()
+    }
+}
+
+/// postgres / mysql；MySQL 一侧为 0 时没有意义
+fn ratio(postgres: f64, mysql: f64) -> Option<f64> {
+    (mysql > 0.0).then(|| postgres / mysql)
+}
+
+impl BenchConfig {
+    /// compare 时两边共用同一份配置，只对单个库有意义的参数不支持
+    pub fn validate_compare(&self) -> Result<()> {
+        if self.read_url.is_some() {
+            bail!("compare 不支持 --read-url：一个只读副本无法同时对应两个库");
+        }
+        if self.plan_baseline.is_some() {
+            bail!("compare 不支持 --plan-baseline：两个库的执行计划指纹不能共用一个基线文件");
+        }
+        if self.prometheus.is_some() || self.pushgateway.is_some() {
+            bail!("compare 不支持 --prometheus/--pushgateway，请分别用 bench 导出各库的指标");
+        }
+        Ok(())
+    }
+}
+
+/// 先 MySQL 后 Postgres，两次运行使用相同的 seed 与参数，之后输出合并的报告
+pub async fn run_compare(mysql: DbConfig, postgres: DbConfig, mut cfg: BenchConfig) -> Result<()> {
+    let gate = PauseGate::install();
+    shutdown::install();
+    if let Some(path) = &cfg.timeseries {
+        timeseries::create(path)?;
+    }
+    tracing::info!("compare：先在 MySQL 上运行，seed {}", cfg.seed);
+    let mysql = collect_report(&mysql, &mut cfg, &gate).await?;
+    let postgres = if shutdown::requested() {
+        tracing::warn!("MySQL 阶段被中断，不再运行 Postgres");
+        None
+    } else {
+        tracing::info!("compare：再在 Postgres 上运行，seed {}", cfg.seed);
+        Some(collect_report(&postgres, &mut cfg, &gate).await?)
+    };
+    let deltas = postgres
+        .as_ref()
+        .map(|postgres| scenario_deltas(&mysql, postgres))
+        .unwrap_or_default();
+    let report = CompareReport {
+        metadata: CompareMetadata {
+            run_id: Uuid::new_v4().to_string(),
+            tag: cfg.tag.clone(),
+            seed: cfg.seed,
+            unit: cfg.unit,
+            interrupted: shutdown::requested(),
+        },
+        deltas,
+        mysql,
+        postgres,
+    };
+    let text = match cfg.format {
+        OutputFormat::Json => serde_json::to_string_pretty(&report)?,
+        OutputFormat::Markdown => render_compare_markdown(&report),
+        OutputFormat::Csv => render_compare_csv(&report),
+    };
+    write_output(&cfg, text).await?;
+
+    if report.metadata.interrupted {
+        return Err(anyhow!("对比被 Ctrl-C 中断，以上为中断前已完成的部分结果"));
+    }
+    Ok(())
+}
+
+/// 按 (场景, 语句模式, 并发数, 第几次重复) 对齐，只有一边有的结果（如中断）不参与对比
+fn scenario_deltas(mysql: &BenchReport, postgres: &BenchReport) -> Vec<ScenarioDelta> {
+    let latency = |latency: &Latency| {
+        let stats = &latency.stats;
+        let mut values = vec![("avg".to_string(), latency.unit.convert_nanos(stats.avg))];
+        for &(p, nanos) in &stats.percentiles {
+            values.push((percentile_name(p), latency.unit.convert_nanos(nanos)));
+        }
+        values
+    };
+    let mut deltas = Vec::new();
+    for m in &mysql.results {
+        let Some(p) = postgres.results.iter().find(|p| {
+            p.scenario == m.scenario
+                && p.statement_mode == m.statement_mode
+                && p.concurrency == m.concurrency
+                && p.repeat == m.repeat
+        }) else {
+            tracing::warn!("场景 {} 只有 MySQL 的结果，不参与对比", m.scenario);
+            continue;
+        };
+        deltas.push(ScenarioDelta {
+            scenario: m.scenario.clone(),
+            statement_mode: m.statement_mode,
+            concurrency: m.concurrency,
+            repeat: m.repeat,
+            mysql_throughput_ops: m.throughput_ops,
+            postgres_throughput_ops: p.throughput_ops,
+            latency: latency(&m.latency)
+                .into_iter()
+                .zip(latency(&p.latency))
+                .map(|((name, mysql), (_, postgres))| (name, mysql, postgres))
+                .collect(),
+            unit: m.latency.unit,
+        });
+    }
+    deltas
+}
+
+/// 对比表的列与各行单元格；avg 只出现在 CSV 中，与 bench 的两种格式一致
+fn compare_table(report: &CompareReport, with_avg: bool) -> (Vec<String>, Vec<Vec<String>>) {
+    let unit = report.metadata.unit.suffix();
+    let with_mode = report.deltas.iter().any(|d| d.statement_mode.is_some());
+    let with_concurrency = report.deltas.iter().any(|d| d.concurrency.is_some());
+    let with_repeat = report.deltas.iter().any(|d| d.repeat.is_some());
+    let mut headers = vec!["scenario".to_string()];
+    if with_mode {
+        headers.push("statement_mode".to_string());
+    }
+    if with_concurrency {
+        headers.push("concurrency".to_string());
+    }
+    if with_repeat {
+        headers.push("repeat".to_string());
+    }
+    headers.push("mysql_throughput_ops".to_string());
+    headers.push("postgres_throughput_ops".to_string());
+    headers.push("throughput_ratio".to_string());
+    let skip = if with_avg { 0 } else { 1 };
+    if let Some(d) = report.deltas.first() {
+        for (name, _, _) in d.latency.iter().skip(skip) {
+            headers.push(format!("mysql_{}_{}", name, unit));
+            headers.push(format!("postgres_{}_{}", name, unit));
+            headers.push(format!("{}_ratio", name));
+        }
+    }
+    let format_ratio = |postgres: f64, mysql: f64| ratio(postgres, mysql).map(|r| format!("{:.2}", r)).unwrap_or_default();
+    let rows = report
+        .deltas
+        .iter()
+        .map(|d| {
+            let mut cells = vec![d.scenario.clone()];
+            if with_mode {
+                cells.push(d.statement_mode.map(StatementMode::as_str).unwrap_or_default().to_string());
+            }
+            if with_concurrency {
+                cells.push(d.concurrency.map(|c| c.to_string()).unwrap_or_default());
+            }
+            if with_repeat {
+                cells.push(d.repeat.map(|i| i.to_string()).unwrap_or_default());
+            }
+            cells.push(format!("{:.2}", d.mysql_throughput_ops));
+            cells.push(format!("{:.2}", d.postgres_throughput_ops));
+            cells.push(format_ratio(d.postgres_throughput_ops, d.mysql_throughput_ops));
+            for &(_, mysql, postgres) in d.latency.iter().skip(skip) {
+                cells.push(format!("{:.3}", mysql));
+                cells.push(format!("{:.3}", postgres));
+                cells.push(format_ratio(postgres, mysql));
+            }
+            cells
+        })
+        .collect();
+    (headers, rows)
+}
+
+fn render_compare_markdown(report: &CompareReport) -> String {
+    let mut title = format!("MySQL vs Postgres，run_id `{}`", report.metadata.run_id);
+    if let Some(tag) = &report.metadata.tag {
+        title.push_str(&format!("，tag `{}`", tag));
+    }
+    title.push_str("；ratio 为 postgres / mysql，吞吐大于 1、延迟小于 1 表示 Postgres 更好");
+    if report.metadata.interrupted {
+        title.push_str("；已被 Ctrl-C 中断，只含两边都完成的场景");
+    }
+    let (headers, rows) = compare_table(report, false);
+    let mut lines = vec![
+        title,
+        String::new(),
+        format!("| {} |", headers.join(" | ")),
+        format!("|{}|", vec![" --- "; headers.len()].join("|")),
+    ];
+    for cells in rows {
+        lines.push(format!("| {} |", cells.join(" | ")));
+    }
+    lines.join("\n")
+}
+
+/// 每行一个对齐的场景，开头带 run_id 与 tag；两边各自的完整结果见 JSON 格式
+fn render_compare_csv(report: &CompareReport) -> String {
+    let (headers, rows) = compare_table(report, true);
+    let run_cells = run_csv_cells(&report.metadata.run_id, report.metadata.tag.as_deref());
+    let mut lines = vec![format!("run_id,tag,{}", headers.join(","))];
+    for mut cells in rows {
+        cells[0] = csv_field(&cells[0]);
+        lines.push(format!("{},{}", run_cells.join(","), cells.join(",")));
+    }
+    lines.join("\n")
+}
+
+/// CSV 每行开头的 run_id 与 tag 两列，未打标签时 tag 为空
+fn run_csv_cells(run_id: &str, tag: Option<&str>) -> Vec<String> {
+    vec![run_id.to_string(), tag.map(csv_field).unwrap_or_default()]
+}
+
+/// 含逗号、引号或换行的字段按 RFC 4180 加引号，如来自 --scenarios 的场景名、语料中的 payload
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+async fn bench_mysql(db: &DbConfig, cfg: &mut BenchConfig, gate: &PauseGate, lookup: &Lookup) -> Result<Vec<BenchResult>> {
+    let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+    let pooler = db.pooler;
+    connect::warn_pool_size(db.connect.pool_size, cfg.concurrency_levels().into_iter().max().unwrap_or(1));
+    let max_id = fetch_mysql_max_id(&pool).await?;
+    if max_id == 0 {
+        return Err(anyhow!("events 表为空，无法基准测试"));
+    }
+    let max_user_id = fetch_mysql_max_user_id(&pool).await?;
+    let users = bench_users(lookup.users, max_user_id);
+    let mut space = ParamSpace::new(max_id, users, cfg.hot_rows, cfg.time_span_days, cfg.payload_type, &lookup.dist)?;
+    apply_sample_fraction(cfg, &mut space);
+    let space = Arc::new(space);
+    let cfg = &*cfg;
+
+    let scenarios = bench_scenarios(cfg);
+    let plans = if cfg.explain || cfg.plan_baseline.is_some() {
+        Some(capture_mysql_plans(&pool, &scenarios, cfg.seed, &space).await?)
+    } else {
+        None
+    };
+    if let (Some(path), Some(plans)) = (&cfg.plan_baseline, &plans) {
+        let fingerprints = plans
+            .iter()
+            .map(|(name, json)| (name.clone(), plan::mysql_fingerprint(json)))
+            .collect();
+        check_plan_baseline(path, &fingerprints, cfg.abort_on_plan_regression)?;
+    }
+    let mut results = Vec::with_capacity(scenarios.len());
+    if let Some(mix) = &cfg.mix {
+        let parts = resolve_mix(mix, scenarios)?;
+        for round in cfg.rounds(cfg.base_mode()) {
+            if shutdown::requested() {
+                break;
+            }
+            log_round(cfg, "mix", round);
+            let before = mysql_counters(&pool, cfg).await?;
+            let mut result = run_mysql_mix(&pool, &parts, cfg, &space, round, pooler, gate)
+                .instrument(round_span("mysql", "mix", round))
+                .await?;
+            if let Some(before) = before {
+                result.server = mysql_counters(&pool, cfg).await?.map(|after| after.since(&before));
+            }
+            result.repeat = round.repeat;
+            results.push(result);
+        }
+        if parts.iter().any(|(sc, _)| matches!(sc.param, ParamKind::Insert | ParamKind::Upsert)) {
+            cleanup_mysql_inserts(&pool, "mix", max_id).await?;
+        }
+    } else {
+        for sc in scenarios {
+            if shutdown::requested() {
+                break;
+            }
+            for mode in statement_modes(cfg) {
+                for round in cfg.rounds(mode) {
+                    if shutdown::requested() {
+                        break;
+                    }
+                    log_round(cfg, &sc.name, round);
+                    let before = mysql_counters(&pool, cfg).await?;
+                    let mut result = run_mysql_scenario(&pool, &sc, cfg, &space, round, pooler, gate)
+                        .instrument(round_span("mysql", &sc.name, round))
+                        .await?;
+                    if let Some(before) = before {
+                        result.server = mysql_counters(&pool, cfg).await?.map(|after| after.since(&before));
+                    }
+                    result.repeat = round.repeat;
+                    results.push(result);
+                }
+            }
+            if matches!(sc.param, ParamKind::Insert | ParamKind::Upsert) {
+                cleanup_mysql_inserts(&pool, &sc.name, max_id).await?;
+            }
+        }
+    }
+    if cfg.explain && let Some(plans) = &plans {
+        attach_plans(&mut results, plans);
+    }
+    if let Some(read_url) = &cfg.read_url
+        && scenario_selected(cfg, "replica_lag")
+        && !shutdown::requested()
+    {
+        results.push(mysql_replica_lag(&pool, read_url, &db.connect, cfg).await?);
+    }
+    pool.disconnect().await?;
+    Ok(results)
+}
+
+/// --server-metrics 时查询一次服务端计数器，否则为 None
+async fn mysql_counters(pool: &mysql_async::Pool, cfg: &BenchConfig) -> Result<Option<ServerCounters>> {
+    if !cfg.server_metrics {
+        return Ok(None);
+    }
+    let mut conn = pool.get_conn().await?;
+    server::fetch_mysql_counters(&mut conn).await.map(Some)
+}
+
+async fn cleanup_mysql_inserts(pool: &mysql_async::Pool, scenario: &str, max_id: u64) -> Result<()> {
+    let mut conn = pool.get_conn().await?;
+    conn.query_drop(insert_cleanup_sql(max_id)).await?;
+    tracing::info!("scenario={} 已删除写入的 {} 行", scenario, conn.affected_rows());
+    conn.disconnect().await?;
+    Ok(())
+}
+
+async fn bench_postgres(db: &DbConfig, cfg: &mut BenchConfig, gate: &PauseGate, lookup: &Lookup) -> Result<Vec<BenchResult>> {
+    let (client, connection) = connect::postgres(&db.url, &db.connect).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("Postgres 连接任务出错: {}", e);
+        }
+    });
+    let max_id = fetch_postgres_max_id(&client).await?;
+    if max_id == 0 {
+        return Err(anyhow!("events 表为空，无法基准测试"));
+    }
+    let max_user_id = fetch_postgres_max_user_id(&client).await?;
+    let users = bench_users(lookup.users, max_user_id);
+    let mut space = ParamSpace::new(max_id, users, cfg.hot_rows, cfg.time_span_days, cfg.payload_type, &lookup.dist)?;
+    apply_sample_fraction(cfg, &mut space);
+    let space = Arc::new(space);
+    let cfg = &*cfg;
+
+    let scenarios = bench_scenarios(cfg);
+    let plans = if cfg.explain || cfg.plan_baseline.is_some() {
+        Some(capture_postgres_plans(&client, &scenarios, cfg.seed, &space).await?)
+    } else {
+        None
+    };
+    if let (Some(path), Some(plans)) = (&cfg.plan_baseline, &plans) {
+        let fingerprints = plans
+            .iter()
+            .map(|(name, json)| (name.clone(), plan::postgres_fingerprint(json)))
+            .collect();
+        check_plan_baseline(path, &fingerprints, cfg.abort_on_plan_regression)?;
+    }
+    let mut results = Vec::with_capacity(scenarios.len());
+    if let Some(mix) = &cfg.mix {
+        let parts = resolve_mix(mix, scenarios)?;
+        for round in cfg.rounds(cfg.base_mode()) {
+            if shutdown::requested() {
+                break;
+            }
+            log_round(cfg, "mix", round);
+            let before = postgres_counters(&client, cfg).await?;
+            let mut result = run_postgres_mix(db, &parts, cfg, &space, round, gate)
+                .instrument(round_span("postgres", "mix", round))
+                .await?;
+            if let Some(before) = before {
+                result.server = postgres_counters(&client, cfg).await?.map(|after| after.since(&before));
+            }
+            result.repeat = round.repeat;
+            results.push(result);
+        }
+        if parts.iter().any(|(sc, _)| matches!(sc.param, ParamKind::Insert | ParamKind::Upsert)) {
+            cleanup_postgres_inserts(&client, "mix", max_id).await?;
+        }
+    } else {
+        for sc in scenarios {
+            if shutdown::requested() {
+                break;
+            }
+            for mode in statement_modes(cfg) {
+                for round in cfg.rounds(mode) {
+                    if shutdown::requested() {
+                        break;
+                    }
+                    log_round(cfg, &sc.name, round);
+                    let before = postgres_counters(&client, cfg).await?;
+                    let mut result = run_postgres_scenario(db, &sc, cfg, &space, round, gate)
+                        .instrument(round_span("postgres", &sc.name, round))
+                        .await?;
+                    if let Some(before) = before {
+                        result.server = postgres_counters(&client, cfg).await?.map(|after| after.since(&before));
+                    }
+                    result.repeat = round.repeat;
+                    results.push(result);
+                }
+            }
+            if matches!(sc.param, ParamKind::Insert | ParamKind::Upsert) {
+                cleanup_postgres_inserts(&client, &sc.name, max_id).await?;
+            }
+        }
+    }
+    if cfg.explain && let Some(plans) = &plans {
+        attach_plans(&mut results, plans);
+    }
+    if let Some(read_url) = &cfg.read_url
+        && scenario_selected(cfg, "replica_lag")
+        && !shutdown::requested()
+    {
+        results.push(postgres_replica_lag(&client, read_url, &db.connect, cfg).await?);
+    }
+    Ok(results)
+}
+
+async fn postgres_counters(client: &PgClient, cfg: &BenchConfig) -> Result<Option<ServerCounters>> {
+    if !cfg.server_metrics {
+        return Ok(None);
+    }
+    server::fetch_postgres_counters(client).await.map(Some)
+}
+
+async fn cleanup_postgres_inserts(client: &PgClient, scenario: &str, max_id: u64) -> Result<()> {
+    let deleted = client
+        .simple_query(&insert_cleanup_sql(max_id))
+        .await?
+        .iter()
+        .find_map(|m| match m {
+            tokio_postgres::SimpleQueryMessage::CommandComplete(n) => Some(*n),
+            _ => None,
+        })
+        .unwrap_or(0);
+    tracing::info!("scenario={} 已删除写入的 {} 行", scenario, deleted);
+    Ok(())
+}
+
+/// 按表大小换算 sample_ops（替换 --sample-ops），并开启 pk_hit 的不重复主键遍历
+fn apply_sample_fraction(cfg: &mut BenchConfig, space: &mut ParamSpace) {
+    let Some(fraction) = cfg.sample_fraction else {
+        return;
+    };
+    cfg.sample_ops = fraction_ops(fraction, space.max_id);
+    tracing::info!(
+        "--sample-fraction {}：按 max(id)={} 估算表大小，每个场景采样 {} 次",
+        fraction,
+        space.max_id,
+        cfg.sample_ops
+    );
+    if cfg.warmup_ops + cfg.sample_ops > space.max_id {
+        tracing::warn!("预热与采样次数之和超过 max(id)，pk_hit 的主键遍历会回绕，采样阶段可能出现重复主键");
+    }
+    space.coverage = Some(PkCoverage::new(space.max_id, cfg.seed));
+}
+
+/// 用一个代表性参数对每个场景做 EXPLAIN，返回原始 JSON。走 text 协议，不受连接池模式影响
+async fn capture_mysql_plans(
+    pool: &mysql_async::Pool,
+    scenarios: &[Scenario],
+    seed: u64,
+    space: &ParamSpace,
+) -> Result<ScenarioPlans> {
+    let mut conn = pool.get_conn().await?;
+    let mut sampler = Sampler::new(seed, space, scenarios);
+    let mut plans = ScenarioPlans::new();
+    for sc in scenarios {
+        let values = match sample_param(sc.param, &mut sampler, space).into_mysql_params() {
+            MyParams::Positional(values) => values,
+            _ => Vec::new(),
+        };
+        let sql = inline_mysql_params(&format!("EXPLAIN FORMAT=JSON {}", sc.mysql_sql), &values);
+        let text: Option<String> = conn.query_first(sql).await?;
+        let text = text.ok_or_else(|| anyhow!("scenario={} EXPLAIN 没有返回结果", sc.name))?;
+        let json: serde_json::Value = serde_json::from_str(&text)?;
+        tracing::debug!("scenario={} mysql 执行计划: {}", sc.name, text);
+        plans.insert(sc.name.to_string(), json);
+    }
+    conn.disconnect().await?;
+    Ok(plans)
+}
+
+async fn capture_postgres_plans(
+    client: &PgClient,
+    scenarios: &[Scenario],
+    seed: u64,
+    space: &ParamSpace,
+) -> Result<ScenarioPlans> {
+    let mut sampler = Sampler::new(seed, space, scenarios);
+    let mut plans = ScenarioPlans::new();
+    for sc in scenarios {
+        let value = sample_param(sc.param, &mut sampler, space);
+        let sql = format!("EXPLAIN (FORMAT JSON) {}", sc.postgres_sql);
+        let rows = client.query_typed(&sql, &value.pg_typed_params()).await?;
+        let row = rows
+            .first()
+            .ok_or_else(|| anyhow!("scenario={} EXPLAIN 没有返回结果", sc.name))?;
+        let json: serde_json::Value = row.get(0);
+        tracing::debug!("scenario={} postgres 执行计划: {}", sc.name, json);
+        plans.insert(sc.name.to_string(), json);
+    }
+    Ok(plans)
+}
+
+/// show-sql 子命令的参数：离线采样，不连接数据库
+pub struct ShowSqlConfig {
+    pub scenario: String,
+    /// 代替表内 max(id)
+    pub max_id: u64,
+    pub seed: u64,
+    pub user_dist: UserDistSpec,
+    /// 代替表内 max(user_id)
+    pub users: u64,
+    pub hot_rows: u64,
+    pub time_span_days: u64,
+    pub payload_type: PayloadType,
+}
+
+/// 用与基准相同的参数逻辑采样一次，返回代入了具体参数的场景 SQL
+pub fn show_sql(kind: DbKind, cfg: &ShowSqlConfig) -> Result<String> {
+    if cfg.max_id == 0 {
+        bail!("--max-id 必须大于 0");
+    }
+    let all = scenarios(true, true, cfg.time_span_days, cfg.payload_type);
+    let sc = all.iter().find(|sc| sc.name == cfg.scenario).ok_or_else(|| {
+        let names: Vec<&str> = all.iter().map(|sc| sc.name.as_str()).collect();
+        anyhow!("未知场景 {}，可选: {}", cfg.scenario, names.join(", "))
+    })?;
+    let space = ParamSpace::new(cfg.max_id, cfg.users, cfg.hot_rows, cfg.time_span_days, cfg.payload_type, &cfg.user_dist)?;
+    let mut sampler = Sampler::new(worker_seed(cfg.seed, 0), &space, std::slice::from_ref(sc));
+    let value = sample_param(sc.param, &mut sampler, &space);
+    let sql = match kind {
+        DbKind::Mysql => {
+            let values = match value.into_mysql_params() {
+                MyParams::Positional(values) => values,
+                _ => Vec::new(),
+            };
+            inline_mysql_params(&sc.mysql_sql, &values)
+        }
+        DbKind::Postgres => {
+            // 从大号往小号替换，避免 $1 误伤 $10 之类
+            let literals = value.pg_literals();
+            let mut sql = sc.postgres_sql.clone();
+            for (i, literal) in literals.iter().enumerate().rev() {
+                sql = sql.replace(&format!("${}", i + 1), literal);
+            }
+            sql
+        }
+    };
+    Ok(format!("{};", sql))
+}
+
+/// 按场景名把执行计划附到结果上；--compare-prepared 的两条结果共用同一份计划
+fn attach_plans(results: &mut [BenchResult], plans: &ScenarioPlans) {
+    for r in results {
+        r.plan = plans.get(&r.scenario).cloned();
+        for part in r.breakdown.iter_mut().flatten() {
+            part.plan = plans.get(&part.scenario).cloned();
+        }
+    }
+}
+
+fn check_plan_baseline(path: &Path, current: &PlanBaseline, abort: bool) -> Result<()> {
+    let Some(baseline) = plan::load_baseline(path)? else {
+        plan::save_baseline(path, current)?;
+        tracing::info!("执行计划基线不存在，已写入 {:?}", path);
+        return Ok(());
+    };
+    let changes = plan::diff(&baseline, current);
+    if changes.is_empty() {
+        tracing::info!("执行计划与基线 {:?} 一致", path);
+        return Ok(());
+    }
+    for change in &changes {
+        tracing::warn!("执行计划变化 {}", change);
+    }
+    if abort {
+        bail!("{} 个场景的执行计划与基线不一致: {}", changes.len(), changes.join("; "));
+    }
+    Ok(())
+}
+
+fn log_round(cfg: &BenchConfig, scenario: &str, round: Round) {
+    if let Some(ramp) = &cfg.ramp {
+        tracing::info!("scenario={} 并发 {}（--ramp {}）", scenario, round.concurrency, ramp);
+    }
+    if let Some(i) = round.repeat {
+        tracing::info!("scenario={} 第 {}/{} 次重复，seed {}", scenario, i, cfg.repeat, round.seed);
+    }
+}
+
+fn statement_modes(cfg: &BenchConfig) -> Vec<StatementMode> {
+    if cfg.compare_prepared {
+        vec![StatementMode::Reparse, StatementMode::Prepared]
+    } else {
+        vec![cfg.base_mode()]
+    }
+}
+
+/// insert 场景写入的行都在开始时的 max(id) 之后，按主键范围删除，不必扫全表
+fn insert_cleanup_sql(max_id: u64) -> String {
+    format!(
+        "DELETE FROM events WHERE id > {} AND category = {}",
+        max_id, INSERT_MARKER_CATEGORY
+    )
+}
+
+/// 本次要运行的场景：--scenarios 文件中的自定义场景，否则为内置场景；再按 --scenario/--exclude 过滤
+fn bench_scenarios(cfg: &BenchConfig) -> Vec<Scenario> {
+    available_scenarios(cfg)
+        .into_iter().filter(|sc| scenario_selected(cfg, &sc.name)).collect()
+}
+
+fn available_scenarios(cfg: &BenchConfig) -> Vec<Scenario> {
+    match &cfg.scenarios {
+        Some(custom) => custom.clone(),
+        None => scenarios(cfg.allow_writes, cfg.allow_deletes, cfg.time_span_days, cfg.payload_type),
+    }
+}
+
+fn scenario_selected(cfg: &BenchConfig, name: &str) -> bool {
+    (cfg.include.is_empty() || cfg.include.iter().any(|n| n == name)) && !cfg.exclude.iter().any(|n| n == name)
+}
+
+/// 本次可运行的全部场景名，含 --read-url 下追加在最后的 replica_lag
+fn runnable_names(cfg: &BenchConfig) -> Vec<String> {
+    let mut names: Vec<String> = available_scenarios(cfg).into_iter().map(|sc| sc.name).collect();
+    if cfg.read_url.is_some() {
+        names.push(REPLICA_LAG_MARKER.to_string());
+    }
+    names
+}
+
+/// --scenario/--exclude 中的名字必须是本次可运行的场景（含 --read-url 下的 replica_lag），过滤后不能为空
+fn check_scenario_filter(cfg: &BenchConfig) -> Result<()> {
+    if cfg.include.is_empty() && cfg.exclude.is_empty() {
+        return Ok(());
+    }
+    let names = runnable_names(cfg);
+    for (flag, list) in [("--scenario", &cfg.include), ("--exclude", &cfg.exclude)] {
+        if let Some(name) = list.iter().find(|n| !names.contains(n)) {
+            bail!(
+                "{} 中的场景 {} 不存在（写场景需要 --allow-writes），可选: {}",
+                flag,
+                name,
+                names.join(", ")
+            );
+        }
+    }
+    if !names.iter().any(|name| scenario_selected(cfg, name)) {
+        bail!("按 --scenario/--exclude 过滤后没有要运行的场景");
+    }
+    Ok(())
+}
+
+fn default_fetch() -> Fetch {
+    Fetch::All
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    scenario: Vec<Scenario>,
+}
+
+/// 读取 --scenarios 指定的 TOML 文件，在连接数据库之前校验每个场景
+pub fn load_scenarios(path: &Path) -> Result<Vec<Scenario>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("读取场景文件 {:?} 失败", path))?;
+    let file: ScenarioFile =
+        toml::from_str(&text).with_context(|| format!("解析场景文件 {:?} 失败", path))?;
+    if file.scenario.is_empty() {
+        bail!("场景文件 {:?} 中没有 [[scenario]] 条目", path);
+    }
+    for sc in &file.scenario {
+        check_scenario(sc).with_context(|| format!("场景文件 {:?} 中的场景 {} 无效", path, sc.name))?;
+    }
+    let names: Vec<&str> = file.scenario.iter().map(|sc| sc.name.as_str()).collect();
+    check_unique_names(&names).with_context(|| format!("场景文件 {:?} 无效", path))?;
+    Ok(file.scenario)
+}
+
+/// 占位符个数必须与参数类型一致：MySQL 每个值一个 `?`，Postgres 用 `$1` 绑定单值或整个数组
+fn check_scenario(sc: &Scenario) -> Result<()> {
+    if sc.name.trim().is_empty() {
+        bail!("name 不能为空");
+    }
+    if sc.mysql_sql.trim().is_empty() || sc.postgres_sql.trim().is_empty() {
+        bail!("mysql_sql 与 postgres_sql 都不能为空");
+    }
+    let values = match sc.param {
+        ParamKind::None => 0,
+        ParamKind::PkHit | ParamKind::HotPk | ParamKind::UserHit | ParamKind::Status | ParamKind::Category => 1,
+        ParamKind::PkList(0) | ParamKind::TimeList(0) => bail!("列表参数的长度必须大于 0"),
+        ParamKind::PkList(n) | ParamKind::TimeList(n) => n,
+        ParamKind::Insert | ParamKind::AmountPk | ParamKind::Upsert => bail!("param {:?} 只用于内置场景", sc.param),
+    };
+    let placeholders = sc.mysql_sql.matches('?').count();
+    if placeholders != values {
+        bail!(
+            "param {:?} 需要 {} 个值，mysql_sql 中有 {} 个 ?",
+            sc.param,
+            values,
+            placeholders
+        );
+    }
+    match (values > 0, sc.postgres_sql.contains("$1")) {
+        (true, false) => bail!("param {:?} 需要绑定参数，postgres_sql 中没有 $1", sc.param),
+        (false, true) => bail!("param 为 none，postgres_sql 却引用了 $1"),
+        _ => {}
+    }
+    if sc.postgres_sql.contains("$2") {
+        bail!("postgres_sql 只支持一个参数 $1，列表参数整体绑定为数组（如 = ANY($1)）");
+    }
+    if sc.rows_per_op == Some(0) {
+        bail!("rows_per_op 必须大于 0");
+    }
+    Ok(())
+}
+
+/// 结果、执行计划基线都按场景名区分，重名会互相覆盖
+fn check_unique_names<S: AsRef<str>>(names: &[S]) -> Result<()> {
+    let mut seen = std::collections::HashSet::new();
+    for name in names.iter().map(AsRef::as_ref) {
+        if !seen.insert(name) {
+            bail!("场景名 {} 重复", name);
+        }
+    }
+    Ok(())
+}
+
+/// 时间窗口的 (MySQL, Postgres) INTERVAL 写法；整天时按天写，默认 30 天跨度下与固定窗口时的语句一致
+fn interval_sql(secs: i64) -> (String, String) {
+    if secs % (24 * 3600) == 0 {
+        let days = secs / (24 * 3600);
+        (format!("INTERVAL {} DAY", days), format!("INTERVAL '{} day'", days))
+    } else {
+        (format!("INTERVAL {} SECOND", secs), format!("INTERVAL '{} second'", secs))
+    }
+}
+
+fn scenarios(allow_writes: bool, allow_deletes: bool, time_span_days: u64, payload_type: PayloadType) -> Vec<Scenario> {
+    // range_small 查最近 1/30 的跨度，range_large 查整个跨度
+    let span_secs = time_span_days as i64 * 24 * 3600;
+    let (my_small, pg_small) = interval_sql(span_secs / 30);
+    let (my_large, pg_large) = interval_sql(span_secs);
+    let mut scenarios = vec![
+        Scenario {
+            name: "pk_hit".to_string(),
+            mysql_sql: "SELECT id FROM events WHERE id = ?".to_string(),
+            postgres_sql: "SELECT id FROM events WHERE id = $1".to_string(),
+            param: ParamKind::PkHit,
+            fetch: Fetch::First,
+            rows_per_op: None,
+        },
+        Scenario {
+            name: "user_lookup".to_string(),
+            mysql_sql: "SELECT id FROM events WHERE user_id = ? ORDER BY created_at DESC LIMIT 1".to_string(),
+            postgres_sql: "SELECT id FROM events WHERE user_id = $1 ORDER BY created_at DESC LIMIT 1".to_string(),
+            param: ParamKind::UserHit,
+            fetch: Fetch::First,
+            rows_per_op: None,
+        },
+        Scenario {
+            name: "range_small".to_string(),
+            mysql_sql: format!("SELECT id FROM events WHERE created_at BETWEEN DATE_SUB(NOW(), {}) AND NOW() ORDER BY created_at DESC LIMIT 50", my_small),
+            postgres_sql: format!("SELECT id FROM events WHERE created_at BETWEEN (NOW() - {}) AND NOW() ORDER BY created_at DESC LIMIT 50", pg_small),
+            param: ParamKind::None,
+            fetch: Fetch::All,
+            rows_per_op: None,
+        },
+        Scenario {
+            name: "range_large".to_string(),
+            mysql_sql: format!("SELECT id FROM events WHERE created_at BETWEEN DATE_SUB(NOW(), {}) AND NOW() ORDER BY created_at DESC LIMIT 200", my_large),
+            postgres_sql: format!("SELECT id FROM events WHERE created_at BETWEEN (NOW() - {}) AND NOW() ORDER BY created_at DESC LIMIT 200", pg_large),
+            param: ParamKind::None,
+            fetch: Fetch::All,
+            rows_per_op: None,
+        },
+        Scenario {
+            name: "order_page".to_string(),
+            mysql_sql: "SELECT id FROM events ORDER BY created_at DESC LIMIT 50 OFFSET 100".to_string(),
+            postgres_sql: "SELECT id FROM events ORDER BY created_at DESC LIMIT 50 OFFSET 100".to_string(),
+            param: ParamKind::None,
+            fetch: Fetch::All,
+            rows_per_op: None,
+        },
+        Scenario {
+            name: "pk_in_list".to_string(),
+            mysql_sql: "SELECT id FROM events WHERE id IN (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)".to_string(),
+            postgres_sql: "SELECT id FROM events WHERE id = ANY($1)".to_string(),
+            param: ParamKind::PkList(IN_LIST_SIZE),
+            fetch: Fetch::All,
+            rows_per_op: None,
+        },
+        Scenario {
+            name: "created_in_list".to_string(),
+            mysql_sql: "SELECT id FROM events WHERE created_at IN (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) LIMIT 50".to_string(),
+            postgres_sql: "SELECT id FROM events WHERE created_at = ANY($1) LIMIT 50".to_string(),
+            param: ParamKind::TimeList(IN_LIST_SIZE),
+            fetch: Fetch::All,
+            rows_per_op: None,
+        },
+        // 聚合场景：参数按 int8 绑定，Postgres 侧显式转换，避免按 smallint/int 列推断参数类型
+        // status 只有 5 种取值，每次约计数全表的 1/5；走 idx_status 时可以只扫索引
+        Scenario {
+            name: "count_status".to_string(),
+            mysql_sql: "SELECT COUNT(*) FROM events WHERE status = ?".to_string(),
+            postgres_sql: "SELECT COUNT(*) FROM events WHERE status = $1::int8".to_string(),
+            param: ParamKind::Status,
+            fetch: Fetch::First,
+            rows_per_op: None,
+        },
+        // category 上没有索引，每次都是全表扫描后聚合；SUM(DECIMAL) 不是整数，按 All 读取结果
+        Scenario {
+            name: "sum_category".to_string(),
+            mysql_sql: "SELECT SUM(amount) FROM events WHERE category = ?".to_string(),
+            postgres_sql: "SELECT SUM(amount) FROM events WHERE category = $1::int8".to_string(),
+            param: ParamKind::Category,
+            fetch: Fetch::All,
+            rows_per_op: None,
+        },
+        // 自连接找同一用户的上一条事件：e1 走主键，e2 走 idx_user_created 按时间倒序取一行
+        Scenario {
+            name: "prev_event".to_string(),
+            mysql_sql: "SELECT e2.id FROM events e1 JOIN events e2 ON e2.user_id = e1.user_id AND e2.created_at < e1.created_at \
+                        WHERE e1.id = ? ORDER BY e2.created_at DESC LIMIT 1"
+                .to_string(),
+            postgres_sql: "SELECT e2.id FROM events e1 JOIN events e2 ON e2.user_id = e1.user_id AND e2.created_at < e1.created_at \
+                           WHERE e1.id = $1 ORDER BY e2.created_at DESC LIMIT 1"
+                .to_string(),
+            param: ParamKind::PkHit,
+            fetch: Fetch::First,
+            rows_per_op: None,
+        },
+        // 用户的 amount 为 NULL 的行：idx_user_created 定位后逐行判断 IS NULL；--null-rate 为 0 装载的数据返回空集
+        Scenario {
+            name: "null_amount".to_string(),
+            mysql_sql: "SELECT id FROM events WHERE user_id = ? AND amount IS NULL".to_string(),
+            postgres_sql: "SELECT id FROM events WHERE user_id = $1 AND amount IS NULL".to_string(),
+            param: ParamKind::UserHit,
+            fetch: Fetch::All,
+            rows_per_op: None,
+        },
+    ];
+    // payload 为 TEXT 时 Postgres 没有 ->> 运算符，只在 JSON 列下加入
+    if payload_type == PayloadType::Json {
+        scenarios.push(Scenario {
+            name: "json_event".to_string(),
+            mysql_sql: "SELECT id FROM events WHERE user_id = ? AND payload->>'$.event' = 'purchase'".to_string(),
+            postgres_sql: "SELECT id FROM events WHERE user_id = $1 AND payload->>'event' = 'purchase'".to_string(),
+            param: ParamKind::UserHit,
+            fetch: Fetch::All,
+            rows_per_op: None,
+        });
+    }
+    // 参数按 TEXT 绑定，JSONB 列需要先转成 text 再显式转换，否则服务端推断出 jsonb 参数类型后无法绑定字符串
+    let pg_payload = match payload_type {
+        PayloadType::Text => "",
+        PayloadType::Json => "::text::jsonb",
+    };
+    if allow_writes {
+        // 所有 worker 争抢同一小批行的行锁，模拟计数器/库存一类的热点更新
+        scenarios.push(Scenario {
+            name: "hot_update".to_string(),
+            mysql_sql: "UPDATE events SET category = category + 1 WHERE id = ?".to_string(),
+            postgres_sql: "UPDATE events SET category = category + 1 WHERE id = $1".to_string(),
+            param: ParamKind::HotPk,
+            fetch: Fetch::None,
+            rows_per_op: Some(1),
+        });
+        // 改写非索引列 amount：InnoDB 可原地更新，Postgres 视 fillfactor 可能走 HOT 更新
+        scenarios.push(Scenario {
+            name: "update_amount".to_string(),
+            mysql_sql: "UPDATE events SET amount = ? WHERE id = ?".to_string(),
+            postgres_sql: "UPDATE events SET amount = $1::float8 WHERE id = $2".to_string(),
+            param: ParamKind::AmountPk,
+            fetch: Fetch::None,
+            rows_per_op: Some(1),
+        });
+        // 放在最后：写入的行在场景结束后删除，不会被之前的读场景看到
+        scenarios.push(Scenario {
+            name: "insert".to_string(),
+            mysql_sql: "INSERT INTO events (user_id, created_at, amount, status, category, payload) \
+                        VALUES (?, ?, ?, ?, ?, ?)"
+                .to_string(),
+            postgres_sql: format!(
+                "INSERT INTO events (user_id, created_at, amount, status, category, payload) \
+                 VALUES ($1, $2, $3::float8, $4, $5, $6{})",
+                pg_payload
+            ),
+            param: ParamKind::Insert,
+            fetch: Fetch::None,
+            rows_per_op: Some(1),
+        });
+        // 冲突时只改 amount；主键为 NULL 时走插入分支，MySQL 由自增分配，Postgres 取 events 的序列。
+        // 新行与 insert 一样在场景结束后删除
+        scenarios.push(Scenario {
+            name: "upsert".to_string(),
+            mysql_sql: "INSERT INTO events (id, user_id, created_at, amount, status, category, payload) \
+                        VALUES (?, ?, ?, ?, ?, ?, ?) ON DUPLICATE KEY UPDATE amount = VALUES(amount)"
+                .to_string(),
+            postgres_sql: format!(
+                "INSERT INTO events (id, user_id, created_at, amount, status, category, payload) \
+                 VALUES (COALESCE($1, nextval(pg_get_serial_sequence('public.events', 'id'))), \
+                 $2, $3, $4::float8, $5, $6, $7{}) \
+                 ON CONFLICT (id) DO UPDATE SET amount = EXCLUDED.amount",
+                pg_payload
+            ),
+            param: ParamKind::Upsert,
+            fetch: Fetch::None,
+            rows_per_op: Some(1),
+        });
+    }
+    if allow_writes && allow_deletes {
+        // 永久删除随机行，必须最后运行；重复抽到已删的主键时删除 0 行，仍计为一次操作
+        scenarios.push(Scenario {
+            name: "delete_pk".to_string(),
+            mysql_sql: "DELETE FROM events WHERE id = ?".to_string(),
+            postgres_sql: "DELETE FROM events WHERE id = $1".to_string(),
+            param: ParamKind::PkHit,
+            fetch: Fetch::None,
+            rows_per_op: Some(1),
+        });
+    }
+    scenarios
+}
+
+/// 一个场景的一轮运行；--compare-prepared、--ramp 与 --repeat 会让同一场景以不同的语句模式、并发数、seed 跑多轮
+#[derive(Debug, Clone, Copy)]
+struct Round {
+    mode: StatementMode,
+    concurrency: usize,
+    /// 本轮的种子，各 worker 的 RNG 种子由 worker_seed 派生
+    seed: u64,
+    /// --repeat 大于 1 时为第几次重复（从 1 计）
+    repeat: Option<usize>,
+}
+
+/// 由一轮的种子派生 worker 的 RNG 种子。先把轮种子打散再混入 worker 号：直接用 seed + worker_id 的话，
+/// 第 i 次重复的 worker j 与第 i + 1 次重复的 worker j - 1 会拿到同一个种子，各次重复的参数序列大段重叠
+fn worker_seed(seed: u64, worker_id: u64) -> u64 {
+    splitmix64(splitmix64(seed) ^ worker_id)
+}
+
+/// SplitMix64 的混合函数：相邻的输入得到互不相关的输出
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// 一轮的 span：其间的日志（含挂在它下面的各 worker span）都带上库、场景与并发数，JSON 日志据此关联
+fn round_span(db: &'static str, scenario: &str, round: Round) -> tracing::Span {
+    tracing::info_span!(
+        "round",
+        db,
+        scenario,
+        mode = ?round.mode,
+        concurrency = round.concurrency,
+        repeat = round.repeat
+    )
+}
+
+/// 开启 --timeseries 时为这一轮启动每秒一次的吞吐采样
+fn start_series(
+    cfg: &BenchConfig,
+    db: &'static str,
+    scenario: &str,
+    round: Round,
+    progress: &Arc<AtomicU64>,
+    start: Instant,
+) -> Result<Option<SeriesRecorder>> {
+    let Some(path) = &cfg.timeseries else {
+        return Ok(None);
+    };
+    let label = SeriesLabel {
+        db,
+        scenario: scenario.to_string(),
+        concurrency: cfg.ramp.is_some().then_some(round.concurrency),
+        repeat: round.repeat,
+    };
+    SeriesRecorder::start(path, label, progress.clone(), start).map(Some)
+}
+
+/// 一轮要跑的负载：单场景时只有一个场景；混合负载按 chooser 的权重每次操作选一个
+struct Workload {
+    db: &'static str,
+    /// 日志、进度条与重连提示里的名字：单场景为场景名，混合负载为 mix
+    label: String,
+    scenarios: Arc<Vec<Scenario>>,
+    chooser: Option<MixChooser>,
+}
+
+impl Workload {
+    fn single(db: &'static str, sc: &Scenario) -> Self {
+        Self {
+            db,
+            label: sc.name.clone(),
+            scenarios: Arc::new(vec![sc.clone()]),
+            chooser: None,
+        }
+    }
+
+    fn mix(db: &'static str, parts: &[(Scenario, u32)]) -> Self {
+        Self {
+            db,
+            label: "mix".to_string(),
+            scenarios: Arc::new(parts.iter().map(|(sc, _)| sc.clone()).collect()),
+            chooser: Some(MixChooser::new(parts)),
+        }
+    }
+}
+
+/// 一轮结束后合并好的样本
+struct RoundRun {
+    samples: RoundSamples,
+    bytes: u64,
+    /// 扣除暂停后的墙钟秒数
+    wall: f64,
+}
+
+/// 启动 round.concurrency 个 worker 跑完预热与采样，单场景与混合负载、两种数据库共用，
+/// 区别只在每次操作选哪个场景。opener 为 workload 的每个场景准备语句，重连时原样再调一次
+async fn run_workers<O: WorkerOpener>(
+    workload: &Workload,
+    cfg: &BenchConfig,
+    space: &Arc<ParamSpace>,
+    round: Round,
+    gate: &PauseGate,
+    opener: O,
+) -> Result<RoundRun> {
+    if let Some(coverage) = &space.coverage {
+        coverage.reset();
+    }
+    let Round { concurrency, seed, .. } = round;
+    let db = workload.db;
+    let workers = concurrency.max(1) as u64;
+    let warm_base = cfg.warmup_ops / workers;
+    let warm_rem = cfg.warmup_ops % workers;
+    let sample_base = cfg.sample_ops / workers;
+    let sample_rem = cfg.sample_ops % workers;
+
+    let mut tasks = JoinSet::new();
+    let bytes_total = Arc::new(AtomicU64::new(0));
+    let progress = Arc::new(AtomicU64::new(0));
+    let bar = progress_bar::new(cfg.duration.is_none().then_some(cfg.sample_ops), &workload.label, "次");
+    let scenario_start = Instant::now();
+    let series = start_series(cfg, db, &workload.label, round, &progress, scenario_start)?;
+    let paused_before = gate.paused_total();
+    let limiter = cfg.target_qps.map(RateLimiter::new);
+    for worker_id in 0..workers {
+        let warm = warm_base + if worker_id < warm_rem { 1 } else { 0 };
+        let limit = match cfg.duration {
+            Some(d) => SampleLimit::Elapsed(d),
+            None => SampleLimit::Ops(sample_base + if worker_id < sample_rem { 1 } else { 0 }),
+        };
+        let opener = opener.clone();
+        let label = workload.label.clone();
+        let scenarios = workload.scenarios.clone();
+        let chooser = workload.chooser.clone();
+        let bytes_total = bytes_total.clone();
+        let progress = progress.clone();
+        let bar = bar.clone();
+        let space = space.clone();
+        let ignore_warmup_errors = cfg.ignore_warmup_errors;
+        let reconnect = cfg.reconnect();
+        let retry = cfg.retry;
+        let continue_on_error = cfg.continue_on_error;
+        let limiter = limiter.clone();
+        let mut gate = gate.clone();
+        tasks.spawn(async move {
+            let open = || opener.open(worker_id);
+            let mut worker = open().await?;
+            // 场景的选择与参数采样共用一个 RNG，同一 --seed 下每个 worker 的操作序列可复现
+            let mut sampler = Sampler::new(worker_seed(seed, worker_id), &space, &scenarios);
+            let pick = |sampler: &mut Sampler| chooser.as_ref().map_or(0, |chooser| chooser.pick(&mut sampler.rng));
+            let mut samples = RoundSamples::new(scenarios.len());
+            // warmup
+            let mut warm_errors = 0u64;
+            for _ in 0..warm {
+                gate.wait().await;
+                if shutdown::requested() {
+                    break;
+                }
+                let i = pick(&mut sampler);
+                let start = Instant::now();
+                match worker.exec(i, &scenarios[i], &mut sampler, &space).await {
+                    Ok(_) => samples.all.warmup.record(start.elapsed().as_nanos() as u64),
+                    Err(e) if ignore_warmup_errors => {
+                        warm_errors += 1;
+                        tracing::debug!("scenario={} worker={} 预热出错: {:#}", scenarios[i].name, worker_id, e);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            if warm_errors > 0 {
+                tracing::warn!("scenario={} {} worker={} 预热期间忽略了 {} 次错误", label, db, worker_id, warm_errors);
+            }
+
+            let sample_start = Instant::now();
+            let paused_at_start = gate.paused_total();
+            let mut attempted = 0u64;
+            loop {
+                gate.wait().await;
+                if !limit.more(attempted, sample_start, &gate, paused_at_start) {
+                    break;
+                }
+                attempted += 1;
+                let i = pick(&mut sampler);
+                let sc = &scenarios[i];
+                let intended = match &limiter {
+                    Some(limiter) => Some(limiter.acquire(&gate).await),
+                    None => None,
+                };
+                let start = Instant::now();
+                let mut retries = 0;
+                let res = loop {
+                    match worker.exec(i, sc, &mut sampler, &space).await {
+                        Err(e) if retries < retry.max_retries && is_transient_error(&e) => {
+                            retries += 1;
+                            tracing::debug!("scenario={} {} worker={} 第 {} 次重试: {:#}", sc.name, db, worker_id, retries, e);
+                            tokio::time::sleep(retry.delay(retries)).await;
+                        }
+                        res => break res,
+                    }
+                };
+                samples.all.retries += retries as u64;
+                let fetched = match res {
+                    Ok(fetched) => fetched,
+                    Err(e) if reconnect.attempts > 0 && is_connection_error(&e) => {
+                        samples.all.loss.lost_ops += 1;
+                        tracing::warn!("scenario={} {} worker={} 连接断开，准备重连: {:#}", label, db, worker_id, e);
+                        worker = reconnect.retry(&label, worker_id, open).await?;
+                        samples.all.loss.reconnects += 1;
+                        continue;
+                    }
+                    Err(e) if continue_on_error => {
+                        samples.all.errors.record(&e, &sc.name, worker_id);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                let end = Instant::now();
+                let origin = intended.unwrap_or(start);
+                let (latency, service) = corrected(intended, start, end);
+                samples.all.durations.record(latency);
+                samples.parts[i].record(latency);
+                if let Some(service) = service {
+                    samples.all.service.record(service);
+                }
+                if let Some(fetched) = fetched {
+                    let first_ns = fetched.first_row.duration_since(origin).as_nanos() as u64;
+                    samples.all.first_row.record(first_ns);
+                    bytes_total.fetch_add(fetched.bytes, Ordering::Relaxed);
+                }
+                let done = progress.fetch_add(1, Ordering::Relaxed) + 1;
+                if let Some(bar) = &bar {
+                    bar.inc(1);
+                } else if done.is_multiple_of(500) {
+                    let rps = done as f64 / scenario_start.elapsed().as_secs_f64().max(0.001);
+                    tracing::info!("scenario={} {} 已完成 {} 次采样, {:.2} ops/s", label, db, done, rps);
+                }
+            }
+
+            worker.close().await?;
+            Ok::<RoundSamples, anyhow::Error>(samples)
+        }.instrument(tracing::info_span!("worker", worker = worker_id)));
+    }
+
+    // 样本留在各 worker 本地，结束后再合并，采样热路径上没有锁
+    let mut samples = RoundSamples::new(workload.scenarios.len());
+    while let Some(res) = tasks.join_next().await {
+        samples.merge(res??);
+    }
+    if let Some(series) = series {
+        series.finish().await?;
+    }
+    let wall = scenario_elapsed(&workload.label, scenario_start, gate, paused_before);
+    Ok(RoundRun {
+        samples,
+        bytes: bytes_total.load(Ordering::Relaxed),
+        wall,
+    })
+}
+
+async fn run_mysql_scenario(
+    pool: &mysql_async::Pool,
+    sc: &Scenario,
+    cfg: &BenchConfig,
+    space: &Arc<ParamSpace>,
+    round: Round,
+    pooler: Pooler,
+    gate: &PauseGate,
+) -> Result<BenchResult> {
+    let workload = Workload::single("mysql", sc);
+    let opener = MysqlOpener::new(pool, &workload, round.mode, pooler, cfg.queries_per_roundtrip, cfg.session());
+    let run = run_workers(&workload, cfg, space, round, gate, opener).await?;
+    Ok(scenario_result(sc, cfg, round, run))
+}
+
+async fn run_postgres_scenario(
+    db: &DbConfig,
+    sc: &Scenario,
+    cfg: &BenchConfig,
+    space: &Arc<ParamSpace>,
+    round: Round,
+    gate: &PauseGate,
+) -> Result<BenchResult> {
+    let workload = Workload::single("postgres", sc);
+    let opener = PgOpener::new(db, &workload, round.mode, cfg.queries_per_roundtrip, cfg.session());
+    let run = run_workers(&workload, cfg, space, round, gate, opener).await?;
+    Ok(scenario_result(sc, cfg, round, run))
+}
+
+fn scenario_result(sc: &Scenario, cfg: &BenchConfig, round: Round, run: RoundRun) -> BenchResult {
+    let RoundRun { samples, bytes, wall } = run;
+    let samples = samples.all;
+    let stats = samples.durations.summary(&cfg.percentiles.0);
+    tracing::debug!(
+        "scenario={} 共 {} 个样本，延迟(ns) min {:.0} max {:.0} stddev {:.0}",
+        sc.name,
+        stats.count,
+        stats.min,
+        stats.max,
+        stats.stddev
+    );
+    let first_row = first_row_latency(sc, &samples.first_row, cfg);
+    let service = cfg.target_qps.is_some().then(|| Latency {
+        stats: samples.service.summary(&cfg.percentiles.0),
+        unit: cfg.unit,
+    });
+    let per_query = (cfg.queries_per_roundtrip > 1).then(|| Latency {
+        stats: stats.amortized(cfg.queries_per_roundtrip as f64),
+        unit: cfg.unit,
+    });
+    // 重连时丢失的操作不算完成
+    let ops = stats.count as u64;
+    let throughput = ops as f64 / wall.max(0.001);
+    warn_below_target(&sc.name, throughput, cfg.target_qps);
+    samples.errors.warn(&sc.name);
+
+    BenchResult {
+        scenario: sc.name.to_string(),
+        statement_mode: cfg.compare_prepared.then_some(round.mode),
+        concurrency: cfg.ramp.is_some().then_some(round.concurrency.max(1)),
+        repeat: None,
+        ops,
+        throughput_ops: throughput,
+        rows_per_sec: sc.rows_per_op.map(|rows| throughput * rows as f64),
+        bytes_per_sec: (sc.fetch == Fetch::All && cfg.queries_per_roundtrip == 1)
+            .then(|| bytes as f64 / wall.max(0.001)),
+        latency: Latency {
+            stats,
+            unit: cfg.unit,
+        },
+        first_row,
+        service,
+        per_query,
+        warmup: warmup_latency(&samples.warmup, cfg),
+        timeouts: None,
+        connection_loss: (cfg.reconnect_attempts > 0).then_some(samples.loss),
+        retries: (cfg.retry.max_retries > 0).then_some(samples.retries),
+        errors: cfg.continue_on_error.then_some(samples.errors.total()),
+        rollbacks: cfg.continue_on_error.then_some(samples.errors.rollback),
+        error_rate: cfg.continue_on_error.then(|| samples.errors.rate(ops)),
+        breakdown: None,
+        server: None,
+        plan: None,
+    }
+}
+
+fn warmup_latency(hist: &LatencyHistogram, cfg: &BenchConfig) -> Option<Latency> {
+    cfg.report_warmup.then(|| Latency {
+        stats: hist.summary(&cfg.percentiles.0),
+        unit: cfg.unit,
+    })
+}
+
+/// 按 --mix 的名字取出场景，附带权重
+fn resolve_mix(mix: &MixSpec, scenarios: Vec<Scenario>) -> Result<Vec<(Scenario, u32)>> {
+    let mut parts = Vec::with_capacity(mix.0.len());
+    for (name, weight) in &mix.0 {
+        let Some(sc) = scenarios.iter().find(|sc| &sc.name == name) else {
+            let names: Vec<&str> = scenarios.iter().map(|sc| sc.name.as_str()).collect();
+            bail!(
+                "--mix 中的场景 {} 不存在（写场景需要 --allow-writes），可选: {}",
+                name,
+                names.join(", ")
+            );
+        };
+        parts.push((sc.clone(), *weight));
+    }
+    Ok(parts)
+}
+
+/// 按权重选场景：累计权重上均匀取点
+#[derive(Debug, Clone)]
+struct MixChooser {
+    cumulative: Vec<u32>,
+}
+
+impl MixChooser {
+    fn new(parts: &[(Scenario, u32)]) -> Self {
+        let cumulative = parts
+            .iter()
+            .scan(0u32, |acc, (_, w)| {
+                *acc += w;
+                Some(*acc)
+            })
+            .collect();
+        Self { cumulative }
+    }
+
+    fn pick(&self, rng: &mut StdRng) -> usize {
+        let total = self.cumulative[self.cumulative.len() - 1];
+        let x = rng.gen_range(0..total);
+        self.cumulative.partition_point(|&c| c <= x)
+    }
+}
+
+/// 单个 worker 的样本：all 为全部操作，parts 按场景下标分别累计，单场景时只有一项
+#[derive(Debug, Default)]
+struct RoundSamples {
+    all: WorkerSamples,
+    parts: Vec<LatencyHistogram>,
+}
+
+impl RoundSamples {
+    fn new(parts: usize) -> Self {
+        Self {
+            all: WorkerSamples::default(),
+            parts: (0..parts).map(|_| LatencyHistogram::new()).collect(),
+        }
+    }
+
+    fn merge(&mut self, other: RoundSamples) {
+        self.all.merge(other.all);
+        for (mine, theirs) in self.parts.iter_mut().zip(&other.parts) {
+            mine.merge(theirs);
+        }
+    }
+}
+
+/// 混合负载：每个 worker 在一条连接上按权重随机选场景执行。预热与采样次数、--duration、
+/// --target-qps、暂停与重连的语义都与单场景相同，只是每次操作的场景不同
+async fn run_mysql_mix(
+    pool: &mysql_async::Pool,
+    parts: &[(Scenario, u32)],
+    cfg: &BenchConfig,
+    space: &Arc<ParamSpace>,
+    round: Round,
+    pooler: Pooler,
+    gate: &PauseGate,
+) -> Result<BenchResult> {
+    let workload = Workload::mix("mysql", parts);
+    let opener = MysqlOpener::new(pool, &workload, round.mode, pooler, 1, cfg.session());
+    let run = run_workers(&workload, cfg, space, round, gate, opener).await?;
+    Ok(mix_result(parts, run.samples, cfg, round.concurrency.max(1), cfg.target_qps.is_some(), run.wall))
+}
+
+async fn run_postgres_mix(
+    db: &DbConfig,
+    parts: &[(Scenario, u32)],
+    cfg: &BenchConfig,
+    space: &Arc<ParamSpace>,
+    round: Round,
+    gate: &PauseGate,
+) -> Result<BenchResult> {
+    let workload = Workload::mix("postgres", parts);
+    let opener = PgOpener::new(db, &workload, round.mode, 1, cfg.session());
+    let run = run_workers(&workload, cfg, space, round, gate, opener).await?;
+    Ok(mix_result(parts, run.samples, cfg, round.concurrency.max(1), cfg.target_qps.is_some(), run.wall))
+}
+
+fn mix_result(
+    parts: &[(Scenario, u32)],
+    samples: RoundSamples,
+    cfg: &BenchConfig,
+    workers: usize,
+    limited: bool,
+    wall: f64,
+) -> BenchResult {
+    let stats = samples.all.durations.summary(&cfg.percentiles.0);
+    let ops = stats.count as u64;
+    let throughput = ops as f64 / wall.max(0.001);
+    warn_below_target("mix", throughput, cfg.target_qps);
+    samples.all.errors.warn("mix");
+    let total_weight: u32 = parts.iter().map(|(_, w)| w).sum();
+    let breakdown = parts
+        .iter()
+        .zip(&samples.parts)
+        .map(|((sc, weight), hist)| MixPart {
+            scenario: sc.name.clone(),
+            weight: *weight as f64 / total_weight as f64,
+            ops: hist.len(),
+            latency: Latency {
+                stats: hist.summary(&cfg.percentiles.0),
+                unit: cfg.unit,
+            },
+            plan: None,
+        })
+        .collect();
+    BenchResult {
+        scenario: "mix".to_string(),
+        statement_mode: None,
+        concurrency: cfg.ramp.is_some().then_some(workers),
+        repeat: None,
+        ops,
+        throughput_ops: throughput,
+        rows_per_sec: None,
+        bytes_per_sec: None,
+        latency: Latency {
+            stats,
+            unit: cfg.unit,
+        },
+        first_row: None,
+        service: limited.then(|| Latency {
+            stats: samples.all.service.summary(&cfg.percentiles.0),
+            unit: cfg.unit,
+        }),
+        per_query: None,
+        warmup: warmup_latency(&samples.all.warmup, cfg),
+        timeouts: None,
+        connection_loss: (cfg.reconnect_attempts > 0).then_some(samples.all.loss),
+        retries: (cfg.retry.max_retries > 0).then_some(samples.all.retries),
+        errors: cfg.continue_on_error.then_some(samples.all.errors.total()),
+        rollbacks: cfg.continue_on_error.then_some(samples.all.errors.rollback),
+        error_rate: cfg.continue_on_error.then(|| samples.all.errors.rate(ops)),
+        breakdown: Some(breakdown),
+        server: None,
+        plan: None,
+    }
+}
+
+/// 每个 worker 一份的采样状态，由 worker 种子派生，同一 --seed 下序列可复现。
+/// insert/upsert 的行生成器整个 worker 只建一次，在开始计时前建好
+struct Sampler {
+    rng: StdRng,
+    seed: u64,
+    rows: Option<EventGenerator>,
+}
+
+impl Sampler {
+    fn new(seed: u64, space: &ParamSpace, scenarios: &[Scenario]) -> Self {
+        let mut sampler = Self {
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            rows: None,
+        };
+        if scenarios.iter().any(|sc| matches!(sc.param, ParamKind::Insert | ParamKind::Upsert)) {
+            sampler.rows(&space.rows);
+        }
+        sampler
+    }
+
+    fn rows(&mut self, cfg: &GeneratorConfig) -> &mut EventGenerator {
+        let seed = splitmix64(self.seed);
+        self.rows.get_or_insert_with(|| EventGenerator::with_seed(cfg.clone(), seed))
+    }
+}
+
+fn sample_param(kind: ParamKind, sampler: &mut Sampler, space: &ParamSpace) -> ParamValue {
+    let rng = &mut sampler.rng;
+    match kind {
+        ParamKind::None => ParamValue::None,
+        ParamKind::PkHit => match &space.coverage {
+            Some(coverage) => ParamValue::Int(coverage.next_id()),
+            None => ParamValue::Int(rng.gen_range(1..=space.max_id as i64)),
+        },
+        ParamKind::HotPk => ParamValue::Int(rng.gen_range(1..=space.hot_rows as i64)),
+        ParamKind::UserHit => ParamValue::Int(space.users.sample(rng)),
+        ParamKind::Status => ParamValue::Int(rng.gen_range(0..STATUS_VALUES as i64)),
+        ParamKind::Category => ParamValue::Int(rng.gen_range(0..=5000)),
+        ParamKind::PkList(n) => {
+            ParamValue::IntList((0..n).map(|_| rng.gen_range(1..=space.max_id as i64)).collect())
+        }
+        ParamKind::TimeList(n) => {
+            // 与生成器一致：最近 time_span_days 天内的随机秒
+            let now = Utc::now().naive_utc().with_nanosecond(0).unwrap_or_default();
+            ParamValue::TimeList(
+                (0..n)
+                    .map(|_| now - ChronoDuration::seconds(rng.gen_range(0..space.time_span_secs)))
+                    .collect(),
+            )
+        }
+        ParamKind::AmountPk => {
+            let amount = (rng.gen_range(0.0f64..1000.0f64) * 100.0f64).round() / 100.0f64;
+            ParamValue::AmountPk(amount, rng.gen_range(1..=space.max_id as i64))
+        }
+        ParamKind::Insert => {
+            let mut row = sampler.rows(&space.rows).next_row();
+            row.category = Some(INSERT_MARKER_CATEGORY);
+            ParamValue::Row(Box::new(row))
+        }
+        ParamKind::Upsert => {
+            // 插入分支的主键交给数据库分配：显式写入的主键不会推进 Postgres 的序列，之后 insert 取到同样的值会冲突
+            let id = rng.gen_bool(0.5).then(|| rng.gen_range(1..=space.max_id as i64));
+            let mut row = sampler.rows(&space.rows).next_row();
+            row.category = Some(INSERT_MARKER_CATEGORY);
+            ParamValue::IdRow(id, Box::new(row))
+        }
+    }
+}
+
+impl ParamValue {
+    fn into_mysql_params(self) -> MyParams {
+        match self {
+            ParamValue::None => MyParams::Empty,
+            ParamValue::Int(v) => MyParams::Positional(vec![MyValue::Int(v)]),
+            ParamValue::IntList(ids) => {
+                MyParams::Positional(ids.into_iter().map(MyValue::Int).collect())
+            }
+            ParamValue::TimeList(times) => {
+                MyParams::Positional(times.iter().map(mysql_datetime).collect())
+            }
+            ParamValue::Row(row) => MyParams::Positional(vec![
+                MyValue::Int(row.user_id),
+                mysql_datetime(&row.created_at),
+                row.amount.map_or(MyValue::NULL, mysql_amount),
+                MyValue::Int(row.status as i64),
+                row.category.map_or(MyValue::NULL, |c| MyValue::Int(c as i64)),
+                MyValue::Bytes(row.payload.into_bytes()),
+            ]),
+            ParamValue::AmountPk(amount, id) => MyParams::Positional(vec![
+                mysql_amount(amount),
+                MyValue::Int(id),
+            ]),
+            ParamValue::IdRow(id, row) => MyParams::Positional(vec![
+                id.map_or(MyValue::NULL, MyValue::Int),
+                MyValue::Int(row.user_id),
+                mysql_datetime(&row.created_at),
+                row.amount.map_or(MyValue::NULL, mysql_amount),
+                MyValue::Int(row.status as i64),
+                row.category.map_or(MyValue::NULL, |c| MyValue::Int(c as i64)),
+                MyValue::Bytes(row.payload.into_bytes()),
+            ]),
+        }
+    }
+
+    fn pg_params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        match self {
+            ParamValue::None => Vec::new(),
+            ParamValue::Int(v) => vec![v],
+            ParamValue::IntList(ids) => vec![ids],
+            ParamValue::TimeList(times) => vec![times],
+            ParamValue::Row(row) => vec![
+                &row.user_id,
+                &row.created_at,
+                &row.amount,
+                &row.status,
+                &row.category,
+                &row.payload,
+            ],
+            ParamValue::AmountPk(amount, id) => vec![amount, id],
+            ParamValue::IdRow(id, row) => vec![
+                id,
+                &row.user_id,
+                &row.created_at,
+                &row.amount,
+                &row.status,
+                &row.category,
+                &row.payload,
+            ],
+        }
+    }
+
+    /// 渲染为可直接粘贴进 psql 的字面量，依次用于替换 `$1`、`$2`……
+    fn pg_literals(&self) -> Vec<String> {
+        let time = |t: &NaiveDateTime| format!("'{}'", t.format("%Y-%m-%d %H:%M:%S"));
+        match self {
+            ParamValue::None => Vec::new(),
+            ParamValue::Int(v) => vec![v.to_string()],
+            ParamValue::IntList(ids) => vec![format!(
+                "ARRAY[{}]::int8[]",
+                ids.iter().map(i64::to_string).collect::<Vec<_>>().join(", ")
+            )],
+            ParamValue::TimeList(times) => vec![format!(
+                "ARRAY[{}]::timestamp[]",
+                times.iter().map(time).collect::<Vec<_>>().join(", ")
+            )],
+            ParamValue::Row(row) => vec![
+                row.user_id.to_string(),
+                time(&row.created_at),
+                row.amount.map_or_else(|| "NULL".to_string(), |a| format!("{:.2}", a)),
+                row.status.to_string(),
+                row.category.map_or_else(|| "NULL".to_string(), |c| c.to_string()),
+                // 生成的 payload 只含字母数字，无需转义
+                format!("'{}'", row.payload),
+            ],
+            ParamValue::AmountPk(amount, id) => vec![format!("{:.2}", amount), id.to_string()],
+            ParamValue::IdRow(id, row) => vec![
+                id.map_or_else(|| "NULL".to_string(), |id| id.to_string()),
+                row.user_id.to_string(),
+                time(&row.created_at),
+                row.amount.map_or_else(|| "NULL".to_string(), |a| format!("{:.2}", a)),
+                row.status.to_string(),
+                row.category.map_or_else(|| "NULL".to_string(), |c| c.to_string()),
+                format!("'{}'", row.payload),
+            ],
+        }
+    }
+
+    /// query_typed 需要显式给出每个参数的类型
+    fn pg_typed_params(&self) -> Vec<(&(dyn ToSql + Sync), PgType)> {
+        match self {
+            ParamValue::None => Vec::new(),
+            ParamValue::Int(v) => vec![(v, PgType::INT8)],
+            ParamValue::IntList(ids) => vec![(ids, PgType::INT8_ARRAY)],
+            ParamValue::TimeList(times) => vec![(times, PgType::TIMESTAMP_ARRAY)],
+            ParamValue::Row(row) => vec![
+                (&row.user_id, PgType::INT8),
+                (&row.created_at, PgType::TIMESTAMP),
+                (&row.amount, PgType::FLOAT8),
+                (&row.status, PgType::INT2),
+                (&row.category, PgType::INT4),
+                (&row.payload, PgType::TEXT),
+            ],
+            ParamValue::AmountPk(amount, id) => vec![(amount, PgType::FLOAT8), (id, PgType::INT8)],
+            ParamValue::IdRow(id, row) => vec![
+                (id, PgType::INT8),
+                (&row.user_id, PgType::INT8),
+                (&row.created_at, PgType::TIMESTAMP),
+                (&row.amount, PgType::FLOAT8),
+                (&row.status, PgType::INT2),
+                (&row.category, PgType::INT4),
+                (&row.payload, PgType::TEXT),
+            ],
+        }
+    }
+}
+
+// MySQL ER_QUERY_TIMEOUT：max_execution_time 到期被中断
+const MYSQL_QUERY_TIMEOUT: u16 = 3024;
+
+/// 服务端因语句超时取消的错误加上明确说明，便于和其他错误区分
+fn label_statement_timeout(e: anyhow::Error) -> anyhow::Error {
+    let timed_out = match e.downcast_ref::<mysql_async::Error>() {
+        Some(mysql_async::Error::Server(err)) => err.code == MYSQL_QUERY_TIMEOUT,
+        _ => e
+            .downcast_ref::<tokio_postgres::Error>()
+            .and_then(|err| err.code())
+            .is_some_and(|code| *code == tokio_postgres::error::SqlState::QUERY_CANCELED),
+    };
+    if timed_out {
+        e.context("语句超时，已被服务端取消（--statement-timeout）")
+    } else {
+        e
+    }
+}
+
+// 重连退避的上限
+const RECONNECT_BACKOFF_MAX_MS: u64 = 10_000;
+
+impl ConnectionLoss {
+    fn add(&mut self, other: ConnectionLoss) {
+        self.lost_ops += other.lost_ops;
+        self.reconnects += other.reconnects;
+    }
+}
+
+/// 单个 worker 采样阶段收集的延迟直方图（纳秒），随任务结果返回
+#[derive(Debug, Default)]
+struct WorkerSamples {
+    durations: LatencyHistogram,
+    /// 预热阶段成功操作的延迟，只在 --report-warmup 时输出
+    warmup: LatencyHistogram,
+    first_row: LatencyHistogram,
+    service: LatencyHistogram,
+    loss: ConnectionLoss,
+    retries: u64,
+    errors: ErrorCounts,
+}
+
+impl WorkerSamples {
+    fn merge(&mut self, other: WorkerSamples) {
+        self.durations.merge(&other.durations);
+        self.warmup.merge(&other.warmup);
+        self.first_row.merge(&other.first_row);
+        self.service.merge(&other.service);
+        self.loss.add(other.loss);
+        self.retries += other.retries;
+        self.errors.add(other.errors);
+    }
+}
+
+/// --continue-on-error 时按类别统计失败的操作
+#[derive(Debug, Clone, Copy, Default)]
+struct ErrorCounts {
+    timeout: u64,
+    connection: u64,
+    rollback: u64,
+    query: u64,
+}
+
+impl ErrorCounts {
+    fn record(&mut self, e: &anyhow::Error, scenario: &str, worker_id: u64) {
+        let kind = classify_error(e);
+        match kind {
+            ErrorKind::Timeout => self.timeout += 1,
+            ErrorKind::Connection => self.connection += 1,
+            ErrorKind::Rollback => self.rollback += 1,
+            ErrorKind::Query => self.query += 1,
+        }
+        tracing::debug!("scenario={} worker={} 操作失败（{}），计数后继续: {:#}", scenario, worker_id, kind.label(), e);
+    }
+
+    fn add(&mut self, other: ErrorCounts) {
+        self.timeout += other.timeout;
+        self.connection += other.connection;
+        self.rollback += other.rollback;
+        self.query += other.query;
+    }
+
+    fn total(&self) -> u64 {
+        self.timeout + self.connection + self.rollback + self.query
+    }
+
+    fn rate(&self, ops: u64) -> f64 {
+        let total = self.total();
+        if total == 0 { 0.0 } else { total as f64 / (ops + total) as f64 }
+    }
+
+    fn warn(&self, scenario: &str) {
+        if self.total() > 0 {
+            tracing::warn!(
+                "scenario={} 采样期间 {} 次操作失败：超时 {}，连接 {}，回滚 {}，语句 {}",
+                scenario,
+                self.total(),
+                self.timeout,
+                self.connection,
+                self.rollback,
+                self.query
+            );
+        }
+    }
+}
+
+/// worker 的重连策略：最多 attempts 次，指数退避
+#[derive(Debug, Clone, Copy)]
+struct Reconnect {
+    attempts: u32,
+    backoff_ms: u64,
+}
+
+/// worker 连接的会话设置，建连与重连时原样应用
+#[derive(Debug, Clone, Copy)]
+struct Session {
+    statement_timeout_ms: Option<u64>,
+    isolation: Option<Isolation>,
+}
+
+impl BenchConfig {
+    fn reconnect(&self) -> Reconnect {
+        Reconnect {
+            attempts: self.reconnect_attempts,
+            backoff_ms: self.reconnect_backoff_ms,
+        }
+    }
+
+    fn session(&self) -> Session {
+        Session {
+            statement_timeout_ms: self.statement_timeout_ms,
+            isolation: self.isolation,
+        }
+    }
+}
+
+impl Reconnect {
+    /// 反复调用 open 直到成功；服务端重启期间连接会被拒绝，所以每次失败后等待时间翻倍
+    async fn retry<T, F, Fut>(self, scenario: &str, worker_id: u64, open: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let mut backoff_ms = self.backoff_ms;
+        for attempt in 1..=self.attempts {
+            tokio::time::sleep(std::time::Duration::from_millis(backoff_ms)).await;
+            match open().await {
+                Ok(v) => {
+                    tracing::info!("scenario={} worker={} 第 {} 次重连成功", scenario, worker_id, attempt);
+                    return Ok(v);
+                }
+                Err(e) if attempt < self.attempts => {
+                    backoff_ms = (backoff_ms * 2).min(RECONNECT_BACKOFF_MAX_MS);
+                    tracing::warn!(
+                        "scenario={} worker={} 第 {} 次重连失败，{}ms 后重试: {:#}",
+                        scenario,
+                        worker_id,
+                        attempt,
+                        backoff_ms,
+                        e
+                    );
+                }
+                Err(e) => {
+                    return Err(e.context(format!(
+                        "scenario={} worker={} 重连 {} 次均失败",
+                        scenario, worker_id, self.attempts
+                    )));
+                }
+            }
+        }
+        unreachable!("attempts 为 0 时不会进入重连")
+    }
+}
+
+/// 一个 worker 的连接与其上为各场景准备好的语句，下标与 Workload 的场景一致
+trait BenchWorker: Send + Sized + 'static {
+    fn exec(
+        &mut self,
+        i: usize,
+        sc: &Scenario,
+        sampler: &mut Sampler,
+        space: &ParamSpace,
+    ) -> impl std::future::Future<Output = Result<Option<Fetched>>> + Send;
+
+    fn close(self) -> impl std::future::Future<Output = Result<()>> + Send;
+}
+
+/// 建立 worker 连接：设置语句超时、隔离级别并按模式准备场景语句。重连时原样再走一遍
+trait WorkerOpener: Clone + Send + Sync + 'static {
+    type Worker: BenchWorker;
+
+    fn open(&self, worker_id: u64) -> impl std::future::Future<Output = Result<Self::Worker>> + Send;
+}
+
+struct MysqlWorker {
+    conn: mysql_async::Conn,
+    stmts: Vec<MysqlStmt>,
+}
+
+impl BenchWorker for MysqlWorker {
+    async fn exec(
+        &mut self,
+        i: usize,
+        sc: &Scenario,
+        sampler: &mut Sampler,
+        space: &ParamSpace,
+    ) -> Result<Option<Fetched>> {
+        self.stmts[i].exec(&mut self.conn, sc, sampler, space).await
+    }
+
+    async fn close(self) -> Result<()> {
+        self.conn.disconnect().await?;
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+struct MysqlOpener {
+    pool: mysql_async::Pool,
+    scenarios: Arc<Vec<Scenario>>,
+    mode: StatementMode,
+    pooler: Pooler,
+    /// 每次往返的语句条数，混合负载固定为 1
+    batch: usize,
+    session: Session,
+}
+
+impl MysqlOpener {
+    fn new(
+        pool: &mysql_async::Pool,
+        workload: &Workload,
+        mode: StatementMode,
+        pooler: Pooler,
+        batch: usize,
+        session: Session,
+    ) -> Self {
+        Self {
+            pool: pool.clone(),
+            scenarios: workload.scenarios.clone(),
+            mode,
+            pooler,
+            batch,
+            session,
+        }
+    }
+}
+
+impl WorkerOpener for MysqlOpener {
+    type Worker = MysqlWorker;
+
+    async fn open(&self, worker_id: u64) -> Result<MysqlWorker> {
+        let mut conn = self.pool.get_conn().await?;
+        if let Some(ms) = self.session.statement_timeout_ms {
+            conn.query_drop(format!("SET SESSION max_execution_time = {}", ms)).await?;
+        }
+        if let Some(isolation) = self.session.isolation {
+            conn.query_drop(format!("SET SESSION TRANSACTION ISOLATION LEVEL {}", isolation.sql())).await?;
+        }
+        let mut stmts = Vec::with_capacity(self.scenarios.len());
+        for sc in self.scenarios.iter() {
+            let (mode, pooler, isolation) = (self.mode, self.pooler, self.session.isolation);
+            stmts.push(MysqlStmt::prepare(&mut conn, sc, mode, pooler, worker_id, self.batch, isolation).await?);
+        }
+        Ok(MysqlWorker { conn, stmts })
+    }
+}
+
+struct PgWorker {
+    client: PgClient,
+    stmts: Vec<PgStmt>,
+}
+
+impl BenchWorker for PgWorker {
+    async fn exec(
+        &mut self,
+        i: usize,
+        sc: &Scenario,
+        sampler: &mut Sampler,
+        space: &ParamSpace,
+    ) -> Result<Option<Fetched>> {
+        self.stmts[i].exec(&self.client, sc, sampler, space).await
+    }
+
+    async fn close(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+struct PgOpener {
+    db: DbConfig,
+    scenarios: Arc<Vec<Scenario>>,
+    mode: StatementMode,
+    batch: usize,
+    session: Session,
+}
+
+impl PgOpener {
+    fn new(db: &DbConfig, workload: &Workload, mode: StatementMode, batch: usize, session: Session) -> Self {
+        Self {
+            db: db.clone(),
+            scenarios: workload.scenarios.clone(),
+            mode,
+            batch,
+            session,
+        }
+    }
+}
+
+impl WorkerOpener for PgOpener {
+    type Worker = PgWorker;
+
+    async fn open(&self, worker_id: u64) -> Result<PgWorker> {
+        let (client, connection) = connect::postgres(&self.db.url, &self.db.connect).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::error!("Postgres worker 连接任务出错: {}", e);
+            }
+        });
+        if let Some(ms) = self.session.statement_timeout_ms {
+            client.batch_execute(&format!("SET statement_timeout = {}", ms)).await?;
+        }
+        let mut stmts = Vec::with_capacity(self.scenarios.len());
+        for sc in self.scenarios.iter() {
+            let (mode, pooler, isolation) = (self.mode, self.db.pooler, self.session.isolation);
+            stmts.push(PgStmt::prepare(&client, sc, mode, pooler, worker_id, self.batch, isolation).await?);
+        }
+        Ok(PgWorker { client, stmts })
+    }
+}
+
+/// 追加唯一注释，让每次执行的语句文本都不同
+fn reparse_sql(sql: &str, worker_id: u64, seq: u64) -> String {
+    format!("{} /* w{} op{} */", sql, worker_id, seq)
+}
+
+/// 单个 worker 连接上按 StatementMode 执行场景语句
+struct MysqlStmt {
+    mode: StatementMode,
+    prepared: Option<mysql_async::Statement>,
+    // 经事务级连接池或 --no-prepare 时走 text 协议，参数内联进 SQL
+    unprepared: bool,
+    worker_id: u64,
+    seq: u64,
+    // 每次往返的语句条数，大于 1 时拼成一条多语句 text 查询
+    batch: usize,
+    // --isolation 下的写场景：每次操作包在显式事务里，隔离级别已在建连时按会话设置
+    transaction: bool,
+}
+
+impl MysqlStmt {
+    async fn prepare(
+        conn: &mut mysql_async::Conn,
+        sc: &Scenario,
+        mode: StatementMode,
+        pooler: Pooler,
+        worker_id: u64,
+        batch: usize,
+        isolation: Option<Isolation>,
+    ) -> Result<Self> {
+        let unprepared = pooler == Pooler::Transaction || mode == StatementMode::Text;
+        let prepared = match mode {
+            StatementMode::Prepared => Some(conn.prep(&sc.mysql_sql).await?),
+            StatementMode::Default if !unprepared => Some(conn.prep(&sc.mysql_sql).await?),
+            _ => None,
+        };
+        Ok(Self {
+            mode,
+            prepared,
+            unprepared,
+            worker_id,
+            seq: 0,
+            batch,
+            transaction: isolation.is_some() && sc.is_write(),
+        })
+    }
+
+    async fn exec(
+        &mut self,
+        conn: &mut mysql_async::Conn,
+        sc: &Scenario,
+        sampler: &mut Sampler,
+        space: &ParamSpace,
+    ) -> Result<Option<Fetched>> {
+        if !self.transaction {
+            return self.exec_once(conn, sc, sampler, space).await;
+        }
+        conn.query_drop("BEGIN").await?;
+        match self.exec_once(conn, sc, sampler, space).await {
+            Ok(fetched) => {
+                conn.query_drop("COMMIT").await?;
+                Ok(fetched)
+            }
+            Err(e) => {
+                // 死锁时 InnoDB 已回滚整个事务；其余错误事务仍开着，回滚后连接才能继续用
+                let _ = conn.query_drop("ROLLBACK").await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn exec_once(
+        &mut self,
+        conn: &mut mysql_async::Conn,
+        sc: &Scenario,
+        sampler: &mut Sampler,
+        space: &ParamSpace,
+    ) -> Result<Option<Fetched>> {
+        self.seq += 1;
+        if self.batch > 1 {
+            return query_mysql_multi(conn, sc, sampler, space, self.batch)
+                .await
+                .map_err(label_statement_timeout);
+        }
+        let reparse = self.mode == StatementMode::Reparse;
+        let result = match &self.prepared {
+            Some(stmt) => exec_mysql(conn, stmt, sc, sampler, space).await,
+            None if self.unprepared => {
+                let sql = if reparse {
+                    reparse_sql(&sc.mysql_sql, self.worker_id, self.seq)
+                } else {
+                    sc.mysql_sql.to_string()
+                };
+                query_mysql_text(conn, &sql, sc, sampler, space).await
+            }
+            None if reparse => {
+                let sql = reparse_sql(&sc.mysql_sql, self.worker_id, self.seq);
+                exec_mysql(conn, sql, sc, sampler, space).await
+            }
+            None => exec_mysql(conn, &sc.mysql_sql, sc, sampler, space).await,
+        };
+        result.map_err(label_statement_timeout)
+    }
+}
+
+/// Postgres 版本的 MysqlStmt
+struct PgStmt {
+    mode: StatementMode,
+    prepared: Option<tokio_postgres::Statement>,
+    // 经事务级连接池时用 query_typed：Parse/Bind/Execute 一次往返完成，不留服务端语句
+    unprepared: bool,
+    worker_id: u64,
+    seq: u64,
+    // 每次往返的语句条数，大于 1 时把多次执行放进同一个管线
+    batch: usize,
+    // --isolation 下的写场景：每次操作前发出的 BEGIN。隔离级别随 BEGIN 指定，经事务级连接池也不会丢
+    begin: Option<String>,
+}
+
+impl PgStmt {
+    async fn prepare(
+        client: &PgClient,
+        sc: &Scenario,
+        mode: StatementMode,
+        pooler: Pooler,
+        worker_id: u64,
+        batch: usize,
+        isolation: Option<Isolation>,
+    ) -> Result<Self> {
+        let unprepared = pooler == Pooler::Transaction;
+        // 默认模式（含管线模式）也预先 prepare，避免每次执行多一轮 Parse/Describe
+        let prepared = match mode {
+            StatementMode::Prepared => Some(client.prepare(&sc.postgres_sql).await?),
+            StatementMode::Default if !unprepared => Some(client.prepare(&sc.postgres_sql).await?),
+            _ => None,
+        };
+        Ok(Self {
+            mode,
+            prepared,
+            unprepared,
+            worker_id,
+            seq: 0,
+            batch,
+            begin: isolation
+                .filter(|_| sc.is_write())
+                .map(|isolation| format!("BEGIN ISOLATION LEVEL {}", isolation.sql())),
+        })
+    }
+
+    async fn exec(
+        &mut self,
+        client: &PgClient,
+        sc: &Scenario,
+        sampler: &mut Sampler,
+        space: &ParamSpace,
+    ) -> Result<Option<Fetched>> {
+        let Some(begin) = &self.begin else {
+            return self.exec_once(client, sc, sampler, space).await;
+        };
+        client.batch_execute(begin).await?;
+        match self.exec_once(client, sc, sampler, space).await {
+            // serializable 下提交时也可能因序列化失败被回滚，错误照常交给重试与计数
+            Ok(fetched) => {
+                client.batch_execute("COMMIT").await?;
+                Ok(fetched)
+            }
+            Err(e) => {
+                let _ = client.batch_execute("ROLLBACK").await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn exec_once(
+        &mut self,
+        client: &PgClient,
+        sc: &Scenario,
+        sampler: &mut Sampler,
+        space: &ParamSpace,
+    ) -> Result<Option<Fetched>> {
+        self.seq += 1;
+        if self.batch > 1 {
+            return pipeline_postgres(client, self.prepared.as_ref(), sc, sampler, space, self.batch)
+                .await
+                .map_err(label_statement_timeout);
+        }
+        let reparse = self.mode == StatementMode::Reparse;
+        let result = match &self.prepared {
+            Some(stmt) => exec_postgres(client, stmt, sc, sampler, space).await,
+            None if self.unprepared => {
+                let sql = if reparse {
+                    reparse_sql(&sc.postgres_sql, self.worker_id, self.seq)
+                } else {
+                    sc.postgres_sql.to_string()
+                };
+                query_postgres_typed(client, &sql, sc, sampler, space).await
+            }
+            None if reparse => {
+                let sql = reparse_sql(&sc.postgres_sql, self.worker_id, self.seq);
+                exec_postgres(client, sql.as_str(), sc, sampler, space).await
+            }
+            None => exec_postgres(client, &sc.postgres_sql, sc, sampler, space).await,
+        };
+        result.map_err(label_statement_timeout)
+    }
+}
+
+/// 多行场景返回首行到达时刻与数据量，单行场景返回 None
+async fn exec_mysql<S>(
+    conn: &mut mysql_async::Conn,
+    stmt: S,
+    sc: &Scenario,
+    sampler: &mut Sampler,
+    space: &ParamSpace,
+) -> Result<Option<Fetched>>
+where
+    S: mysql_async::prelude::StatementLike,
+{
+    let params = sample_param(sc.param, sampler, space).into_mysql_params();
+    match sc.fetch {
+        Fetch::First => {
+            first_mysql_row(conn.exec_iter(stmt, params).await?).await?;
+            Ok(None)
+        }
+        Fetch::All => {
+            let result = conn.exec_iter(stmt, params).await?;
+            drain_mysql_rows(result).await.map(Some)
+        }
+        Fetch::None => {
+            conn.exec_drop(stmt, params).await?;
+            Ok(None)
+        }
+    }
+}
+
+async fn exec_postgres<S>(
+    client: &PgClient,
+    stmt: &S,
+    sc: &Scenario,
+    sampler: &mut Sampler,
+    space: &ParamSpace,
+) -> Result<Option<Fetched>>
+where
+    S: tokio_postgres::ToStatement + ?Sized + Sync,
+{
+    let value = sample_param(sc.param, sampler, space);
+    let params = value.pg_params();
+    match sc.fetch {
+        Fetch::First => {
+            first_pg_row(client.query_raw(stmt, params).await?).await?;
+            Ok(None)
+        }
+        Fetch::All => {
+            let rows = client.query_raw(stmt, params).await?;
+            drain_pg_rows(rows).await.map(Some)
+        }
+        Fetch::None => {
+            client.execute(stmt, &params).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// text 协议执行：参数内联为字面量，服务端不创建预处理语句
+async fn query_mysql_text(
+    conn: &mut mysql_async::Conn,
+    sql: &str,
+    sc: &Scenario,
+    sampler: &mut Sampler,
+    space: &ParamSpace,
+) -> Result<Option<Fetched>> {
+    let values = match sample_param(sc.param, sampler, space).into_mysql_params() {
+        MyParams::Positional(values) => values,
+        _ => Vec::new(),
+    };
+    let sql = inline_mysql_params(sql, &values);
+    match sc.fetch {
+        Fetch::First => {
+            first_mysql_row(conn.query_iter(sql).await?).await?;
+            Ok(None)
+        }
+        Fetch::All => {
+            let result = conn.query_iter(sql).await?;
+            drain_mysql_rows(result).await.map(Some)
+        }
+        Fetch::None => {
+            conn.query_drop(sql).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// 把 batch 条语句（各自采样参数）拼成一条多语句 text 查询，一次往返发出并读完全部结果集。
+/// mysql_async 默认开启 CLIENT_MULTI_STATEMENTS
+async fn query_mysql_multi(
+    conn: &mut mysql_async::Conn,
+    sc: &Scenario,
+    sampler: &mut Sampler,
+    space: &ParamSpace,
+    batch: usize,
+) -> Result<Option<Fetched>> {
+    let mut sql = String::new();
+    for _ in 0..batch {
+        let values = match sample_param(sc.param, sampler, space).into_mysql_params() {
+            MyParams::Positional(values) => values,
+            _ => Vec::new(),
+        };
+        sql.push_str(&inline_mysql_params(&sc.mysql_sql, &values));
+        sql.push_str(";\n");
+    }
+    conn.query_drop(sql).await?;
+    Ok(None)
+}
+
+/// 同时发起 batch 次执行：tokio-postgres 会把并发的请求写进同一条连接的管线，
+/// 不等上一条返回就发送下一条
+async fn pipeline_postgres(
+    client: &PgClient,
+    prepared: Option<&tokio_postgres::Statement>,
+    sc: &Scenario,
+    sampler: &mut Sampler,
+    space: &ParamSpace,
+    batch: usize,
+) -> Result<Option<Fetched>> {
+    let values: Vec<ParamValue> = (0..batch).map(|_| sample_param(sc.param, sampler, space)).collect();
+    match prepared {
+        Some(stmt) => {
+            try_join_all(values.iter().map(|v| async move { client.query(stmt, &v.pg_params()).await }))
+                .await?;
+        }
+        None => {
+            try_join_all(values.iter().map(|v| async move {
+                client.query_typed(&sc.postgres_sql, &v.pg_typed_params()).await
+            }))
+            .await?;
+        }
+    }
+    Ok(None)
+}
+
+/// 显式声明参数类型的单次往返执行，不依赖服务端保留语句
+async fn query_postgres_typed(
+    client: &PgClient,
+    sql: &str,
+    sc: &Scenario,
+    sampler: &mut Sampler,
+    space: &ParamSpace,
+) -> Result<Option<Fetched>> {
+    let value = sample_param(sc.param, sampler, space);
+    let params = value.pg_typed_params();
+    match sc.fetch {
+        Fetch::First => {
+            first_pg_row(client.query_typed_raw(sql, params).await?).await?;
+            Ok(None)
+        }
+        Fetch::None => {
+            client.query_typed(sql, &params).await?;
+            Ok(None)
+        }
+        Fetch::All => {
+            let rows = client.query_typed_raw(sql, params).await?;
+            drain_pg_rows(rows).await.map(Some)
+        }
+    }
+}
+
+/// 多行场景一次执行读到的结果：首行到达时刻（无结果行时为读完时刻）与结果数据量
+struct Fetched {
+    first_row: Instant,
+    bytes: u64,
+}
+
+/// fetch = "first"：只取首行，列数与类型不限；其余行由驱动丢弃（MySQL 协议上仍需读完）
+async fn first_mysql_row<P>(mut result: mysql_async::QueryResult<'_, '_, P>) -> Result<()>
+where
+    P: mysql_async::prelude::Protocol,
+{
+    let _: Option<mysql_async::Row> = result.next().await?;
+    result.drop_result().await?;
+    Ok(())
+}
+
+async fn first_pg_row(rows: tokio_postgres::RowStream) -> Result<()> {
+    pin_mut!(rows);
+    rows.try_next().await?;
+    Ok(())
+}
+
+async fn drain_mysql_rows<P>(mut result: mysql_async::QueryResult<'_, '_, P>) -> Result<Fetched>
+where
+    P: mysql_async::prelude::Protocol,
+{
+    let mut first = None;
+    let mut bytes = 0;
+    while let Some(row) = result.next().await? {
+        first.get_or_insert_with(Instant::now);
+        bytes += (0..row.len()).filter_map(|i| row.as_ref(i)).map(mysql_value_bytes).sum::<u64>();
+    }
+    Ok(Fetched {
+        first_row: first.unwrap_or_else(Instant::now),
+        bytes,
+    })
+}
+
+/// 按二进制协议的编码长度估算：驱动已解码成 Value，拿不到原始包大小
+fn mysql_value_bytes(value: &MyValue) -> u64 {
+    match value {
+        MyValue::NULL => 0,
+        MyValue::Bytes(b) => b.len() as u64,
+        MyValue::Int(_) | MyValue::UInt(_) | MyValue::Double(_) => 8,
+        MyValue::Float(_) => 4,
+        MyValue::Date(..) => 11,
+        MyValue::Time(..) => 12,
+    }
+}
+
+async fn drain_pg_rows(rows: tokio_postgres::RowStream) -> Result<Fetched> {
+    pin_mut!(rows);
+    let mut first = None;
+    let mut bytes = 0;
+    while let Some(row) = rows.try_next().await? {
+        first.get_or_insert_with(Instant::now);
+        for i in 0..row.len() {
+            bytes += row.try_get::<_, RawLen>(i)?.0;
+        }
+    }
+    Ok(Fetched {
+        first_row: first.unwrap_or_else(Instant::now),
+        bytes,
+    })
+}
+
+/// 只取列值在线上的字节数，不做解码；接受任意类型，NULL 计为 0
+struct RawLen(u64);
+
+impl<'a> FromSql<'a> for RawLen {
+    fn from_sql(
+        _: &PgType,
+        raw: &'a [u8],
+    ) -> std::result::Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(RawLen(raw.len() as u64))
+    }
+
+    fn from_sql_null(_: &PgType) -> std::result::Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(RawLen(0))
+    }
+
+    fn accepts(_: &PgType) -> bool {
+        true
+    }
+}
+
+// replica_lag 写入的标记行：user_id 0 不在生成器的取值范围内，结束后只按它清理。
+// payload 写成合法的 JSON 文本，--payload-type json 时的 JSON/JSONB 列与文本列都能接受
+const REPLICA_LAG_MARKER: &str = "replica_lag";
+const REPLICA_LAG_MAX_SAMPLES: u64 = 10_000;
+const REPLICA_LAG_INSERT: &str = "INSERT INTO events (user_id, created_at, amount, status, category, payload) \
+     VALUES (0, NOW(), 0, 0, 0, '{\"event\":\"replica_lag\"}')";
+const REPLICA_LAG_CLEANUP: &str = "DELETE FROM events WHERE user_id = 0";
+
+/// 副本上还看不到刚写入的行时，隔这么久再查一次，不让探测连接空转压满副本
+const REPLICA_LAG_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// 主库每写入一行，就在副本上反复点查直到看到它：从写入确认到副本可见的时间即复制延迟。
+/// 单连接串行执行，走 text 协议，不受连接池模式影响
+async fn mysql_replica_lag(
+    primary: &mysql_async::Pool,
+    read_url: &str,
+    connect: &ConnectOptions,
+    cfg: &BenchConfig,
+) -> Result<BenchResult> {
+    let replica = connect::mysql_pool(read_url, connect).await?;
+    let mut writer = primary.get_conn().await?;
+    let mut reader = replica
+        .get_conn()
+        .await
+        .with_context(|| format!("连接只读副本失败: {}", connect::redact_url(read_url)))?;
+    // 上次运行被强行终止时可能留下标记行，先清一次
+    writer.query_drop(REPLICA_LAG_CLEANUP).await?;
+    let start = Instant::now();
+    let probed = mysql_probe_lag(&mut writer, &mut reader, cfg).await;
+    let wall = start.elapsed().as_secs_f64();
+    // 出错或被 Ctrl-C 中断时也要清理；写连接可能已经断开，从连接池另取一条
+    let cleaned = match primary.get_conn().await {
+        Ok(mut conn) => conn.query_drop(REPLICA_LAG_CLEANUP).await.map_err(anyhow::Error::from),
+        Err(e) => Err(e.into()),
+    };
+    let (lags, timeouts) = after_replica_lag_cleanup(probed, cleaned)?;
+    writer.disconnect().await?;
+    reader.disconnect().await?;
+    replica.disconnect().await?;
+    Ok(replica_lag_result(cfg, &lags, timeouts, wall))
+}
+
+async fn mysql_probe_lag(
+    writer: &mut mysql_async::Conn,
+    reader: &mut mysql_async::Conn,
+    cfg: &BenchConfig,
+) -> Result<(LatencyHistogram, u64)> {
+    let samples = replica_lag_samples(cfg);
+    let timeout = Duration::from_millis(cfg.replica_lag_timeout_ms);
+    let mut lags = LatencyHistogram::new();
+    let mut timeouts = 0u64;
+    'samples: for _ in 0..samples {
+        if shutdown::requested() {
+            break;
+        }
+        writer.query_drop(REPLICA_LAG_INSERT).await?;
+        let written = Instant::now();
+        let id = writer
+            .last_insert_id()
+            .ok_or_else(|| anyhow!("replica_lag 写入后拿不到自增 id"))?;
+        let probe = format!("SELECT 1 FROM events WHERE id = {}", id);
+        loop {
+            let seen: Option<u8> = reader.query_first(&probe).await?;
+            if seen.is_some() {
+                lags.record(written.elapsed().as_nanos() as u64);
+                break;
+            }
+            if written.elapsed() >= timeout {
+                timeouts += 1;
+                break;
+            }
+            if shutdown::requested() {
+                break 'samples;
+            }
+            tokio::time::sleep(REPLICA_LAG_POLL_INTERVAL).await;
+        }
+    }
+    Ok((lags, timeouts))
+}
+
+async fn postgres_replica_lag(
+    primary: &PgClient,
+    read_url: &str,
+    connect: &ConnectOptions,
+    cfg: &BenchConfig,
+) -> Result<BenchResult> {
+    let (reader, connection) = connect::postgres(read_url, connect)
+        .await
+        .with_context(|| format!("连接只读副本失败: {}", connect::redact_url(read_url)))?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("Postgres 只读副本连接任务出错: {}", e);
+        }
+    });
+    // 上次运行被强行终止时可能留下标记行，先清一次
+    primary.batch_execute(REPLICA_LAG_CLEANUP).await?;
+    let start = Instant::now();
+    let probed = postgres_probe_lag(primary, &reader, cfg).await;
+    let wall = start.elapsed().as_secs_f64();
+    // 出错或被 Ctrl-C 中断时也要清理
+    let cleaned = primary.batch_execute(REPLICA_LAG_CLEANUP).await.map_err(anyhow::Error::from);
+    let (lags, timeouts) = after_replica_lag_cleanup(probed, cleaned)?;
+    Ok(replica_lag_result(cfg, &lags, timeouts, wall))
+}
+
+async fn postgres_probe_lag(primary: &PgClient, reader: &PgClient, cfg: &BenchConfig) -> Result<(LatencyHistogram, u64)> {
+    let samples = replica_lag_samples(cfg);
+    let timeout = Duration::from_millis(cfg.replica_lag_timeout_ms);
+    let insert = format!("{} RETURNING id", REPLICA_LAG_INSERT);
+    let mut lags = LatencyHistogram::new();
+    let mut timeouts = 0u64;
+    'samples: for _ in 0..samples {
+        if shutdown::requested() {
+            break;
+        }
+        let rows = primary.query_typed(&insert, &[]).await?;
+        let written = Instant::now();
+        let id: i64 = rows
+            .first()
+            .ok_or_else(|| anyhow!("replica_lag 写入后没有返回 id"))?
+            .get(0);
+        let probe = format!("SELECT 1 FROM events WHERE id = {}", id);
+        loop {
+            if !reader.query_typed(&probe, &[]).await?.is_empty() {
+                lags.record(written.elapsed().as_nanos() as u64);
+                break;
+            }
+            if written.elapsed() >= timeout {
+                timeouts += 1;
+                break;
+            }
+            if shutdown::requested() {
+                break 'samples;
+            }
+            tokio::time::sleep(REPLICA_LAG_POLL_INTERVAL).await;
+        }
+    }
+    Ok((lags, timeouts))
+}
+
+/// 采样出错时优先返回采样的错误，清理也失败时只留一条警告，提示手动删除标记行
+fn after_replica_lag_cleanup<T>(probed: Result<T>, cleaned: Result<()>) -> Result<T> {
+    match (probed, cleaned) {
+        (Ok(v), Ok(())) => Ok(v),
+        (Ok(_), Err(e)) => Err(e.context("清理 replica_lag 标记行失败")),
+        (Err(e), Ok(())) => Err(e),
+        (Err(e), Err(cleanup)) => {
+            tracing::warn!("清理 replica_lag 标记行失败，可手动执行 {}: {:#}", REPLICA_LAG_CLEANUP, cleanup);
+            Err(e)
+        }
+    }
+}
+
+fn replica_lag_samples(cfg: &BenchConfig) -> u64 {
+    let samples = cfg.sample_ops.min(REPLICA_LAG_MAX_SAMPLES);
+    tracing::info!(
+        "scenario=replica_lag 串行写入 {} 行并在副本上轮询，单次最多等待 {}ms",
+        samples,
+        cfg.replica_lag_timeout_ms
+    );
+    samples
+}
+
+fn replica_lag_result(cfg: &BenchConfig, lags: &LatencyHistogram, timeouts: u64, wall: f64) -> BenchResult {
+    if timeouts > 0 {
+        tracing::warn!(
+            "scenario=replica_lag 有 {} 次写入在 {}ms 内未出现在副本上，未计入延迟分布",
+            timeouts,
+            cfg.replica_lag_timeout_ms
+        );
+    }
+    let stats = lags.summary(&cfg.percentiles.0);
+    BenchResult {
+        scenario: REPLICA_LAG_MARKER.to_string(),
+        statement_mode: None,
+        concurrency: None,
+        repeat: None,
+        ops: stats.count as u64,
+        throughput_ops: stats.count as f64 / wall.max(0.001),
+        rows_per_sec: None,
+        bytes_per_sec: None,
+        latency: Latency {
+            stats,
+            unit: cfg.unit,
+        },
+        first_row: None,
+        service: None,
+        per_query: None,
+        warmup: None,
+        timeouts: Some(timeouts),
+        connection_loss: None,
+        retries: None,
+        errors: None,
+        rollbacks: None,
+        error_rate: None,
+        breakdown: None,
+        server: None,
+        plan: None,
+    }
+}
+
+/// --target-qps 的限速器：同一场景的所有 worker 共用一个发送计划，第 n 个许可的计划时刻为
+/// 起点 + n × 周期。tokio 的 Mutex 按等待顺序交出，许可在 worker 之间轮流分配，总速率即目标 QPS。
+/// 服务端卡顿导致落后时计划不顺延，之后的许可立即放行，直到追上计划
+#[derive(Clone)]
+struct RateLimiter {
+    period: Duration,
+    state: Arc<tokio::sync::Mutex<RateState>>,
+}
+
+struct RateState {
+    /// 下一个许可的计划发出时刻；首次申请时才开始计划，预热时间不算落后
+    next: Option<Instant>,
+    /// 上次发放许可时读到的暂停总时长
+    paused_seen: Duration,
+}
+
+impl RateLimiter {
+    fn new(qps: f64) -> Self {
+        Self {
+            period: Duration::from_secs_f64(1.0 / qps),
+            state: Arc::new(tokio::sync::Mutex::new(RateState {
+                next: None,
+                paused_seen: Duration::ZERO,
+            })),
+        }
+    }
+
+    /// 等到下一个许可，返回它的计划发出时刻
+    async fn acquire(&self, gate: &PauseGate) -> Instant {
+        let mut state = self.state.lock().await;
+        // 暂停期间本就不发请求，整个计划顺延暂停的时长，避免恢复后把积压的许可一次放出
+        let paused = gate.paused_total();
+        let shift = paused.saturating_sub(state.paused_seen);
+        state.paused_seen = paused;
+        let intended = match state.next {
+            Some(next) => next + shift,
+            None => Instant::now(),
+        };
+        state.next = Some(intended + self.period);
+        tokio::time::sleep_until(intended).await;
+        intended
+    }
+}
+
+/// 一次操作的 (延迟, 服务时间)，单位纳秒。限速时延迟从计划发出时刻 intended 算起：请求因前一个慢操作而
+/// 推迟发出的排队时间也计入延迟，避免服务端卡顿时慢请求被少计（coordinated omission）；服务时间只含执行本身，
+/// 不限速时两者相同，不单独给出
+fn corrected(intended: Option<Instant>, start: Instant, end: Instant) -> (u64, Option<u64>) {
+    let latency = end.duration_since(intended.unwrap_or(start)).as_nanos() as u64;
+    (latency, intended.map(|_| end.duration_since(start).as_nanos() as u64))
+}
+
+/// 实际吞吐明显低于目标时说明已压满，此时的延迟不再代表该吞吐下的表现
+fn warn_below_target(scenario: &str, throughput: f64, target_qps: Option<f64>) {
+    if let Some(target) = target_qps
+        && throughput < target * 0.95
+    {
+        tracing::warn!(
+            "scenario={} 实际吞吐 {:.2} ops/s 未达到 --target-qps {}，服务端或并发数已是瓶颈",
+            scenario,
+            throughput,
+            target
+        );
+    }
+}
+
+/// worker 采样阶段的结束条件
+#[derive(Debug, Clone, Copy)]
+enum SampleLimit {
+    /// 固定次数（含因断线丢失的操作）
+    Ops(u64),
+    /// 达到时长为止，扣除期间的暂停时间
+    Elapsed(Duration),
+}
+
+impl SampleLimit {
+    fn more(&self, attempted: u64, start: Instant, gate: &PauseGate, paused_before: Duration) -> bool {
+        if shutdown::requested() {
+            return false;
+        }
+        match *self {
+            SampleLimit::Ops(n) => attempted < n,
+            SampleLimit::Elapsed(d) => {
+                let paused = gate.paused_total().saturating_sub(paused_before);
+                start.elapsed().saturating_sub(paused) < d
+            }
+        }
+    }
+}
+
+/// 场景耗时，扣除期间的暂停时长
+fn scenario_elapsed(scenario: &str, start: Instant, gate: &PauseGate, paused_before: Duration) -> f64 {
+    let paused = gate.paused_total().saturating_sub(paused_before);
+    if !paused.is_zero() {
+        tracing::info!(
+            "scenario={} 期间暂停 {:.1}s，已从耗时与吞吐中扣除",
+            scenario,
+            paused.as_secs_f64()
+        );
+    }
+    start.elapsed().saturating_sub(paused).as_secs_f64()
+}
+
+fn first_row_latency(sc: &Scenario, durations_ns: &LatencyHistogram, cfg: &BenchConfig) -> Option<Latency> {
+    if sc.fetch != Fetch::All {
+        return None;
+    }
+    Some(Latency {
+        stats: durations_ns.summary(&cfg.percentiles.0),
+        unit: cfg.unit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worker_seeds_do_not_overlap_across_repeats() {
+        let mut seen = std::collections::HashSet::new();
+        // --seed u64::MAX 的第二次重复回绕为 0
+        for base in [1, 1000, u64::MAX] {
+            for repeat in 0..2u64 {
+                let round_seed = base.wrapping_add(repeat);
+                for worker_id in 0..64 {
+                    assert!(seen.insert(worker_seed(round_seed, worker_id)), "seed={} repeat={} worker={}", base, repeat, worker_id);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stall_under_rate_limit_raises_p99() {
+        // 1000 qps 的发送计划下单个 worker 依次执行，第 500 次卡住 100ms，其余每次 100µs
+        let period = Duration::from_millis(1);
+        let base = Instant::now();
+        let (mut latency, mut service) = (LatencyHistogram::new(), LatencyHistogram::new());
+        let mut free_at = base;
+        for i in 0..1000u32 {
+            let intended = base + period * i;
+            let start = intended.max(free_at);
+            let end = start + if i == 500 { Duration::from_millis(100) } else { Duration::from_micros(100) };
+            free_at = end;
+            let (l, s) = corrected(Some(intended), start, end);
+            latency.record(l);
+            service.record(s.unwrap());
+        }
+        let p99 = |hist: &LatencyHistogram| hist.summary(&[99.0]).percentiles[0].1;
+        // 卡顿之后约 100 个请求都推迟发出，排队时间计入延迟；只看服务时间时卡顿仅占一个样本
+        assert!(p99(&latency) > 50e6, "p99 {}", p99(&latency));
+        assert!(p99(&service) < 1e6, "service p99 {}", p99(&service));
+        assert_eq!(corrected(None, base, base + period), (1_000_000, None));
+    }
+
+    fn builtin(name: &str) -> Scenario {
+        scenarios(false, false, 30, PayloadType::Text).into_iter().find(|sc| sc.name == name).unwrap()
+    }
+
+    #[test]
+    fn pg_literals_render_arrays() {
+        assert_eq!(ParamValue::IntList(vec![1, 22, 333]).pg_literals(), vec!["ARRAY[1, 22, 333]::int8[]"]);
+        let t = NaiveDateTime::parse_from_str("2026-01-02 03:04:05", "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(
+            ParamValue::TimeList(vec![t, t]).pg_literals(),
+            vec!["ARRAY['2026-01-02 03:04:05', '2026-01-02 03:04:05']::timestamp[]"]
+        );
+    }
+
+    /// 需要一个装载过的 Postgres：DATABASE_URL=postgres://... cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_any_binds_arrays() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL 未设置");
+        let (client, connection) = connect::postgres(&url, &Default::default()).await.unwrap();
+        tokio::spawn(connection);
+        let ids = ParamValue::IntList(vec![1, 2, 3, i64::MAX]);
+        let sc = builtin("pk_in_list");
+        let bound = client.query_typed(&sc.postgres_sql, &ids.pg_typed_params()).await.unwrap();
+        let mut found: Vec<i64> = bound.iter().map(|r| r.get(0)).collect();
+        found.sort();
+        assert!(found.iter().all(|id| [1, 2, 3].contains(id)), "{:?}", found);
+        // show-sql 渲染的字面量与绑定参数查到同样的行
+        let inlined = sc.postgres_sql.replace("$1", &ids.pg_literals()[0]);
+        assert_eq!(client.query(&inlined, &[]).await.unwrap().len(), found.len());
+
+        let row = client.query_one("SELECT created_at FROM events ORDER BY id LIMIT 1", &[]).await.unwrap();
+        let times = ParamValue::TimeList(vec![row.get(0)]);
+        let sc = builtin("created_in_list");
+        assert!(!client.query_typed(&sc.postgres_sql, &times.pg_typed_params()).await.unwrap().is_empty());
+    }
+
+    /// 多行、首列不是整数的 fetch = "first" 自定义场景
+    fn multi_row_first() -> Scenario {
+        let text = "[[scenario]]\nname = \"payload_first\"\n\
+            mysql_sql = \"SELECT payload, created_at FROM events WHERE id IN (?, ?, ?)\"\n\
+            postgres_sql = \"SELECT payload, created_at FROM events WHERE id = ANY($1)\"\n\
+            param = { pk_list = 3 }\nfetch = \"first\"\n";
+        toml::from_str::<ScenarioFile>(text).unwrap().scenario.remove(0)
+    }
+
+    /// 与 bench 命令行默认值一致的配置
+    fn bench_config() -> BenchConfig {
+        BenchConfig {
+            warmup_ops: 1000,
+            sample_ops: DEFAULT_SAMPLE_OPS,
+            duration: None,
+            concurrency: 1,
+            target_qps: None,
+            output: None,
+            seed: 42,
+            compare_prepared: false,
+            no_prepare: false,
+            resource_stats: false,
+            server_metrics: false,
+            ignore_warmup_errors: false,
+            report_warmup: false,
+            plan_baseline: None,
+            abort_on_plan_regression: false,
+            explain: false,
+            lookup_dist: None,
+            users: None,
+            unit: LatencyUnit::Ms,
+            percentiles: Percentiles::default(),
+            allow_writes: false,
+            allow_deletes: false,
+            hot_rows: 100,
+            time_span_days: crate::config::DEFAULT_TIME_SPAN_DAYS,
+            payload_type: PayloadType::Text,
+            sample_fraction: None,
+            statement_timeout_ms: None,
+            isolation: None,
+            format: OutputFormat::Json,
+            prometheus: None,
+            timeseries: None,
+            pushgateway: None,
+            queries_per_roundtrip: 1,
+            tag: None,
+            reconnect_attempts: 0,
+            reconnect_backoff_ms: 200,
+            retry: RetryPolicy {
+                max_retries: 0,
+                backoff: Duration::from_millis(100),
+            },
+            continue_on_error: false,
+            read_url: None,
+            replica_lag_timeout_ms: 5000,
+            scenarios: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            mix: None,
+            ramp: None,
+            repeat: 1,
+        }
+    }
+
+    fn small_space() -> ParamSpace {
+        ParamSpace::new(100, 100, 10, 30, PayloadType::Text, &UserDistSpec::single(Distribution::Uniform)).unwrap()
+    }
+
+    const ALL_MODES: [StatementMode; 4] =
+        [StatementMode::Default, StatementMode::Text, StatementMode::Prepared, StatementMode::Reparse];
+
+    /// 需要一个装载过的 Postgres：DATABASE_URL=postgres://... cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_first_reads_any_row_shape() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL 未设置");
+        let (client, connection) = connect::postgres(&url, &Default::default()).await.unwrap();
+        tokio::spawn(connection);
+        let (sc, space) = (multi_row_first(), small_space());
+        let mut sampler = Sampler::new(1, &space, std::slice::from_ref(&sc));
+        for pooler in [Pooler::None, Pooler::Transaction] {
+            for mode in ALL_MODES {
+                let mut stmt = PgStmt::prepare(&client, &sc, mode, pooler, 0, 1, None).await.unwrap();
+                for _ in 0..10 {
+                    stmt.exec(&client, &sc, &mut sampler, &space).await.unwrap();
+                }
+            }
+        }
+    }
+
+    /// 需要一个装载过的 MySQL：MYSQL_URL=mysql://... cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn mysql_first_reads_any_row_shape() {
+        let url = std::env::var("MYSQL_URL").expect("MYSQL_URL 未设置");
+        let pool = connect::mysql_pool(&url, &Default::default()).await.unwrap();
+        let mut conn = pool.get_conn().await.unwrap();
+        let (sc, space) = (multi_row_first(), small_space());
+        let mut sampler = Sampler::new(1, &space, std::slice::from_ref(&sc));
+        for pooler in [Pooler::None, Pooler::Transaction] {
+            for mode in ALL_MODES {
+                let mut stmt = MysqlStmt::prepare(&mut conn, &sc, mode, pooler, 0, 1, None).await.unwrap();
+                for _ in 0..10 {
+                    stmt.exec(&mut conn, &sc, &mut sampler, &space).await.unwrap();
+                }
+            }
+        }
+        conn.disconnect().await.unwrap();
+        pool.disconnect().await.unwrap();
+    }
+
+    #[test]
+    fn scenario_file_rejects_duplicate_names() {
+        let path = std::env::temp_dir().join(format!("dbpo-dup-scenarios-{}.toml", std::process::id()));
+        let scenario = "[[scenario]]\nname = \"count_all\"\nmysql_sql = \"SELECT COUNT(*) FROM events\"\npostgres_sql = \"SELECT COUNT(*) FROM events\"\n";
+        std::fs::write(&path, format!("{}{}", scenario, scenario)).unwrap();
+        let err = load_scenarios(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(format!("{:#}", err).contains("场景名 count_all 重复"), "{:#}", err);
+    }
+
+    #[test]
+    fn replica_lag_cleanup_error_does_not_hide_probe_error() {
+        let probed: Result<()> = Err(anyhow!("副本断开"));
+        let err = after_replica_lag_cleanup(probed, Err(anyhow!("主库断开"))).unwrap_err();
+        assert_eq!(err.to_string(), "副本断开");
+        let err = after_replica_lag_cleanup(Ok(()), Err(anyhow!("主库断开"))).unwrap_err();
+        assert!(err.to_string().contains("清理 replica_lag 标记行失败"));
+        assert!(after_replica_lag_cleanup(Ok(1), Ok(())).is_ok());
+    }
+
+    #[test]
+    fn custom_scenario_may_not_shadow_replica_lag() {
+        let text = "[[scenario]]\nname = \"replica_lag\"\n\
+            mysql_sql = \"SELECT 1\"\npostgres_sql = \"SELECT 1\"\n";
+        let mut cfg = bench_config();
+        cfg.allow_writes = true;
+        cfg.scenarios = Some(toml::from_str::<ScenarioFile>(text).unwrap().scenario);
+        assert!(cfg.validate().is_ok());
+        cfg.read_url = Some("postgres://replica/perf".to_string());
+        let err = cfg.validate().unwrap_err();
+        assert!(err.to_string().contains("场景名 replica_lag 重复"));
     }
 }
@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+/// 装载期间检查点的落盘间隔
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// --checkpoint 文件的内容：装载的进度日志，只记录已经提交的行。
+/// 定期落盘，不是每批同步写入，所以只作核对与留档，续装时不据此决定起点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// 目标行数；与本次 --scale 不一致时不沿用
+    pub scale: u64,
+    /// 本次装载开始前已提交的行数（表内原有的行，加上之前各次装载的进度）
+    pub base_rows: u64,
+    /// 本次装载各 worker 已提交的行数
+    pub workers: Vec<u64>,
+    /// 达到目标行数后置为 true
+    pub complete: bool,
+}
+
+impl Checkpoint {
+    pub fn committed(&self) -> u64 {
+        self.base_rows + self.workers.iter().sum::<u64>()
+    }
+}
+
+pub fn load(path: &Path) -> Result<Option<Checkpoint>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(path).with_context(|| format!("读取检查点 {:?} 失败", path))?;
+    let checkpoint = serde_json::from_str(&text).with_context(|| format!("解析检查点 {:?} 失败", path))?;
+    Ok(Some(checkpoint))
+}
+
+/// 先写临时文件再改名，进程中途被杀也不会留下写了一半的检查点
+pub fn save(path: &Path, checkpoint: &Checkpoint) -> Result<()> {
+    let json = serde_json::to_string_pretty(checkpoint)?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, json).with_context(|| format!("写入检查点 {:?} 失败", tmp))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("写入检查点 {:?} 失败", path))
+}
+
+/// 装载中的进度：worker 每提交一批就累加，后台任务定期落盘
+pub struct Progress {
+    path: PathBuf,
+    scale: u64,
+    base_rows: u64,
+    workers: Vec<AtomicU64>,
+}
+
+impl Progress {
+    pub fn new(path: PathBuf, scale: u64, base_rows: u64, workers: usize) -> Arc<Self> {
+        Arc::new(Self {
+            path,
+            scale,
+            base_rows,
+            workers: (0..workers).map(|_| AtomicU64::new(0)).collect(),
+        })
+    }
+
+    /// 只能在这一批确认提交之后调用
+    pub fn add(&self, worker: usize, rows: u64) {
+        self.workers[worker].fetch_add(rows, Ordering::Relaxed);
+    }
+
+    pub fn save(&self, complete: bool) -> Result<()> {
+        let checkpoint = Checkpoint {
+            scale: self.scale,
+            base_rows: self.base_rows,
+            workers: self.workers.iter().map(|w| w.load(Ordering::Relaxed)).collect(),
+            complete,
+        };
+        save(&self.path, &checkpoint)
+    }
+
+    /// 定期落盘直到被 abort；写失败只记日志，不影响装载
+    pub fn spawn_flusher(self: &Arc<Self>) -> JoinHandle<()> {
+        let progress = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(CHECKPOINT_INTERVAL);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = progress.save(false) {
+                    tracing::warn!("写入检查点失败: {:#}", e);
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,218 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use toml::{Table, Value};
+
+/// 环境变量前缀：--url 对应 DBPO_URL，--sample-ops 对应 DBPO_SAMPLE_OPS
+const ENV_PREFIX: &str = "DBPO_";
+
+/// 只能在命令行上给出的参数：--config 本身，以及确认破坏性操作的 --yes（reset）
+const CLI_ONLY: &[&str] = &["config", "yes"];
+
+/// 把 --config 文件与 DBPO_* 环境变量补成命令行参数，交给 clap 按与命令行完全相同的规则解析、校验。
+/// 优先级：命令行 > 环境变量 > 配置文件 > 参数默认值。
+///
+/// 文件顶层写全局参数（db、url、pooler 等），`[load]`、`[bench]` 等节写对应子命令的参数，
+/// 节里也可以写只对该子命令生效的全局参数；键名即长参数名，`sample-ops` 与 `sample_ops` 均可
+pub fn resolve_args(cmd: &Command, args: Vec<OsString>) -> Result<Vec<OsString>> {
+    let mut cmd = cmd.clone().ignore_errors(true);
+    cmd.build();
+    // 先宽松解析一遍，只为知道子命令与命令行上已经给出的参数；--help 等原样交给之后的正式解析
+    let Ok(matches) = cmd.clone().try_get_matches_from(&args) else {
+        return Ok(args);
+    };
+    let file = match config_path(&matches) {
+        Some(path) => load(&path)?,
+        None => Table::new(),
+    };
+    check_keys(&cmd, &file)?;
+
+    let sub = matches
+        .subcommand()
+        .and_then(|(name, sub_matches)| Some((cmd.find_subcommand(name)?, sub_matches)));
+    let section = sub.and_then(|(sub_cmd, _)| file.get(sub_cmd.get_name())?.as_table());
+    let empty = Table::new();
+    let mut resolved: Vec<OsString> = args.iter().take(1).cloned().collect();
+    // 全局参数必须写在子命令之前，子命令的参数追加在最后
+    push_args(&mut resolved, &cmd, &matches, &[section.unwrap_or(&empty), &file])?;
+    resolved.extend(args.iter().skip(1).cloned());
+    if let Some((sub_cmd, sub_matches)) = sub {
+        push_args(&mut resolved, sub_cmd, sub_matches, &[section.unwrap_or(&empty)])?;
+    }
+    Ok(resolved)
+}
+
+fn config_path(matches: &ArgMatches) -> Option<PathBuf> {
+    if let Some(path) = matches.get_one::<PathBuf>("config") {
+        return Some(path.clone());
+    }
+    std::env::var_os(format!("{}CONFIG", ENV_PREFIX)).map(PathBuf::from)
+}
+
+fn load(path: &Path) -> Result<Table> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("读取配置文件 {:?} 失败", path))?;
+    toml::from_str(&text).with_context(|| format!("解析配置文件 {:?} 失败", path))
+}
+
+/// 未知的键直接报错，避免拼错的参数名被静默忽略
+fn check_keys(cmd: &Command, file: &Table) -> Result<()> {
+    for (key, value) in file {
+        if let Some(sub_cmd) = cmd.find_subcommand(key) {
+            let Some(section) = value.as_table() else {
+                bail!("配置文件中的 {} 应为 [{}] 节", key, key);
+            };
+            for sub_key in section.keys() {
+                check_cli_only(sub_key)?;
+                if find_arg(sub_cmd, sub_key).is_none() && find_arg(cmd, sub_key).is_none() {
+                    bail!("配置文件 [{}] 节中的 {} 不是该子命令或全局参数", key, sub_key);
+                }
+            }
+        } else {
+            check_cli_only(key)?;
+            if find_arg(cmd, key).is_none() {
+                bail!("配置文件中的 {} 既不是全局参数也不是子命令", key);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn find_arg<'a>(cmd: &'a Command, key: &str) -> Option<&'a Arg> {
+    let long = key.replace('_', "-");
+    cmd.get_arguments()
+        .find(|arg| configurable(arg) && names(arg).any(|name| name == long))
+}
+
+/// 长参数名及其别名（如 bench 的 --user-dist 是 --lookup-dist 的别名）
+fn names(arg: &Arg) -> impl Iterator<Item = &str> {
+    arg.get_long()
+        .into_iter()
+        .chain(arg.get_all_aliases().unwrap_or_default())
+}
+
+fn check_cli_only(key: &str) -> Result<()> {
+    if CLI_ONLY.contains(&key.replace('-', "_").as_str()) {
+        bail!("{} 只能在命令行上给出，不能写进配置文件", key);
+    }
+    Ok(())
+}
+
+/// 位置参数、--help/--version 与 CLI_ONLY 中的参数不能来自配置文件或环境变量
+fn configurable(arg: &Arg) -> bool {
+    !arg.is_positional()
+        && !CLI_ONLY.contains(&arg.get_id().as_str())
+        && !matches!(
+            arg.get_action(),
+            ArgAction::Help | ArgAction::HelpShort | ArgAction::HelpLong | ArgAction::Version
+        )
+}
+
+/// 为命令行上没有给出的参数依次查环境变量与 tables（按顺序取第一个有值的），生成对应的命令行参数
+fn push_args(out: &mut Vec<OsString>, cmd: &Command, matches: &ArgMatches, tables: &[&Table]) -> Result<()> {
+    let on_cli: Vec<&Arg> = cmd
+        .get_arguments()
+        .filter(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .collect();
+    for arg in cmd.get_arguments().filter(|arg| configurable(arg)) {
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        // 与命令行参数互斥的值不再补上，例如命令行给了 --socket 时忽略文件里的 url
+        if on_cli
+            .iter()
+            .any(|other| other.get_id() == arg.get_id() || conflicts(cmd, arg, other))
+        {
+            continue;
+        }
+        let env = format!("{}{}", ENV_PREFIX, long.to_uppercase().replace('-', "_"));
+        if let Ok(raw) = std::env::var(&env) {
+            push_env(out, arg, long, &raw).with_context(|| format!("环境变量 {} 无效", env))?;
+            continue;
+        }
+        let value = tables.iter().find_map(|table| {
+            names(arg).find_map(|name| table.get(name).or_else(|| table.get(&name.replace('-', "_"))))
+        });
+        if let Some(value) = value {
+            push_value(out, arg, long, value).with_context(|| format!("配置文件中的 {} 无效", long))?;
+        }
+    }
+    Ok(())
+}
+
+fn conflicts(cmd: &Command, a: &Arg, b: &Arg) -> bool {
+    cmd.get_arg_conflicts_with(a).iter().any(|c| c.get_id() == b.get_id())
+        || cmd.get_arg_conflicts_with(b).iter().any(|c| c.get_id() == a.get_id())
+}
+
+fn push_value(out: &mut Vec<OsString>, arg: &Arg, long: &str, value: &Value) -> Result<()> {
+    match (arg.get_action(), value) {
+        (ArgAction::SetTrue, Value::Boolean(set)) | (ArgAction::SetFalse, Value::Boolean(set)) => {
+            if *set == matches!(arg.get_action(), ArgAction::SetTrue) {
+                out.push(format!("--{}", long).into());
+            }
+        }
+        (ArgAction::SetTrue | ArgAction::SetFalse, _) => bail!("应为 true 或 false"),
+        (ArgAction::Count, Value::Integer(n)) if (0..=u8::MAX as i64).contains(n) => {
+            for _ in 0..*n {
+                out.push(format!("--{}", long).into());
+            }
+        }
+        (ArgAction::Count, _) => bail!("应为非负整数"),
+        (ArgAction::Append, Value::Array(items)) => {
+            for item in items {
+                out.push(format!("--{}={}", long, scalar(item)?).into());
+            }
+        }
+        // 其余参数的多个值（--percentiles、--ramp 等）在命令行上本就以逗号分隔
+        (_, Value::Array(items)) => {
+            let delimiter = arg.get_value_delimiter().unwrap_or(',');
+            let items = items.iter().map(scalar).collect::<Result<Vec<_>>>()?;
+            out.push(format!("--{}={}", long, items.join(&delimiter.to_string())).into());
+        }
+        (_, value) => out.push(format!("--{}={}", long, scalar(value)?).into()),
+    }
+    Ok(())
+}
+
+fn push_env(out: &mut Vec<OsString>, arg: &Arg, long: &str, raw: &str) -> Result<()> {
+    match arg.get_action() {
+        ArgAction::SetTrue | ArgAction::SetFalse => {
+            let set = match raw.to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" => true,
+                "0" | "false" | "no" | "" => false,
+                _ => bail!("应为 true/false、1/0 或 yes/no"),
+            };
+            if set == matches!(arg.get_action(), ArgAction::SetTrue) {
+                out.push(format!("--{}", long).into());
+            }
+        }
+        ArgAction::Count => {
+            let n: u8 = raw.parse().context("应为非负整数")?;
+            for _ in 0..n {
+                out.push(format!("--{}", long).into());
+            }
+        }
+        // 可重复的参数用逗号分隔多个值
+        ArgAction::Append => {
+            for item in raw.split(',') {
+                out.push(format!("--{}={}", long, item).into());
+            }
+        }
+        _ => out.push(format!("--{}={}", long, raw).into()),
+    }
+    Ok(())
+}
+
+fn scalar(value: &Value) -> Result<String> {
+    Ok(match value {
+        Value::String(s) => s.clone(),
+        Value::Integer(n) => n.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Datetime(d) => d.to_string(),
+        Value::Array(_) | Value::Table(_) => bail!("只接受字符串、数字或布尔值"),
+    })
+}
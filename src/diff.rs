@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::config::LatencyUnit;
+
+pub struct DiffConfig {
+    pub baseline: PathBuf,
+    pub current: PathBuf,
+    /// 吞吐下降或 p95/p99 上升超过该百分比即判为回退
+    pub threshold_pct: f64,
+}
+
+impl DiffConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.threshold_pct.is_finite() || self.threshold_pct < 0.0 {
+            bail!("--threshold 必须是非负的百分比");
+        }
+        Ok(())
+    }
+}
+
+/// bench --format json 的输出中对比需要的部分。延迟字段名带单位与 --percentiles 决定的后缀（如 p99_ms），
+/// 不能原样反序列化回 BenchResult，这里按名字从剩余字段里取
+#[derive(Deserialize)]
+struct SavedReport {
+    metadata: SavedMetadata,
+    results: Vec<SavedResult>,
+}
+
+#[derive(Deserialize)]
+struct SavedMetadata {
+    run_id: String,
+    unit: LatencyUnit,
+}
+
+#[derive(Deserialize)]
+struct SavedResult {
+    scenario: String,
+    statement_mode: Option<String>,
+    concurrency: Option<usize>,
+    repeat: Option<usize>,
+    throughput_ops: f64,
+    #[serde(flatten)]
+    fields: HashMap<String, Value>,
+}
+
+impl SavedResult {
+    fn key(&self) -> (&str, Option<&str>, Option<usize>, Option<usize>) {
+        (&self.scenario, self.statement_mode.as_deref(), self.concurrency, self.repeat)
+    }
+
+    /// 换算成纳秒的延迟字段；--percentiles 没有包含该分位点时为 None
+    fn latency_nanos(&self, name: &str, unit: LatencyUnit) -> Option<f64> {
+        let value = self.fields.get(&format!("{}_{}", name, unit.suffix()))?.as_f64()?;
+        Some(value / unit.convert_nanos(1.0))
+    }
+}
+
+/// 参与回退判断的延迟分位点
+const LATENCY_METRICS: [&str; 2] = ["p95", "p99"];
+
+fn load_report(path: &Path) -> Result<SavedReport> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("读取 {:?} 失败", path))?;
+    serde_json::from_str(&text).with_context(|| format!("解析 {:?} 失败，需要 bench --format json 写出的结果文件", path))
+}
+
+/// 逐场景比较两个结果文件，打印变化百分比；有指标回退超过阈值时返回错误，进程以非零状态退出
+pub fn run_diff(cfg: DiffConfig) -> Result<()> {
+    let baseline = load_report(&cfg.baseline)?;
+    let current = load_report(&cfg.current)?;
+    // 两边的 --unit 可以不同，延迟统一按当前文件的单位展示
+    let unit = current.metadata.unit;
+    tracing::info!(
+        "对比基线 {:?}（run_id {}）与当前 {:?}（run_id {}），回退阈值 {}%",
+        cfg.baseline,
+        baseline.metadata.run_id,
+        cfg.current,
+        current.metadata.run_id,
+        cfg.threshold_pct
+    );
+
+    let with_mode = baseline.results.iter().chain(&current.results).any(|r| r.statement_mode.is_some());
+    let with_concurrency = baseline.results.iter().chain(&current.results).any(|r| r.concurrency.is_some());
+    let with_repeat = baseline.results.iter().chain(&current.results).any(|r| r.repeat.is_some());
+    let mut headers = vec!["scenario"];
+    if with_mode {
+        headers.push("statement_mode");
+    }
+    if with_concurrency {
+        headers.push("concurrency");
+    }
+    if with_repeat {
+        headers.push("repeat");
+    }
+    headers.extend(["metric", "baseline", "current", "change", "status"]);
+    let mut lines = vec![
+        format!("| {} |", headers.join(" | ")),
+        format!("|{}|", vec![" --- "; headers.len()].join("|")),
+    ];
+    let mut push_row = |r: &SavedResult, cells: [String; 5]| {
+        let mut row = vec![r.scenario.clone()];
+        if with_mode {
+            row.push(r.statement_mode.clone().unwrap_or_default());
+        }
+        if with_concurrency {
+            row.push(r.concurrency.map(|c| c.to_string()).unwrap_or_default());
+        }
+        if with_repeat {
+            row.push(r.repeat.map(|i| i.to_string()).unwrap_or_default());
+        }
+        row.extend(cells);
+        lines.push(format!("| {} |", row.join(" | ")));
+    };
+
+    let mut regressions = 0;
+    let mut missing = 0;
+    for cur in &current.results {
+        let Some(base) = baseline.results.iter().find(|b| b.key() == cur.key()) else {
+            tracing::warn!("场景 {} 只在当前结果中出现，没有可比的基线", cur.scenario);
+            missing += 1;
+            push_row(cur, ["-".into(), "-".into(), "-".into(), "-".into(), "基线中缺少".into()]);
+            continue;
+        };
+        // 吞吐越高越好，延迟越低越好；统一成“变差的百分比”与阈值比较
+        let mut metrics = vec![(
+            "throughput_ops".to_string(),
+            Some(base.throughput_ops),
+            Some(cur.throughput_ops),
+            -1.0,
+        )];
+        for name in LATENCY_METRICS {
+            metrics.push((
+                format!("{}_{}", name, unit.suffix()),
+                base.latency_nanos(name, baseline.metadata.unit).map(|v| unit.convert_nanos(v)),
+                cur.latency_nanos(name, unit).map(|v| unit.convert_nanos(v)),
+                1.0,
+            ));
+        }
+        for (metric, base_value, cur_value, worse_sign) in metrics {
+            let (Some(b), Some(c)) = (base_value, cur_value) else {
+                let side = if base_value.is_none() { "基线" } else { "当前" };
+                let status = format!("{}缺少该指标", side);
+                push_row(cur, [metric, fmt_value(base_value), fmt_value(cur_value), "-".into(), status]);
+                continue;
+            };
+            let change = (b > 0.0).then(|| (c - b) / b * 100.0);
+            let regressed = change.is_some_and(|pct| pct * worse_sign > cfg.threshold_pct);
+            if regressed {
+                regressions += 1;
+                tracing::warn!("场景 {} 的 {} 回退: {:.3} -> {:.3}", cur.scenario, metric, b, c);
+            }
+            push_row(
+                cur,
+                [
+                    metric,
+                    format!("{:.3}", b),
+                    format!("{:.3}", c),
+                    change.map(|pct| format!("{:+.1}%", pct)).unwrap_or_else(|| "-".into()),
+                    if regressed { "回退".into() } else { String::new() },
+                ],
+            );
+        }
+    }
+    for base in &baseline.results {
+        if !current.results.iter().any(|c| c.key() == base.key()) {
+            tracing::warn!("场景 {} 只在基线中出现，当前结果里没有", base.scenario);
+            missing += 1;
+            push_row(base, ["-".into(), "-".into(), "-".into(), "-".into(), "当前结果中缺少".into()]);
+        }
+    }
+    println!("{}", lines.join("\n"));
+
+    if missing > 0 {
+        tracing::warn!("{} 个场景只在其中一个文件中出现，未参与回退判断", missing);
+    }
+    if regressions > 0 {
+        bail!("{} 项指标回退超过 {}%", regressions, cfg.threshold_pct);
+    }
+    tracing::info!("没有指标回退超过 {}%", cfg.threshold_pct);
+    Ok(())
+}
+
+fn fmt_value(value: Option<f64>) -> String {
+    value.map(|v| format!("{:.3}", v)).unwrap_or_else(|| "-".into())
+}
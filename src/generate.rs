@@ -0,0 +1,321 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, FixedLenByteArray, FixedLenByteArrayType, Int32Type, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+use crate::bench::csv_field;
+use crate::config::{BatchSize, ExportFormat, PkStrategy};
+use crate::generator::{EventGenerator, EventRow, RowKey};
+use crate::load::LoadConfig;
+
+/// 保留到微秒，与两边 TIMESTAMP 列能存下的精度一致
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.6f";
+
+type FileWriter = BufWriter<File>;
+
+pub struct GenerateConfig {
+    pub out: PathBuf,
+    pub format: ExportFormat,
+    /// 行数、并发、批大小与生成器参数；写库相关的字段不使用
+    pub data: LoadConfig,
+}
+
+/// 多个 worker 并行生成，单个写入线程按 worker 编号轮流各取一批落盘，输出与线程调度无关，同样的参数与种子
+/// 总是得到同样顺序的文件；自增主键下 id 由写入线程按文件顺序编号
+pub async fn run_generate(cfg: GenerateConfig) -> Result<()> {
+    let data = &cfg.data;
+    let workers = data.concurrency;
+    let batch_size = match data.batch_size {
+        BatchSize::Fixed(n) => n,
+        BatchSize::Auto => unreachable!("generate 只接受固定批大小"),
+    };
+    let file = File::create(&cfg.out).with_context(|| format!("创建输出文件 {:?} 失败", cfg.out))?;
+    let mut sink = RowWriter::new(cfg.format, BufWriter::new(file), data.pk)?;
+    tracing::info!(
+        "生成 {} 行到 {:?}（{:?}），{} 个 worker，每批 {} 行",
+        data.scale,
+        cfg.out,
+        cfg.format,
+        workers,
+        batch_size
+    );
+
+    let start = Instant::now();
+    let mut batches = Vec::with_capacity(workers);
+    let base_quota = data.scale / workers as u64;
+    let remainder = data.scale % workers as u64;
+    let mut tasks = JoinSet::new();
+    for worker_id in 0..workers {
+        let quota = base_quota + if worker_id < remainder as usize { 1 } else { 0 };
+        // 种子派生方式与 MySQL 装载一致，同样的参数下生成的数据相同
+        let seed = data.seed.map_or(worker_id as u64 + 1, |seed| seed.wrapping_add(worker_id as u64));
+        let mut generator = EventGenerator::with_seed(data.generator_config(data.scale), seed)
+            .with_lane(worker_id as u64, workers as u64);
+        let (tx, rx) = mpsc::channel::<Vec<EventRow>>(2);
+        batches.push(rx);
+        tasks.spawn_blocking(move || {
+            let mut generated = 0;
+            while generated < quota {
+                let rows = generator.next_batch((quota - generated).min(batch_size as u64) as usize);
+                generated += rows.len() as u64;
+                // 写入线程出错退出后通道关闭，错误由写入线程返回
+                if tx.blocking_send(rows).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    let writer = tokio::task::spawn_blocking(move || -> Result<u64> {
+        let mut written: u64 = 0;
+        while !batches.is_empty() {
+            let mut i = 0;
+            while i < batches.len() {
+                // 通道关闭说明该 worker 已生成完自己的份额，之后的轮次跳过它
+                let Some(rows) = batches[i].blocking_recv() else {
+                    batches.remove(i);
+                    continue;
+                };
+                i += 1;
+                sink.write(&rows)?;
+                let prev = written;
+                written += rows.len() as u64;
+                if written / 1_000_000 != prev / 1_000_000 {
+                    let rps = written as f64 / start.elapsed().as_secs_f64().max(0.001);
+                    tracing::info!("已生成 {} 行, {:.2} rows/s", written, rps);
+                }
+            }
+        }
+        sink.finish()?;
+        Ok(written)
+    });
+    let written = writer.await??;
+    while let Some(res) = tasks.join_next().await {
+        res?;
+    }
+
+    tracing::info!(
+        "生成完成，共 {} 行，耗时 {:.2}s，输出 {:?}",
+        written,
+        start.elapsed().as_secs_f64(),
+        cfg.out
+    );
+    if let Some(hist) = &data.user_histogram {
+        hist.report(&data.user_dist());
+    }
+    Ok(())
+}
+
+/// 输出文件中的 id：自增主键按写入顺序从 1 编号，否则沿用生成器给出的主键
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ExportId {
+    Int(i64),
+    Uuid(String),
+}
+
+#[derive(Serialize)]
+struct JsonRow<'a> {
+    id: ExportId,
+    user_id: i64,
+    created_at: String,
+    amount: Option<f64>,
+    status: i16,
+    category: Option<i32>,
+    payload: &'a str,
+}
+
+enum Output {
+    Csv(FileWriter),
+    Jsonl(FileWriter),
+    Parquet(Box<SerializedFileWriter<FileWriter>>),
+}
+
+struct RowWriter {
+    output: Output,
+    /// 自增主键下最近一次分配的 id
+    next_id: i64,
+}
+
+impl RowWriter {
+    fn new(format: ExportFormat, mut out: FileWriter, pk: PkStrategy) -> Result<Self> {
+        let output = match format {
+            ExportFormat::Csv => {
+                writeln!(out, "id,user_id,created_at,amount,status,category,payload")?;
+                Output::Csv(out)
+            }
+            ExportFormat::Jsonl => Output::Jsonl(out),
+            ExportFormat::Parquet => {
+                let id = match pk {
+                    PkStrategy::Uuid => "REQUIRED FIXED_LEN_BYTE_ARRAY(16) id (UUID);",
+                    _ => "REQUIRED INT64 id;",
+                };
+                let schema = format!(
+                    "message events {{
+                        {}
+                        REQUIRED INT64 user_id;
+                        REQUIRED INT64 created_at (TIMESTAMP(MICROS,false));
+                        OPTIONAL INT64 amount (DECIMAL(10,2));
+                        REQUIRED INT32 status (INTEGER(16,true));
+                        OPTIONAL INT32 category;
+                        REQUIRED BYTE_ARRAY payload (STRING);
+                    }}",
+                    id
+                );
+                let props = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let writer = SerializedFileWriter::new(out, Arc::new(parse_message_type(&schema)?), Arc::new(props))?;
+                Output::Parquet(Box::new(writer))
+            }
+        };
+        Ok(Self { output, next_id: 0 })
+    }
+
+    fn next_id(&mut self, row: &EventRow) -> ExportId {
+        match row.id {
+            Some(RowKey::Int(id)) => ExportId::Int(id),
+            Some(RowKey::Uuid(id)) => ExportId::Uuid(id.to_string()),
+            None => {
+                self.next_id += 1;
+                ExportId::Int(self.next_id)
+            }
+        }
+    }
+
+    fn write(&mut self, rows: &[EventRow]) -> Result<()> {
+        let ids: Vec<ExportId> = rows.iter().map(|row| self.next_id(row)).collect();
+        match &mut self.output {
+            Output::Csv(out) => {
+                for (row, id) in rows.iter().zip(ids) {
+                    let id = match id {
+                        ExportId::Int(id) => id.to_string(),
+                        ExportId::Uuid(id) => id,
+                    };
+                    // NULL 写成空字段
+                    writeln!(
+                        out,
+                        "{},{},{},{},{},{},{}",
+                        id,
+                        row.user_id,
+                        row.created_at.format(TIME_FORMAT),
+                        row.amount.map(|a| format!("{:.2}", a)).unwrap_or_default(),
+                        row.status,
+                        row.category.map(|c| c.to_string()).unwrap_or_default(),
+                        csv_field(&row.payload)
+                    )?;
+                }
+            }
+            Output::Jsonl(out) => {
+                for (row, id) in rows.iter().zip(ids) {
+                    let json = JsonRow {
+                        id,
+                        user_id: row.user_id,
+                        created_at: row.created_at.format(TIME_FORMAT).to_string(),
+                        amount: row.amount,
+                        status: row.status,
+                        category: row.category,
+                        payload: &row.payload,
+                    };
+                    serde_json::to_writer(&mut *out, &json)?;
+                    out.write_all(b"\n")?;
+                }
+            }
+            Output::Parquet(writer) => write_row_group(writer, rows, ids)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self.output {
+            Output::Csv(mut out) | Output::Jsonl(mut out) => out.flush()?,
+            Output::Parquet(writer) => {
+                writer.close()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 一批写成一个 row group，列顺序与 schema 一致
+fn write_row_group(writer: &mut SerializedFileWriter<FileWriter>, rows: &[EventRow], ids: Vec<ExportId>) -> Result<()> {
+    let mut group = writer.next_row_group()?;
+    let mut index = 0;
+    while let Some(mut column) = group.next_column()? {
+        match index {
+            0 => match ids.first() {
+                Some(ExportId::Uuid(_)) => {
+                    let values: Vec<FixedLenByteArray> = rows
+                        .iter()
+                        .map(|row| match row.id {
+                            Some(RowKey::Uuid(id)) => ByteArray::from(id.as_bytes().to_vec()).into(),
+                            _ => unreachable!("uuid 主键的每一行都带 id"),
+                        })
+                        .collect();
+                    column.typed::<FixedLenByteArrayType>().write_batch(&values, None, None)?;
+                }
+                _ => {
+                    let values: Vec<i64> = ids
+                        .iter()
+                        .map(|id| match id {
+                            ExportId::Int(id) => *id,
+                            ExportId::Uuid(_) => unreachable!("同一文件的主键类型一致"),
+                        })
+                        .collect();
+                    column.typed::<Int64Type>().write_batch(&values, None, None)?;
+                }
+            },
+            1 => {
+                let values: Vec<i64> = rows.iter().map(|row| row.user_id).collect();
+                column.typed::<Int64Type>().write_batch(&values, None, None)?;
+            }
+            2 => {
+                let values: Vec<i64> = rows
+                    .iter()
+                    .map(|row| row.created_at.and_utc().timestamp_micros())
+                    .collect();
+                column.typed::<Int64Type>().write_batch(&values, None, None)?;
+            }
+            3 => {
+                // DECIMAL(10,2) 以分为单位存成整数；可空列只写非 NULL 的值，definition level 标出哪些行有值
+                let values: Vec<i64> = rows
+                    .iter()
+                    .filter_map(|row| row.amount.map(|a| (a * 100.0).round() as i64))
+                    .collect();
+                let levels: Vec<i16> = rows.iter().map(|row| row.amount.is_some() as i16).collect();
+                column.typed::<Int64Type>().write_batch(&values, Some(&levels), None)?;
+            }
+            4 => {
+                let values: Vec<i32> = rows.iter().map(|row| row.status as i32).collect();
+                column.typed::<Int32Type>().write_batch(&values, None, None)?;
+            }
+            5 => {
+                let values: Vec<i32> = rows.iter().filter_map(|row| row.category).collect();
+                let levels: Vec<i16> = rows.iter().map(|row| row.category.is_some() as i16).collect();
+                column.typed::<Int32Type>().write_batch(&values, Some(&levels), None)?;
+            }
+            _ => {
+                let values: Vec<ByteArray> = rows
+                    .iter()
+                    .map(|row| ByteArray::from(row.payload.as_bytes().to_vec()))
+                    .collect();
+                column.typed::<ByteArrayType>().write_batch(&values, None, None)?;
+            }
+        }
+        column.close()?;
+        index += 1;
+    }
+    group.close()?;
+    Ok(())
+}
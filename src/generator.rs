@@ -1,75 +1,237 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
 use chrono::{Duration as ChronoDuration, NaiveDateTime, Utc};
 use rand::distributions::{Alphanumeric, DistString};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
-use rand_distr::{Distribution as RandDistribution, Zipf};
+use uuid::Uuid;
 
-use crate::config::Distribution;
+use crate::config::{AmountRule, AmountRules, PayloadModel, PkStrategy, STATUS_VALUES};
+use crate::userdist::{UserDistSpec, UserHistogram, UserSampler};
 
 #[derive(Clone, Debug)]
 pub struct EventRow {
+    /// 自增主键策略下为空，由数据库生成
+    pub id: Option<RowKey>,
     pub user_id: i64,
     pub created_at: NaiveDateTime,
-    pub amount: f64,
+    /// 按 --null-rate 置空
+    pub amount: Option<f64>,
     pub status: i16,
-    pub category: i32,
+    /// 按 --null-rate 置空
+    pub category: Option<i32>,
     pub payload: String,
 }
 
+/// 客户端生成的主键值
+#[derive(Clone, Copy, Debug)]
+pub enum RowKey {
+    Int(i64),
+    Uuid(Uuid),
+}
+
+/// created_at 的取值模型
+#[derive(Clone, Debug)]
+pub enum TimeModel {
+    /// now 之前 span_secs 秒内随机；给出 half_life_secs 时距今时长按该半衰期指数衰减
+    /// （截断在 span_secs 内），越新的时间点越密集，否则均匀分布
+    Random { span_secs: i64, half_life_secs: Option<f64> },
+    /// 从 start 起每行递增 interval_ms
+    Monotonic { start: NaiveDateTime, interval_ms: i64 },
+}
+
+/// 生成器参数，由 LoadConfig 转换而来，每个 worker 各持一份
+#[derive(Clone, Debug)]
+pub struct GeneratorConfig {
+    /// user_id 分布，`--distribution` 也被表示为单分量的混合
+    pub user_dist: UserDistSpec,
+    /// user_id 的基数，取值范围 1..=users
+    pub users: u64,
+    pub payload_size: usize,
+    pub payload_model: PayloadModel,
+    /// payload 由 user_id 的哈希决定而非逐行随机：同一用户总是得到同一 payload
+    pub payload_seeded_by_user: bool,
+    /// 预先生成的 payload 模板数，每行从中挑一个；None 表示逐行完全随机
+    pub payload_templates: Option<usize>,
+    /// 模板模式下每行末尾替换为随机字符的长度
+    pub payload_suffix: usize,
+    /// 用户提供的语料，每行随机取一条并截断/循环补齐到 payload_size；各 worker 共享只读
+    pub payload_corpus: Option<Arc<Vec<String>>>,
+    pub pk: PkStrategy,
+    /// 按 status 决定 amount 的规则；为空时 amount 与 status 相互独立
+    pub amount_rules: AmountRules,
+    pub time: TimeModel,
+    /// amount、category 各自独立为 NULL 的概率
+    pub null_rate: f64,
+    /// --dist-report 时各 worker 共同累计的 user_id 直方图
+    pub user_histogram: Option<Arc<UserHistogram>>,
+}
+
 pub struct EventGenerator {
     rng: StdRng,
-    payload_size: usize,
-    distribution: Distribution,
-    zipf: Option<Zipf<f64>>,
+    /// 客户端主键单独用一个 RNG，由 with_key_offset 按表内已有行数重新派生
+    key_rng: StdRng,
+    seed: Option<u64>,
+    cfg: GeneratorConfig,
+    users: UserSampler,
+    // 单调时间模式下的 (下一个时间点, 步长)
+    clock: Option<(NaiveDateTime, ChronoDuration)>,
+    templates: Vec<String>,
+    // 本地直方图计数，每批结束后合并进共享直方图
+    histogram_counts: Vec<u64>,
 }
 
+// 模板用固定种子生成，所有 worker 共享同一组模板，N 即全表的模板数
+const TEMPLATE_SEED: u64 = 0x7E4D_1A7E;
+
 impl EventGenerator {
-    pub fn new(distribution: Distribution, payload_size: usize) -> Self {
-        let rng = StdRng::from_entropy();
-        let zipf = if matches!(distribution, Distribution::Zipf) {
-            Some(Zipf::new(1_000_000, 1.03).expect("zipf parameters valid"))
-        } else {
-            None
-        };
+    pub fn new(cfg: GeneratorConfig) -> Self {
+        Self::from_rng(cfg, StdRng::from_entropy(), None)
+    }
 
-        Self {
+    pub fn with_seed(cfg: GeneratorConfig, seed: u64) -> Self {
+        Self::from_rng(cfg, StdRng::seed_from_u64(seed), Some(seed))
+    }
+
+    fn from_rng(cfg: GeneratorConfig, rng: StdRng, seed: Option<u64>) -> Self {
+        // 参数已在 LoadConfig::validate 中校验
+        let users = UserSampler::new(&cfg.user_dist, cfg.users).expect("user 分布参数有效");
+        let templates = match cfg.payload_templates {
+            Some(n) => {
+                let mut template_rng = StdRng::seed_from_u64(TEMPLATE_SEED);
+                (0..n)
+                    .map(|_| sample_payload(cfg.payload_model, &mut template_rng, cfg.payload_size))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        let mut generator = Self {
             rng,
-            payload_size,
-            distribution,
-            zipf,
+            key_rng: StdRng::from_entropy(),
+            seed,
+            cfg,
+            users,
+            clock: None,
+            templates,
+            histogram_counts: Vec::new(),
+        };
+        if let Some(hist) = &generator.cfg.user_histogram {
+            generator.histogram_counts = vec![0; hist.bucket_count()];
         }
+        generator.set_lane(0, 1);
+        generator.set_key_offset(0);
+        generator
     }
 
-    pub fn with_seed(distribution: Distribution, payload_size: usize, seed: u64) -> Self {
-        let rng = StdRng::seed_from_u64(seed);
-        let zipf = if matches!(distribution, Distribution::Zipf) {
-            Some(Zipf::new(1_000_000, 1.03).expect("zipf parameters valid"))
-        } else {
-            None
-        };
+    /// 客户端主键序列按 offset（开始写入前表内已有的行数）派生：同一种子补齐、续装或重跑时
+    /// 起点不同，不会重新生成已经写过的主键；其余列的序列不受影响。未指定种子时主键本来就随机
+    pub fn with_key_offset(mut self, offset: u64) -> Self {
+        self.set_key_offset(offset);
+        self
+    }
 
-        Self {
-            rng,
-            payload_size,
-            distribution,
-            zipf,
+    fn set_key_offset(&mut self, offset: u64) {
+        if let Some(seed) = self.seed {
+            self.key_rng = StdRng::seed_from_u64(mix64(seed ^ mix64(offset)));
+        }
+    }
+
+    /// 多个 worker 交错分配单调时间点：第 lane 个取 start + (lane + i * lanes) * interval，
+    /// 各 worker 递增且互不重叠，合起来仍集中在索引的右侧边缘。随机时间模式下无效果
+    pub fn with_lane(mut self, lane: u64, lanes: u64) -> Self {
+        self.set_lane(lane, lanes);
+        self
+    }
+
+    fn set_lane(&mut self, lane: u64, lanes: u64) {
+        if let TimeModel::Monotonic { start, interval_ms } = self.cfg.time {
+            let first = start + ChronoDuration::milliseconds(interval_ms * lane as i64);
+            self.clock = Some((first, ChronoDuration::milliseconds(interval_ms * lanes as i64)));
         }
     }
 
     pub fn next_batch(&mut self, size: usize) -> Vec<EventRow> {
-        (0..size).map(|_| self.next_row()).collect()
+        // 一批内共用同一个基准时刻：逐行取系统时间在大批量生成时开销可观，而批内相差的几毫秒对
+        // 以天计的时间跨度没有影响
+        let now = Utc::now().naive_utc();
+        let rows: Vec<EventRow> = (0..size).map(|_| self.row_at(now)).collect();
+        if let Some(hist) = &self.cfg.user_histogram {
+            for row in &rows {
+                self.histogram_counts[UserHistogram::bucket_of(row.user_id)] += 1;
+            }
+            hist.merge(&mut self.histogram_counts);
+        }
+        rows
+    }
+
+    /// 单独生成一行；不经过 next_batch，不计入 --dist-report 直方图
+    pub fn next_row(&mut self) -> EventRow {
+        self.row_at(Utc::now().naive_utc())
     }
 
-    fn next_row(&mut self) -> EventRow {
+    /// 随机时间模式下 created_at 落在 now 之前的 span_secs 秒内
+    fn row_at(&mut self, now: NaiveDateTime) -> EventRow {
         let user_id = self.sample_user_id();
-        let now = Utc::now().naive_utc();
-        let created_at = now - ChronoDuration::seconds(self.rng.gen_range(0..(30 * 24 * 3600)));
-        let amount = (self.rng.gen_range(0.0f64..1000.0f64) * 100.0f64).round() / 100.0f64;
-        let status = self.rng.gen_range(0..5) as i16;
-        let category = self.rng.gen_range(0..=5000) as i32;
-        let payload = Alphanumeric.sample_string(&mut self.rng, self.payload_size);
+        let created_at = match (&mut self.clock, &self.cfg.time) {
+            (Some((next, stride)), _) => {
+                let t = *next;
+                *next += *stride;
+                t
+            }
+            (None, TimeModel::Random { span_secs, half_life_secs: None }) => {
+                now - ChronoDuration::seconds(self.rng.gen_range(0..*span_secs))
+            }
+            (None, TimeModel::Random { span_secs, half_life_secs: Some(half_life) }) => {
+                // 截断指数分布的逆变换采样：u 均匀分布在 [0, 1)，age 落在 [0, span_secs)
+                let rate = std::f64::consts::LN_2 / *half_life;
+                let u: f64 = self.rng.r#gen();
+                let age = -(1.0 - u * (1.0 - (-rate * *span_secs as f64).exp())).ln() / rate;
+                now - ChronoDuration::seconds((age as i64).min(*span_secs - 1))
+            }
+            (None, TimeModel::Monotonic { .. }) => unreachable!("单调时间模式在构造时设置了 clock"),
+        };
+        let mut amount = (self.rng.gen_range(0.0f64..1000.0f64) * 100.0f64).round() / 100.0f64;
+        let status = self.rng.gen_range(0..STATUS_VALUES as i32) as i16;
+        // 先照常抽 amount 再覆盖，未命中规则的行与默认模式的随机序列一致
+        match self.cfg.amount_rules.get(status) {
+            Some(AmountRule::Fixed(v)) => amount = v,
+            Some(AmountRule::Range(lo, hi)) => {
+                amount = (self.rng.gen_range(lo..hi) * 100.0f64).round() / 100.0f64;
+            }
+            None => {}
+        }
+        let category = self.rng.gen_range(0..=5000);
+        let amount = self.null_or(amount);
+        let category = self.null_or(category);
+        let payload = if self.cfg.payload_seeded_by_user {
+            // 独立的子 RNG，不影响主 RNG 的序列
+            let mut sub = StdRng::seed_from_u64(mix64(user_id as u64));
+            sample_payload(self.cfg.payload_model, &mut sub, self.cfg.payload_size)
+        } else if let Some(corpus) = &self.cfg.payload_corpus {
+            let entry = &corpus[self.rng.gen_range(0..corpus.len())];
+            entry.chars().cycle().take(self.cfg.payload_size).collect()
+        } else if !self.templates.is_empty() {
+            let template = &self.templates[self.rng.gen_range(0..self.templates.len())];
+            let keep = self.cfg.payload_size - self.cfg.payload_suffix;
+            let mut payload = template[..keep].to_string();
+            Alphanumeric.append_string(&mut self.rng, &mut payload, self.cfg.payload_suffix);
+            payload
+        } else {
+            sample_payload(self.cfg.payload_model, &mut self.rng, self.cfg.payload_size)
+        };
+        // 主键用单独的 RNG，其余列的随机序列与自增策略下保持一致
+        let id = match self.cfg.pk {
+            PkStrategy::AutoIncrement => None,
+            PkStrategy::Uuid => Some(RowKey::Uuid(
+                uuid::Builder::from_random_bytes(self.key_rng.r#gen()).into_uuid(),
+            )),
+            PkStrategy::RandomInt => Some(RowKey::Int(self.key_rng.gen_range(1..=i64::MAX))),
+        };
 
         EventRow {
+            id,
             user_id,
             created_at,
             amount,
@@ -79,10 +241,157 @@ impl EventGenerator {
         }
     }
 
+    /// 以 null_rate 的概率返回 None；null_rate 为 0 时不消耗随机数，序列与不置空时一致
+    fn null_or<T>(&mut self, value: T) -> Option<T> {
+        if self.cfg.null_rate > 0.0 && self.rng.gen_bool(self.cfg.null_rate) {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
     fn sample_user_id(&mut self) -> i64 {
-        match (self.distribution, &self.zipf) {
-            (Distribution::Zipf, Some(zipf)) => zipf.sample(&mut self.rng) as i64,
-            _ => self.rng.gen_range(1..=1_000_000),
+        self.users.sample(&mut self.rng)
+    }
+}
+
+/// splitmix64 的混洗步骤：把相邻的 user_id 打散成差异很大的种子，且跨平台/版本稳定
+fn mix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// words/json 模型的词表，均为 ASCII，截断时不会切开多字节字符
+const WORDS: [&str; 24] = [
+    "lorem", "ipsum", "dolor", "sit", "amet", "consectetur", "adipiscing", "elit", "sed", "do", "eiusmod",
+    "tempor", "incididunt", "ut", "labore", "et", "dolore", "magna", "aliqua", "enim", "ad", "minim",
+    "veniam", "quis",
+];
+const JSON_EVENTS: [&str; 4] = ["view", "click", "purchase", "refund"];
+/// json 模型的固定字段最长占用的字节数，--payload-size 不能小于它
+pub const JSON_PAYLOAD_MIN: usize = 80;
+
+/// 按内容模型生成恰好 size 字节的 payload
+fn sample_payload(model: PayloadModel, rng: &mut StdRng, size: usize) -> String {
+    match model {
+        PayloadModel::Random => Alphanumeric.sample_string(rng, size),
+        PayloadModel::Words => {
+            let mut text = String::with_capacity(size + 16);
+            push_words(rng, &mut text, size);
+            text
+        }
+        PayloadModel::Json => {
+            let mut doc = format!(
+                r#"{{"event":"{}","level":{},"tags":["{}","{}"],"note":""#,
+                JSON_EVENTS[rng.gen_range(0..JSON_EVENTS.len())],
+                rng.gen_range(0..10),
+                WORDS[rng.gen_range(0..WORDS.len())],
+                WORDS[rng.gen_range(0..WORDS.len())],
+            );
+            // note 填充到总长恰好为 size，文档仍是合法 JSON
+            let note_len = size - doc.len() - 2;
+            let start = doc.len();
+            push_words(rng, &mut doc, start + note_len);
+            doc.push_str(r#""}"#);
+            doc
+        }
+        PayloadModel::Zeros => "0".repeat(size),
+    }
+}
+
+/// 追加空格分隔的随机词，直到 text 恰好为 len 字节
+fn push_words(rng: &mut StdRng, text: &mut String, len: usize) {
+    let start = text.len();
+    while text.len() < len {
+        if text.len() > start {
+            text.push(' ');
+        }
+        text.push_str(WORDS[rng.gen_range(0..WORDS.len())]);
+    }
+    text.truncate(len);
+}
+
+/// 读取 payload 语料：文件按行，每个非空行一条；目录按文件名排序，每个文件的内容一条。
+/// 非 UTF-8 内容按有损方式转换，NUL 字符会被去掉（Postgres 文本类型不接受）
+pub fn load_corpus(path: &Path) -> Result<Vec<String>> {
+    let clean = |bytes: &[u8]| String::from_utf8_lossy(bytes).replace('\0', "");
+    let entries: Vec<String> = if path.is_dir() {
+        let mut files: Vec<_> = std::fs::read_dir(path)
+            .with_context(|| format!("读取语料目录 {:?} 失败", path))?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file())
+            .collect();
+        files.sort();
+        let mut entries = Vec::with_capacity(files.len());
+        for file in files {
+            let bytes = std::fs::read(&file).with_context(|| format!("读取语料文件 {:?} 失败", file))?;
+            entries.push(clean(&bytes));
+        }
+        entries
+    } else {
+        let bytes = std::fs::read(path).with_context(|| format!("读取语料文件 {:?} 失败", path))?;
+        clean(&bytes).lines().map(str::to_string).collect()
+    };
+    let entries: Vec<String> = entries.into_iter().filter(|e| !e.trim().is_empty()).collect();
+    if entries.is_empty() {
+        bail!("payload 语料 {:?} 为空（没有非空的行或文件）", path);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Distribution;
+
+    fn test_config(pk: PkStrategy) -> GeneratorConfig {
+        GeneratorConfig {
+            user_dist: UserDistSpec::single(Distribution::Uniform),
+            users: 1000,
+            payload_size: 16,
+            payload_model: PayloadModel::Random,
+            payload_seeded_by_user: false,
+            payload_templates: None,
+            payload_suffix: 0,
+            payload_corpus: None,
+            pk,
+            amount_rules: AmountRules::default(),
+            time: TimeModel::Random { span_secs: 30 * 24 * 3600, half_life_secs: None },
+            null_rate: 0.0,
+            user_histogram: None,
+        }
+    }
+
+    fn keys(mut generator: EventGenerator, rows: usize) -> Vec<String> {
+        generator.next_batch(rows).iter().map(|r| format!("{:?}", r.id)).collect()
+    }
+
+    #[test]
+    fn batch_created_at_spans_the_whole_window() {
+        // 一批共用同一个基准时刻，created_at 仍应铺满它之前的 30 天
+        let before = Utc::now().naive_utc();
+        let rows = EventGenerator::with_seed(test_config(PkStrategy::AutoIncrement), 7).next_batch(100_000);
+        let after = Utc::now().naive_utc();
+        let min = rows.iter().map(|r| r.created_at).min().unwrap();
+        let max = rows.iter().map(|r| r.created_at).max().unwrap();
+        assert!(max <= after && min >= before - ChronoDuration::days(30), "{} .. {}", min, max);
+        assert!(max - min >= ChronoDuration::days(30) - ChronoDuration::hours(1), "{} .. {}", min, max);
+    }
+
+    #[test]
+    fn key_offset_changes_only_the_keys() {
+        for pk in [PkStrategy::Uuid, PkStrategy::RandomInt] {
+            let first = keys(EventGenerator::with_seed(test_config(pk), 7), 100);
+            assert_eq!(first, keys(EventGenerator::with_seed(test_config(pk), 7).with_key_offset(0), 100));
+            // 同一种子补齐时从表内已有的 100 行之后开始，主键不能与第一次装载的重复
+            let top_up = keys(EventGenerator::with_seed(test_config(pk), 7).with_key_offset(100), 100);
+            assert!(top_up.iter().all(|k| !first.contains(k)), "{:?}", pk);
         }
+        let mut plain = EventGenerator::with_seed(test_config(PkStrategy::Uuid), 7);
+        let mut shifted = EventGenerator::with_seed(test_config(PkStrategy::Uuid), 7).with_key_offset(100);
+        let (a, b) = (plain.next_row(), shifted.next_row());
+        assert_eq!((a.user_id, a.status, a.category, a.payload), (b.user_id, b.status, b.category, b.payload));
     }
 }
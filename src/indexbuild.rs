@@ -0,0 +1,176 @@
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use mysql_async::prelude::Queryable;
+use mysql_async::Pool as MyPool;
+use serde::Serialize;
+use tokio_postgres::Client as PgClient;
+
+use crate::config::{BuildMode, DbConfig, DbKind, SecondaryIndex};
+use crate::connect;
+use crate::load::{count_mysql_rows, count_postgres_rows, drop_mysql_index};
+
+#[derive(Debug)]
+pub struct IndexBuildConfig {
+    pub index: SecondaryIndex,
+    pub mode: BuildMode,
+}
+
+#[derive(Debug, Serialize)]
+struct IndexBuildResult {
+    db: DbKind,
+    index: SecondaryIndex,
+    mode: BuildMode,
+    rows: u64,
+    duration_s: f64,
+    /// 拿不到统计信息（如没有 mysql 库的读权限）时为空
+    size_bytes: Option<u64>,
+}
+
+/// 删掉已有的同名索引后计时重建，输出耗时与建成后的索引大小；建完的索引保留在表上
+pub async fn run_build_index(db: DbConfig, cfg: IndexBuildConfig) -> Result<()> {
+    let modes: &[BuildMode] = match cfg.mode {
+        BuildMode::Both => &[BuildMode::Offline, BuildMode::Online],
+        BuildMode::Offline => &[BuildMode::Offline],
+        BuildMode::Online => &[BuildMode::Online],
+    };
+    let rows = match db.kind {
+        DbKind::Mysql => count_mysql_rows(&db).await?,
+        DbKind::Postgres => count_postgres_rows(&db).await?,
+    };
+    if rows == 0 {
+        tracing::warn!("events 表为空，建索引耗时没有参考意义");
+    }
+
+    let mut results = Vec::with_capacity(modes.len());
+    match db.kind {
+        DbKind::Mysql => {
+            let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+            for &mode in modes {
+                let (duration_s, size_bytes) = build_mysql(&pool, cfg.index, mode).await?;
+                results.push(IndexBuildResult {
+                    db: db.kind,
+                    index: cfg.index,
+                    mode,
+                    rows,
+                    duration_s,
+                    size_bytes,
+                });
+            }
+            pool.disconnect().await?;
+        }
+        DbKind::Postgres => {
+            let (client, connection) = connect::postgres(&db.url, &db.connect).await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    tracing::error!("Postgres 连接任务出错: {}", e);
+                }
+            });
+            for &mode in modes {
+                let (duration_s, size_bytes) = build_postgres(&client, cfg.index, mode).await?;
+                results.push(IndexBuildResult {
+                    db: db.kind,
+                    index: cfg.index,
+                    mode,
+                    rows,
+                    duration_s,
+                    size_bytes: Some(size_bytes),
+                });
+            }
+        }
+    }
+
+    for r in &results {
+        tracing::info!(
+            "{} {:?} 建索引耗时 {:.2}s（{} 行），索引大小 {}",
+            r.index.name(),
+            r.mode,
+            r.duration_s,
+            r.rows,
+            r.size_bytes
+                .map(|b| format!("{:.1}MB", b as f64 / 1024.0 / 1024.0))
+                .unwrap_or_else(|| "未知".to_string())
+        );
+    }
+    println!("{}", serde_json::to_string_pretty(&results)?);
+    Ok(())
+}
+
+async fn build_mysql(
+    pool: &MyPool,
+    index: SecondaryIndex,
+    mode: BuildMode,
+) -> Result<(f64, Option<u64>)> {
+    let mut conn = pool.get_conn().await.context("连接 MySQL 失败")?;
+    drop_mysql_index(&mut conn, index.name()).await?;
+    // COPY 会重建整张表，耗时包含复制数据；INPLACE 只排序并写入索引，期间不阻塞 DML
+    let algorithm = match mode {
+        BuildMode::Online => "ALGORITHM=INPLACE, LOCK=NONE",
+        _ => "ALGORITHM=COPY",
+    };
+    let sql = format!(
+        "ALTER TABLE events ADD INDEX {} ({}), {}",
+        index.name(),
+        index.columns(),
+        algorithm
+    );
+    tracing::info!("{}", sql);
+    let start = Instant::now();
+    conn.query_drop(&sql)
+        .await
+        .with_context(|| format!("建索引失败: {}", sql))?;
+    let duration_s = start.elapsed().as_secs_f64();
+
+    // 先刷新持久化统计信息，否则 innodb_index_stats 里可能还是旧值
+    conn.query_drop("ANALYZE TABLE events").await?;
+    let size: Result<Option<u64>, _> = conn
+        .exec_first(
+            "SELECT stat_value * @@innodb_page_size FROM mysql.innodb_index_stats \
+             WHERE database_name = DATABASE() AND table_name = 'events' AND index_name = ? AND stat_name = 'size'",
+            (index.name(),),
+        )
+        .await;
+    let size_bytes = match size {
+        Ok(size) => size,
+        Err(e) => {
+            tracing::warn!("读取 mysql.innodb_index_stats 失败，无法得到索引大小: {}", e);
+            None
+        }
+    };
+    conn.disconnect().await?;
+    Ok((duration_s, size_bytes))
+}
+
+async fn build_postgres(
+    client: &PgClient,
+    index: SecondaryIndex,
+    mode: BuildMode,
+) -> Result<(f64, u64)> {
+    client
+        .batch_execute(&format!("DROP INDEX IF EXISTS {}", index.name()))
+        .await?;
+    // CONCURRENTLY 不能放在事务块里，这里每条语句单独执行即可
+    let create = match mode {
+        BuildMode::Online => "CREATE INDEX CONCURRENTLY",
+        _ => "CREATE INDEX",
+    };
+    let sql = format!("{} {} ON public.events ({})", create, index.name(), index.columns());
+    tracing::info!("{}", sql);
+    let start = Instant::now();
+    client
+        .batch_execute(&sql)
+        .await
+        .with_context(|| format!("建索引失败: {}", sql))?;
+    let duration_s = start.elapsed().as_secs_f64();
+
+    // 索引名来自固定的枚举，直接内联，避免走预处理语句（兼容事务级连接池）
+    let sql = format!("SELECT pg_relation_size('public.{}'::regclass)", index.name());
+    let size: i64 = client
+        .query_typed(&sql, &[])
+        .await?
+        .into_iter()
+        .next()
+        .map(|r| r.get(0))
+        .unwrap_or(0);
+    Ok((duration_s, size.max(0) as u64))
+}
@@ -1,115 +1,763 @@
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use chrono::{Datelike, Timelike};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDateTime, Timelike, Utc};
 use mysql_async::{prelude::*, Conn as MyConn, Params as MyParams, Pool as MyPool, Value as MyValue};
 use tokio::task::JoinSet;
 use tokio::time::Instant;
+use tokio_postgres::types::{ToSql, Type as PgType};
 use tokio_postgres::Client as PgClient;
+use tracing::Instrument;
 use bytes::Bytes;
-use futures_util::{pin_mut, sink::SinkExt};
+use futures_util::sink::SinkExt;
+use futures_util::stream::StreamExt;
 
-use crate::config::{DbConfig, Distribution, IndexMode};
-use crate::generator::{EventGenerator, EventRow};
+use crate::config::{
+    AmountRules, BatchSize, Column, DbConfig, Distribution, IndexMode, LoadMethod, PayloadModel, PayloadType,
+    PkStrategy, Pooler, SecondaryIndex, TimeMode,
+};
+use crate::checkpoint::{self, Progress};
+use crate::connect;
+use crate::generator::{EventGenerator, EventRow, GeneratorConfig, RowKey, TimeModel, JSON_PAYLOAD_MIN};
+use crate::preflight;
+use crate::progress_bar;
+use crate::resources::ResourceSampler;
+use crate::retry::{is_connection_error, is_retryable, RetryPolicy};
+use crate::schema;
+use crate::shutdown;
+use crate::storage;
+use crate::userdist::{check_normal_params, check_zipf_theta, UserDistSpec, UserHistogram, UserSampler};
+use crate::verify;
 
 pub struct LoadConfig {
     pub scale: u64,
     pub concurrency: usize,
-    pub batch_size: usize,
+    pub batch_size: BatchSize,
     pub distribution: Distribution,
+    /// 加权混合的 user_id 分布，指定时覆盖 distribution
+    pub user_dist: Option<UserDistSpec>,
+    /// --distribution zipf 的指数
+    pub zipf_theta: f64,
+    /// --distribution normal 的均值与标准差，未指定时按 users 取默认值
+    pub dist_mean: Option<f64>,
+    pub dist_stddev: Option<f64>,
+    /// user_id 的基数，取值范围 1..=users
+    pub users: u64,
     pub payload_size: usize,
+    /// payload 内容模型：随机字符、词、JSON 或全零，决定数据的可压缩程度
+    pub payload_model: PayloadModel,
+    /// payload 列的类型；JSON 列时 payload 总是按 json 模型生成
+    pub payload_type: PayloadType,
+    pub payload_seeded_by_user: bool,
+    pub payload_templates: Option<usize>,
+    pub payload_template_suffix: usize,
+    /// 已读入内存的 payload 语料
+    pub payload_corpus: Option<Arc<Vec<String>>>,
+    /// 主键策略；非自增时由生成器产生 id 并写入
+    pub pk: PkStrategy,
+    /// amount 按 status 取值的规则，未指定时两列独立随机
+    pub amount_by_status: Option<AmountRules>,
+    /// 装载时累计生成的 user_id 直方图，结束后与理论分布对比输出
+    pub user_histogram: Option<Arc<UserHistogram>>,
+    /// 所有 worker 在途批次的内存上限（MB），按它收紧单批行数
+    pub max_memory_mb: Option<u64>,
+    /// 装载结束后执行 FLUSH TABLES / CHECKPOINT 并报告耗时
+    pub flush: bool,
     pub indexes: IndexMode,
+    pub resource_stats: bool,
+    /// 不写入、交给数据库默认值/生成列计算的列
+    pub skip_columns: Vec<Column>,
+    pub time_mode: TimeMode,
+    /// created_at 覆盖的天数：随机模式在最近这些天内取值，单调模式未指定间隔时让新增的行铺满这段时间
+    pub time_span_days: u64,
+    /// 随机时间模式下 created_at 向近期倾斜的半衰期（天）：距今每多这么久，密度减半；None 为均匀分布
+    pub recency_half_life_days: Option<f64>,
+    /// amount、category 各自独立为 NULL 的概率，0 表示不写入 NULL
+    pub null_rate: f64,
+    /// 单调时间模式下相邻两行的间隔；未指定时按 time_span_days 推算
+    pub time_interval_ms: Option<u64>,
+    /// 死锁、连接断开等瞬时错误的重试策略
+    pub retry: RetryPolicy,
+    /// 写入方式；未指定时 MySQL 用 INSERT，Postgres 用 COPY
+    pub load_method: Option<LoadMethod>,
+    /// 进度日志文件：定期记录已提交的行数。再次装载总是按表内行数补齐，文件只用于核对与留档
+    pub checkpoint: Option<PathBuf>,
+    /// events 表不存在时按主键策略建表
+    pub create_table: bool,
+    /// 装载前先删表再重建，用于从空表重跑
+    pub drop_table: bool,
+    /// 装载结束后的表与索引大小另以 JSON 写入该文件
+    pub output: Option<PathBuf>,
+    /// 装载结束后精确计数并计算整表校验和，行数不等于目标时报错
+    pub verify: bool,
+    /// 生成器种子，worker i 使用 seed + i；未指定时 MySQL 各 worker 用 i + 1，Postgres 随机
+    pub seed: Option<u64>,
+}
+
+impl LoadConfig {
+    /// 单连接（Postgres）装载用的生成器；rows 为本次要写的行数，表内已有 scale - rows 行
+    fn single_generator(&self, rows: u64) -> EventGenerator {
+        match self.seed {
+            Some(seed) => EventGenerator::with_seed(self.generator_config(rows), seed).with_key_offset(self.scale - rows),
+            None => EventGenerator::new(self.generator_config(rows)),
+        }
+    }
+
+    /// rows 为本次要生成的行数，单调时间模式据此让时间线结束于当前时刻
+    pub fn generator_config(&self, rows: u64) -> GeneratorConfig {
+        let time = match self.time_mode {
+            TimeMode::Random => TimeModel::Random {
+                span_secs: self.time_span_days as i64 * SECS_PER_DAY,
+                half_life_secs: self.recency_half_life_days.map(|days| days * SECS_PER_DAY as f64),
+            },
+            TimeMode::Monotonic => {
+                let interval_ms = match self.time_interval_ms {
+                    Some(ms) => ms as i64,
+                    None => (self.time_span_days as i64 * SECS_PER_DAY * 1000 / rows.max(1) as i64).max(1),
+                };
+                let start = Utc::now().naive_utc()
+                    - ChronoDuration::milliseconds(interval_ms.saturating_mul(rows as i64));
+                TimeModel::Monotonic { start, interval_ms }
+            }
+        };
+        GeneratorConfig {
+            user_dist: self.user_dist(),
+            users: self.users,
+            payload_size: self.payload_size,
+            payload_model: self.effective_payload_model(),
+            payload_seeded_by_user: self.payload_seeded_by_user,
+            payload_templates: self.payload_templates,
+            payload_suffix: self.payload_template_suffix,
+            payload_corpus: self.payload_corpus.clone(),
+            pk: self.pk,
+            amount_rules: self.amount_by_status.clone().unwrap_or_default(),
+            user_histogram: self.user_histogram.clone(),
+            time,
+            null_rate: self.null_rate,
+        }
+    }
+
+    fn effective_payload_model(&self) -> PayloadModel {
+        match self.payload_type {
+            PayloadType::Text => self.payload_model,
+            PayloadType::Json => PayloadModel::Json,
+        }
+    }
+
+    /// 会被写入 NULL 的列，预检要求它们允许 NULL
+    pub fn nullable_columns(&self) -> Vec<Column> {
+        if self.null_rate > 0.0 {
+            vec![Column::Amount, Column::Category]
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub fn user_dist(&self) -> UserDistSpec {
+        match (&self.user_dist, self.distribution) {
+            (Some(spec), _) => spec.clone(),
+            (None, Distribution::Zipf) => UserDistSpec::zipf(self.zipf_theta),
+            (None, Distribution::Normal) => UserDistSpec::normal(self.dist_mean, self.dist_stddev, self.users),
+            (None, distribution) => UserDistSpec::single(distribution),
+        }
+    }
+
+    /// 检查 clap 无法单独表达的参数约束
+    pub fn validate(&self) -> Result<()> {
+        if self.concurrency == 0 {
+            bail!("--concurrency 必须大于 0");
+        }
+        if self.payload_size == 0 {
+            bail!("--payload-size 必须大于 0");
+        }
+        if self.payload_templates == Some(0) {
+            bail!("--payload-templates 必须大于 0");
+        }
+        if self.payload_templates.is_some() && self.payload_seeded_by_user {
+            bail!("--payload-templates 与 --payload-seeded-by-user 不能同时使用");
+        }
+        if self.payload_corpus.is_some() && (self.payload_templates.is_some() || self.payload_seeded_by_user) {
+            bail!("--payload-corpus 不能与 --payload-templates / --payload-seeded-by-user 同时使用");
+        }
+        if self.payload_corpus.is_some() && self.payload_model != PayloadModel::Random {
+            bail!("--payload-corpus 不能与 --payload-model 同时使用");
+        }
+        if self.payload_type == PayloadType::Json {
+            if !matches!(self.payload_model, PayloadModel::Random | PayloadModel::Json) {
+                bail!("--payload-type json 只能搭配 --payload-model json");
+            }
+            if self.payload_corpus.is_some() {
+                bail!("--payload-type json 不能与 --payload-corpus 同时使用");
+            }
+            // 随机后缀会破坏模板的 JSON 结构
+            if self.payload_template_suffix > 0 {
+                bail!("--payload-type json 不能与 --payload-template-suffix 同时使用");
+            }
+        }
+        if self.effective_payload_model() == PayloadModel::Json && self.payload_size < JSON_PAYLOAD_MIN {
+            bail!("--payload-model json 需要 --payload-size 至少为 {}", JSON_PAYLOAD_MIN);
+        }
+        if self.payload_template_suffix > 0 && self.payload_templates.is_none() {
+            bail!("--payload-template-suffix 需要同时指定 --payload-templates");
+        }
+        if self.payload_template_suffix > self.payload_size {
+            bail!("--payload-template-suffix 不能超过 --payload-size");
+        }
+        if Column::ALL.iter().all(|c| self.skip_columns.contains(c)) {
+            bail!("--skip-columns 不能跳过全部列");
+        }
+        if self.time_span_days == 0 || self.time_span_days > MAX_TIME_SPAN_DAYS {
+            bail!("--time-span-days 必须在 1..={} 之间", MAX_TIME_SPAN_DAYS);
+        }
+        if !(0.0..=1.0).contains(&self.null_rate) {
+            bail!("--null-rate 必须在 0 到 1 之间");
+        }
+        if let Some(days) = self.recency_half_life_days {
+            if !days.is_finite() || days <= 0.0 {
+                bail!("--recency-bias 的半衰期必须是正的天数");
+            }
+            if self.time_mode != TimeMode::Random {
+                bail!("--recency-bias 只在 --time-mode random 下有效");
+            }
+        }
+        if self.time_interval_ms == Some(0) {
+            bail!("--time-interval-ms 必须大于 0");
+        }
+        if self.time_interval_ms.is_some() && self.time_mode != TimeMode::Monotonic {
+            bail!("--time-interval-ms 只在 --time-mode monotonic 下有效");
+        }
+        if self.max_memory_mb == Some(0) {
+            bail!("--max-memory 必须大于 0");
+        }
+        if self.memory_batch_cap(self.concurrency) == Some(0) {
+            bail!(
+                "--max-memory {}MB 不足以让 {} 个 worker 各容纳一行（每行约 {} 字节）",
+                self.max_memory_mb.unwrap_or_default(),
+                self.concurrency,
+                self.row_memory_bytes()
+            );
+        }
+        self.retry.validate()?;
+        if let Err(e) = check_zipf_theta(self.zipf_theta) {
+            bail!("--zipf-theta 无效: {}", e);
+        }
+        if (self.dist_mean.is_some() || self.dist_stddev.is_some())
+            && (self.distribution != Distribution::Normal || self.user_dist.is_some())
+        {
+            bail!("--dist-mean/--dist-stddev 只在 --distribution normal 时生效");
+        }
+        if let Err(e) = check_normal_params(self.dist_mean, self.dist_stddev) {
+            bail!("--dist-mean/--dist-stddev 无效: {}", e);
+        }
+        UserSampler::new(&self.user_dist(), self.users)?;
+        Ok(())
+    }
+
+    /// 单行在一批中的内存估算：生成的行本身，加上编码后的 INSERT 参数或 COPY 缓冲区中的一份拷贝
+    fn row_memory_bytes(&self) -> usize {
+        2 * (self.payload_size + ROW_OVERHEAD_BYTES)
+    }
+
+    /// 按 --max-memory 与 worker 数算出的单批行数上限；未设置时不限制
+    fn memory_batch_cap(&self, workers: usize) -> Option<usize> {
+        let budget = self.max_memory_mb? as usize * 1024 * 1024;
+        Some(budget / workers.max(1) / self.row_memory_bytes())
+    }
+
+    fn log_memory_cap(&self, workers: usize) -> usize {
+        match self.memory_batch_cap(workers) {
+            Some(cap) => {
+                tracing::info!(
+                    "--max-memory {}MB，{} 个 worker：单批最多 {} 行",
+                    self.max_memory_mb.unwrap_or_default(),
+                    workers,
+                    cap
+                );
+                cap
+            }
+            None => usize::MAX,
+        }
+    }
+
+    /// 实际写入的列，保持 Column::ALL 的顺序；非自增主键时 id 排在最前
+    pub fn insert_columns(&self) -> Vec<Column> {
+        let id = (self.pk != PkStrategy::AutoIncrement).then_some(Column::Id);
+        id.into_iter()
+            .chain(Column::ALL.into_iter().filter(|c| !self.skip_columns.contains(c)))
+            .collect()
+    }
+}
+
+const SECS_PER_DAY: i64 = 24 * 3600;
+// 约 100 年，远早于这个时间点的 created_at 没有测试意义
+const MAX_TIME_SPAN_DAYS: u64 = 36_500;
+// 固定批大小的上限：防止单批 payload 过大导致 PG 报 “value too large to transmit”
+const FIXED_BATCH_CAP: usize = 1_000;
+// auto 模式的起点、下限与上限
+const AUTO_BATCH_START: usize = 100;
+const AUTO_BATCH_MIN: usize = 10;
+const AUTO_BATCH_MAX: usize = 20_000;
+// MySQL 预处理语句最多 65535 个占位符，每行的个数等于写入的列数（客户端生成主键时多一个 id）
+const MYSQL_MAX_PARAMS: usize = 65_535;
+// Postgres 单条语句最多 65535 个参数，--load-method insert 按列数折算每批行数上限
+const PG_MAX_PARAMS: usize = 65_535;
+// 估算单行在协议包中的固定开销（不含 payload）
+const ROW_OVERHEAD_BYTES: usize = 64;
+
+/// 自适应批大小：每个 worker 独立做简单爬山，按 rows/s 决定增大或缩小批大小
+struct BatchTuner {
+    size: usize,
+    max: usize,
+    factor: f64,
+    growing: bool,
+    last_rate: f64,
+    window_rows: usize,
+    window_elapsed: Duration,
+    window_batches: u32,
+    converged: bool,
+}
+
+impl BatchTuner {
+    // 每个批大小观察几批再做决定，平滑单批抖动
+    const BATCHES_PER_STEP: u32 = 3;
+
+    fn new(max: usize) -> Self {
+        let max = max.max(1);
+        Self {
+            size: AUTO_BATCH_START.min(max),
+            max,
+            factor: 2.0,
+            growing: true,
+            last_rate: 0.0,
+            window_rows: 0,
+            window_elapsed: Duration::ZERO,
+            window_batches: 0,
+            converged: false,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    /// 记录一批的行数与耗时；返回 true 表示本次调用后刚刚收敛
+    fn observe(&mut self, rows: usize, elapsed: Duration) -> bool {
+        if self.converged {
+            return false;
+        }
+        self.window_rows += rows;
+        self.window_elapsed += elapsed;
+        self.window_batches += 1;
+        if self.window_batches < Self::BATCHES_PER_STEP {
+            return false;
+        }
+
+        let rate = self.window_rows as f64 / self.window_elapsed.as_secs_f64().max(1e-6);
+        self.window_rows = 0;
+        self.window_elapsed = Duration::ZERO;
+        self.window_batches = 0;
+
+        if rate < self.last_rate {
+            // 吞吐下降：掉头并缩小步长
+            self.growing = !self.growing;
+            self.factor = 1.0 + (self.factor - 1.0) / 2.0;
+        }
+        self.last_rate = rate;
+        if self.factor < 1.05 {
+            self.converged = true;
+            return true;
+        }
+
+        let next = if self.growing {
+            self.size as f64 * self.factor
+        } else {
+            self.size as f64 / self.factor
+        };
+        let next = (next.round() as usize).clamp(AUTO_BATCH_MIN.min(self.max), self.max);
+        if next == self.size {
+            // 已顶到边界，继续爬没有意义
+            self.converged = true;
+            return true;
+        }
+        self.size = next;
+        false
+    }
 }
 
 pub async fn run_load(db: DbConfig, cfg: LoadConfig) -> Result<()> {
+    // 装载前检查表结构：列类型不对时写入往往不报错，而是被隐式转换成错误的数据；
+    // 跳过的列也必须有默认值或是生成列，否则插入会违反 NOT NULL
+    let expect = preflight::Expectations {
+        payload_size: Some(cfg.payload_size),
+        require_rows: false,
+        skip_columns: cfg.skip_columns.clone(),
+        pk: Some(cfg.pk),
+        nullable_columns: cfg.nullable_columns(),
+        payload_type: Some(cfg.payload_type),
+    };
+    match (db.kind, cfg.load_method) {
+        (crate::config::DbKind::Mysql, Some(LoadMethod::Copy)) => bail!("--load-method copy 只支持 Postgres"),
+        (crate::config::DbKind::Postgres, Some(LoadMethod::LoadData)) => {
+            bail!("--load-method load-data 只支持 MySQL")
+        }
+        _ => {}
+    }
+    if cfg.create_table || cfg.drop_table {
+        let payload = schema::PayloadColumn {
+            kind: cfg.payload_type,
+            size: cfg.payload_size,
+        };
+        schema::prepare_table(&db, cfg.pk, payload, cfg.null_rate > 0.0, cfg.drop_table).await?;
+    }
+    // 表已重建，旧检查点记录的进度不再成立
+    if cfg.drop_table
+        && let Some(path) = &cfg.checkpoint
+        && path.exists()
+    {
+        std::fs::remove_file(path).with_context(|| format!("删除检查点 {:?} 失败", path))?;
+        tracing::info!("已删除旧检查点 {:?}", path);
+    }
+    preflight::check(&db, &expect).await?;
+
     // 先读取当前行数，按需补齐到目标规模
-    let current = match db.kind {
-        crate::config::DbKind::Mysql => count_mysql_rows(&db.url).await?,
-        crate::config::DbKind::Postgres => count_postgres_rows(&db.url).await?,
+    let live = match db.kind {
+        crate::config::DbKind::Mysql => count_mysql_rows(&db).await?,
+        crate::config::DbKind::Postgres => count_postgres_rows(&db).await?,
     };
-    if current >= cfg.scale {
+    if let Some(path) = &cfg.checkpoint {
+        report_checkpoint(path, cfg.scale, live)?;
+    }
+    if live >= cfg.scale {
         tracing::info!(
             "当前已有 {} 行，已达到/超过目标 {}，跳过装载",
-            current,
+            live,
             cfg.scale
         );
-        return Ok(());
+        if let Some(path) = &cfg.checkpoint {
+            Progress::new(path.clone(), cfg.scale, live, 0).save(true)?;
+        }
+        if cfg.verify {
+            verify::check(&db, live).await?;
+        }
+        return storage::report_sizes(&db, cfg.output.as_deref()).await;
     }
 
-    let remaining = cfg.scale - current;
+    let remaining = cfg.scale - live;
     tracing::info!(
         "当前已有 {} 行，目标 {} 行，本次需新增 {} 行",
-        current,
+        live,
         cfg.scale,
         remaining
     );
 
-    let mut generator = EventGenerator::new(cfg.distribution, cfg.payload_size);
+    if let Some(rules) = &cfg.amount_by_status {
+        tracing::info!("amount 按 status 规则生成: {}（其余 status 仍为 0..1000 随机）", rules);
+    }
+    let mut generator = cfg.single_generator(remaining);
+    let sampler = if cfg.resource_stats {
+        ResourceSampler::start()
+    } else {
+        None
+    };
 
-    match db.kind {
-        crate::config::DbKind::Mysql => {
-            load_mysql(&db.url, &cfg, remaining, &mut generator).await?
+    let progress = cfg.checkpoint.as_ref().map(|path| {
+        let workers = match db.kind {
+            crate::config::DbKind::Mysql => cfg.concurrency.max(1),
+            crate::config::DbKind::Postgres => 1,
+        };
+        Progress::new(path.clone(), cfg.scale, live, workers)
+    });
+    // Postgres 的单条 COPY 到结束才提交，中途没有可记录的进度，只在结束时写一次
+    let copy = db.kind == crate::config::DbKind::Postgres && cfg.load_method != Some(LoadMethod::Insert);
+    let flusher = progress.as_ref().filter(|_| !copy).map(Progress::spawn_flusher);
+    shutdown::install();
+    let result = load_rows(&db, &cfg, remaining, &mut generator, progress.as_ref()).await;
+    if let Some(flusher) = flusher {
+        flusher.abort();
+    }
+    let interrupted = shutdown::requested();
+    // 失败或中断时也把最后的进度写下来
+    if let Some(progress) = &progress {
+        progress.save(result.is_ok() && !interrupted)?;
+    }
+    let retries = result?;
+    if cfg.retry.max_retries > 0 {
+        tracing::info!("装载期间瞬时错误共重试 {} 次", retries);
+    }
+    if interrupted {
+        // 已发出的批都已提交（Postgres 的 COPY 也正常结束），重新计数即为实际写入的行数
+        let now = match db.kind {
+            crate::config::DbKind::Mysql => count_mysql_rows(&db).await?,
+            crate::config::DbKind::Postgres => count_postgres_rows(&db).await?,
+        };
+        tracing::warn!(
+            "装载被 Ctrl-C 中断：本次已提交 {} 行，表内现有 {} 行，目标 {} 行；以同样的参数重新运行即可补齐",
+            now.saturating_sub(live),
+            now,
+            cfg.scale
+        );
+        return Err(anyhow!("装载被 Ctrl-C 中断"));
+    }
+
+    if let Some(hist) = &cfg.user_histogram {
+        hist.report(&cfg.user_dist());
+    }
+    let dist = schema::RecordedUserDist {
+        spec: cfg.user_dist(),
+        users: cfg.users,
+    };
+    schema::record_user_dist(&db, &dist).await?;
+
+    if cfg.flush {
+        let start = Instant::now();
+        match db.kind {
+            crate::config::DbKind::Mysql => flush_mysql(&db).await?,
+            crate::config::DbKind::Postgres => flush_postgres(&db).await?,
         }
+        tracing::info!("装载后落盘耗时 {:.2}s", start.elapsed().as_secs_f64());
+    }
+
+    if let Some(sampler) = sampler
+        && let Some(r) = sampler.finish().await
+    {
+        tracing::info!(
+            "装载期间客户端资源: CPU 平均 {:.1}% 峰值 {:.1}%，RSS 平均 {:.1}MB 峰值 {:.1}MB",
+            r.cpu_avg_pct,
+            r.cpu_peak_pct,
+            r.rss_avg_mb,
+            r.rss_peak_mb
+        );
+    }
+
+    if cfg.verify {
+        verify::check(&db, cfg.scale).await?;
+    }
+    storage::report_sizes(&db, cfg.output.as_deref()).await
+}
+
+/// 写入 remaining 行，返回瞬时错误的重试次数
+async fn load_rows(
+    db: &DbConfig,
+    cfg: &LoadConfig,
+    remaining: u64,
+    generator: &mut EventGenerator,
+    progress: Option<&Arc<Progress>>,
+) -> Result<u64> {
+    match db.kind {
+        crate::config::DbKind::Mysql => load_mysql(db, cfg, remaining, generator, progress).await,
         crate::config::DbKind::Postgres => {
-            load_postgres(&db.url, &cfg, remaining, &mut generator).await?
+            // COPY 是一个整体，失败时已写入的部分随之回滚，只能重新计数后整段重来
+            let mut remaining = remaining;
+            let mut attempt = 0;
+            loop {
+                match load_postgres(db, cfg, remaining, generator, progress).await {
+                    Ok(()) => break,
+                    Err(e) if attempt < cfg.retry.max_retries && is_retryable(&e) => {
+                        attempt += 1;
+                        let delay = cfg.retry.delay(attempt);
+                        tracing::warn!(
+                            "Postgres 装载出错，COPY 已回滚，{:?} 后第 {} 次重试: {:#}",
+                            delay,
+                            attempt,
+                            e
+                        );
+                        tokio::time::sleep(delay).await;
+                        remaining = cfg.scale.saturating_sub(count_postgres_rows(db).await?);
+                        if remaining == 0 {
+                            break;
+                        }
+                        *generator = cfg.single_generator(remaining);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(attempt as u64)
         }
+
     }
+}
 
+/// 把上次装载的进度日志与表内行数对照着记进日志。补齐总是以表内 COUNT(*) 为准：检查点每隔几秒才落盘，
+/// 进程被杀时最后几秒提交的行不在其中，它不能说明表内哪些行可信
+fn report_checkpoint(path: &Path, scale: u64, live: u64) -> Result<()> {
+    let Some(checkpoint) = checkpoint::load(path)? else {
+        return Ok(());
+    };
+    if checkpoint.scale != scale {
+        tracing::warn!(
+            "检查点 {:?} 的目标行数 {} 与 --scale {} 不一致，本次装载会覆盖它",
+            path,
+            checkpoint.scale,
+            scale
+        );
+        return Ok(());
+    }
+    let committed = checkpoint.committed();
+    if live < committed {
+        tracing::warn!(
+            "检查点 {:?} 记录已提交 {} 行，但表内只有 {} 行，说明之后有行被删除；按表内 {} 行补齐",
+            path,
+            committed,
+            live,
+            live
+        );
+        return Ok(());
+    }
+    tracing::info!(
+        "检查点 {:?} 记录上次装载已提交 {} 行（各 worker {:?}），表内 {} 行，按表内行数补齐",
+        path,
+        committed,
+        checkpoint.workers,
+        live
+    );
     Ok(())
 }
 
-async fn load_mysql(url: &str, cfg: &LoadConfig, remaining: u64, _gen: &mut EventGenerator) -> Result<()> {
-    let pool = MyPool::new(mysql_async::Opts::from_url(url)?);
+async fn load_mysql(
+    db: &DbConfig,
+    cfg: &LoadConfig,
+    remaining: u64,
+    _gen: &mut EventGenerator,
+    progress: Option<&Arc<Progress>>,
+) -> Result<u64> {
+    let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+    let method = cfg.load_method.unwrap_or(LoadMethod::Insert);
+    let auto_cap;
     {
         let mut conn = pool
             .get_conn()
             .await
-            .with_context(|| format!("连接 MySQL 失败: {}", url))?;
+            .with_context(|| format!("连接 MySQL 失败: {}", connect::redact_url(&db.url)))?;
         configure_mysql_indexes(&mut conn, cfg.indexes).await?;
+        auto_cap = mysql_auto_batch_cap(&mut conn, cfg.payload_size, cfg.insert_columns().len()).await?;
+        if method == LoadMethod::LoadData {
+            check_mysql_local_infile(&mut conn).await?;
+            if db.pooler != Pooler::None {
+                tracing::warn!("连接池代理未必支持 LOAD DATA LOCAL INFILE（如 ProxySQL），出错时改用 --load-method insert");
+            }
+        }
         conn.disconnect().await?;
     }
-
     let workers = cfg.concurrency.max(1).min(remaining as usize);
-    let batch_cap = cfg.batch_size.min(1_000); // 防止单批 payload 过大导致 PG 报 “value too large to transmit”
+    connect::warn_pool_size(db.connect.pool_size, workers);
+    let mem_cap = cfg.log_memory_cap(workers);
+    let auto_cap = auto_cap.min(mem_cap);
+    if cfg.batch_size == BatchSize::Auto {
+        tracing::info!("MySQL 批大小自适应开启，上限 {} 行/批", auto_cap);
+    }
+
     let base_quota = remaining / workers as u64;
     let remainder = remaining % workers as u64;
     let total = Arc::new(AtomicU64::new(0));
+    let retries = Arc::new(AtomicU64::new(0));
     let start = Instant::now();
+    let bar = progress_bar::new(Some(remaining), "MySQL 装载", "行");
 
     let mut tasks = JoinSet::new();
     for worker_id in 0..workers {
         let quota = base_quota + if worker_id < remainder as usize { 1 } else { 0 };
-        let mut generator =
-            EventGenerator::with_seed(cfg.distribution, cfg.payload_size, worker_id as u64 + 1);
+        // 按 seed + worker_id 派生；同一种子下改变并发数仍会改变各 worker 分到的数据
+        let seed = cfg.seed.map_or(worker_id as u64 + 1, |seed| seed.wrapping_add(worker_id as u64));
+        let mut generator = EventGenerator::with_seed(cfg.generator_config(remaining), seed)
+            .with_lane(worker_id as u64, workers as u64)
+            .with_key_offset(cfg.scale - remaining);
         let pool = pool.clone();
-        let batch_size = batch_cap;
+        let pooler = db.pooler;
+        let batch_size = cfg.batch_size;
+        let columns = cfg.insert_columns();
+        let load_sql = mysql_load_data_sql(&columns, cfg.pk);
         let total = total.clone();
-        let start = start.clone();
+        let retries = retries.clone();
+        let retry = cfg.retry;
+        let progress = progress.cloned();
+        let bar = bar.clone();
 
         tasks.spawn(async move {
             let mut conn = pool.get_conn().await?;
             let mut inserted: u64 = 0;
+            let mut tuner = match batch_size {
+                BatchSize::Auto => Some(BatchTuner::new(auto_cap)),
+                BatchSize::Fixed(_) => None,
+            };
 
-            while inserted < quota {
+            while inserted < quota && !shutdown::requested() {
                 let remaining = (quota - inserted) as usize;
-                let this_batch = remaining.min(batch_size);
+                let this_batch =
+                    remaining.min(current_batch_size(batch_size, tuner.as_ref()).min(mem_cap));
                 let rows = generator.next_batch(this_batch);
-                let (sql, params) = build_mysql_insert(&rows);
-                conn.exec_drop(sql, params).await?;
+                let batch = match method {
+                    LoadMethod::LoadData => {
+                        let mut buf = String::new();
+                        for row in &rows {
+                            push_copy_line(&mut buf, row, &columns);
+                        }
+                        MysqlBatch::LoadData(Bytes::from(buf))
+                    }
+                    _ => {
+                        let (sql, params) = build_mysql_insert(&rows, &columns);
+                        MysqlBatch::Insert(sql, params)
+                    }
+                };
+                let batch_start = Instant::now();
+                let mut attempt = 0;
+                loop {
+                    match write_mysql_batch(&mut conn, pooler, &load_sql, &batch).await {
+                        Ok(()) => break,
+                        // 连接断开时这一批可能已经提交，自增主键下重试会多写一批，显式主键下会报主键冲突
+                        Err(e) if attempt < retry.max_retries && is_retryable(&e) => {
+                            attempt += 1;
+                            retries.fetch_add(1, Ordering::Relaxed);
+                            let delay = retry.delay(attempt);
+                            tracing::warn!(
+                                "MySQL worker {} 写入出错，{:?} 后第 {} 次重试: {:#}",
+                                worker_id,
+                                delay,
+                                attempt,
+                                e
+                            );
+                            tokio::time::sleep(delay).await;
+                            if is_connection_error(&e) {
+                                // 换不上新连接时沿用旧连接，下一轮会再次失败并重试
+                                match pool.get_conn().await {
+                                    Ok(fresh) => conn = fresh,
+                                    Err(e) => tracing::warn!("MySQL worker {} 重新获取连接失败: {}", worker_id, e),
+                                }
+                            }
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
                 inserted += rows.len() as u64;
+                if let Some(progress) = &progress {
+                    progress.add(worker_id, rows.len() as u64);
+                }
+                if let Some(tuner) = tuner.as_mut()
+                    && tuner.observe(rows.len(), batch_start.elapsed())
+                {
+                    tracing::info!("MySQL worker {} 批大小已收敛为 {} 行", worker_id, tuner.size());
+                }
 
                 let prev = total.fetch_add(rows.len() as u64, Ordering::Relaxed);
                 let new_total = prev + rows.len() as u64;
-                if new_total / 100_000 != prev / 100_000 {
+                if let Some(bar) = &bar {
+                    bar.inc(rows.len() as u64);
+                } else if new_total / 100_000 != prev / 100_000 {
                     let rps = new_total as f64 / start.elapsed().as_secs_f64().max(0.001);
                     tracing::info!("MySQL 已插入 {} 行, {:.2} rows/s", new_total, rps);
                 }
             }
 
+            if let Some(tuner) = tuner.as_ref()
+                && !tuner.converged
+            {
+                tracing::info!("MySQL worker {} 装载结束时批大小未收敛，最终 {} 行", worker_id, tuner.size());
+            }
             conn.disconnect().await?;
             Ok::<(), anyhow::Error>(())
-        });
+        }.instrument(tracing::info_span!("worker", worker = worker_id)));
     }
 
     while let Some(res) = tasks.join_next().await {
@@ -128,11 +776,74 @@ async fn load_mysql(url: &str, cfg: &LoadConfig, remaining: u64, _gen: &mut Even
         conn.disconnect().await?;
     }
     pool.disconnect().await?;
+    Ok(retries.load(Ordering::Relaxed))
+}
+
+/// MySQL 的一批数据：多行 INSERT 及其参数，或交给 LOAD DATA 的文本
+enum MysqlBatch {
+    Insert(String, MyParams),
+    LoadData(Bytes),
+}
+
+async fn write_mysql_batch(conn: &mut MyConn, pooler: Pooler, load_sql: &str, batch: &MysqlBatch) -> Result<()> {
+    match batch {
+        MysqlBatch::Insert(sql, params) if pooler == Pooler::Transaction => {
+            // 事务级连接池下不使用服务端预处理语句
+            let MyParams::Positional(values) = params else {
+                unreachable!("build_mysql_insert 只生成位置参数")
+            };
+            conn.query_drop(inline_mysql_params(sql, values)).await?;
+        }
+        MysqlBatch::Insert(sql, params) => conn.exec_drop(sql, params.clone()).await?,
+        MysqlBatch::LoadData(data) => {
+            // 处理器每条 LOAD DATA 用完即取走，重试时也要重新设置；Bytes 克隆只增加引用计数
+            let data = data.clone();
+            conn.set_infile_handler(async move {
+                Ok(futures_util::stream::once(async move { Ok(data) }).boxed())
+            });
+            conn.query_drop(load_sql).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 服务端没开 local_infile 时 LOAD DATA LOCAL 会在第一批才报错，提前检查给出提示
+async fn check_mysql_local_infile(conn: &mut MyConn) -> Result<()> {
+    let enabled: Option<i64> = conn.query_first("SELECT @@local_infile").await?;
+    if enabled != Some(1) {
+        bail!("服务端未开启 local_infile，--load-method load-data 需要先执行 SET GLOBAL local_infile = 1");
+    }
     Ok(())
 }
 
-async fn load_postgres(url: &str, cfg: &LoadConfig, remaining: u64, generator: &mut EventGenerator) -> Result<()> {
-    let (client, connection) = tokio_postgres::connect(url, tokio_postgres::NoTls).await?;
+/// 文件内容与 COPY text 格式相同：制表符分隔、反斜杠转义、\N 表示 NULL。
+/// uuid 主键以文本读入用户变量，再转成 BINARY(16)
+fn mysql_load_data_sql(columns: &[Column], pk: PkStrategy) -> String {
+    let uuid_id = pk == PkStrategy::Uuid;
+    let targets = columns
+        .iter()
+        .map(|&c| if c == Column::Id && uuid_id { "@id" } else { c.name() })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut sql = format!(
+        "LOAD DATA LOCAL INFILE 'events.tsv' INTO TABLE events \
+         FIELDS TERMINATED BY '\\t' ESCAPED BY '\\\\' LINES TERMINATED BY '\\n' ({})",
+        targets
+    );
+    if uuid_id && columns.contains(&Column::Id) {
+        sql.push_str(" SET id = UNHEX(REPLACE(@id, '-', ''))");
+    }
+    sql
+}
+
+async fn load_postgres(
+    db: &DbConfig,
+    cfg: &LoadConfig,
+    remaining: u64,
+    generator: &mut EventGenerator,
+    progress: Option<&Arc<Progress>>,
+) -> Result<()> {
+    let (client, connection) = connect::postgres(&db.url, &db.connect).await?;
     tokio::spawn(async move {
         if let Err(e) = connection.await {
             tracing::error!("Postgres 连接任务出错: {}", e);
@@ -140,129 +851,348 @@ async fn load_postgres(url: &str, cfg: &LoadConfig, remaining: u64, generator: &
     });
     configure_postgres_indexes(&client, cfg.indexes).await?;
 
-    // 使用 COPY 流式写入，避免超大批次 INSERT
-    let batch_cap = cfg.batch_size.min(1_000);
+    // 默认使用 COPY 流式写入；--load-method insert 改为逐批多行 INSERT，便于对比两者的写入速度。
+    // 只有一个连接，内存上限全部给它
+    // load-data 在装载前已经被拒绝
+    let method = match cfg.load_method {
+        Some(LoadMethod::Insert) => LoadMethod::Insert,
+        _ => LoadMethod::Copy,
+    };
+    let columns = cfg.insert_columns();
+    let mem_cap = match method {
+        LoadMethod::Copy => cfg.log_memory_cap(1),
+        _ => cfg.log_memory_cap(1).min(PG_MAX_PARAMS / columns.len()),
+    };
+    let mut tuner = match cfg.batch_size {
+        BatchSize::Auto => {
+            let cap = AUTO_BATCH_MAX.min(mem_cap);
+            tracing::info!("Postgres 批大小自适应开启，上限 {} 行/批", cap);
+            Some(BatchTuner::new(cap))
+        }
+        BatchSize::Fixed(_) => None,
+    };
     let start = Instant::now();
+    let bar = progress_bar::new(Some(remaining), "Postgres 装载", "行");
     let mut inserted: u64 = 0;
-    let sink = client
-        .copy_in("COPY public.events (user_id, created_at, amount, status, category, payload) FROM STDIN")
-        .await?;
-    pin_mut!(sink);
+    let mut sink = match method {
+        LoadMethod::Copy => {
+            let copy_sql = format!(
+                "COPY public.events ({}) FROM STDIN",
+                column_list(&columns)
+            );
+            Some(Box::pin(client.copy_in::<_, Bytes>(copy_sql.as_str()).await?))
+        }
+        _ => None,
+    };
 
-    while inserted < remaining {
+    while inserted < remaining && !shutdown::requested() {
         let remaining_rows = (remaining - inserted) as usize;
-        let this_batch = remaining_rows.min(batch_cap);
+        let this_batch =
+            remaining_rows.min(current_batch_size(cfg.batch_size, tuner.as_ref()).min(mem_cap));
+        let batch_start = Instant::now();
         let rows = generator.next_batch(this_batch);
-        let mut buf = String::new();
-        for row in rows {
-            buf.push_str(&format!(
-                "{}\t{}\t{:.2}\t{}\t{}\t{}\n",
-                row.user_id,
-                row.created_at.format("%Y-%m-%d %H:%M:%S"),
-                row.amount,
-                row.status,
-                row.category,
-                row.payload
-            ));
-        }
-        sink.as_mut().send(Bytes::from(buf)).await?;
+        match sink.as_mut() {
+            Some(sink) => {
+                let mut buf = String::new();
+                for row in &rows {
+                    push_copy_line(&mut buf, row, &columns);
+                }
+                sink.send(Bytes::from(buf)).await?;
+            }
+            None => {
+                insert_postgres_batch(&client, &rows, &columns, cfg.payload_type).await?;
+                // 每批 INSERT 单独提交，可以逐批记入检查点
+                if let Some(progress) = progress {
+                    progress.add(0, rows.len() as u64);
+                }
+            }
+        }
         inserted += this_batch as u64;
-        if inserted / 100_000 != (inserted - this_batch as u64) / 100_000 || inserted == remaining {
+        if let Some(tuner) = tuner.as_mut()
+            && tuner.observe(this_batch, batch_start.elapsed())
+        {
+            tracing::info!("Postgres 批大小已收敛为 {} 行", tuner.size());
+        }
+        if let Some(bar) = &bar {
+            bar.inc(this_batch as u64);
+        } else if inserted / 100_000 != (inserted - this_batch as u64) / 100_000 || inserted == remaining {
             let rps = inserted as f64 / start.elapsed().as_secs_f64().max(0.001);
             tracing::info!("Postgres 已插入 {} 行, {:.2} rows/s", inserted, rps);
         }
     }
-    sink.as_mut().send(Bytes::from_static(b"\\.\n")).await?;
-    sink.close().await?;
+    if let Some(tuner) = tuner.as_ref()
+        && !tuner.converged
+    {
+        tracing::info!("Postgres 装载结束时批大小未收敛，最终 {} 行", tuner.size());
+    }
+    if let Some(mut sink) = sink {
+        sink.send(Bytes::from_static(b"\\.\n")).await?;
+        sink.close().await?;
+        // COPY 到这里才整体提交
+        if let Some(progress) = progress {
+            progress.add(0, inserted);
+        }
+    }
 
     tracing::info!(
         "Postgres 装载完成，总行数 {}，耗时 {:.2}s",
         inserted,
         start.elapsed().as_secs_f64()
     );
-    client.execute("ANALYZE events", &[]).await?;
+    client.batch_execute("ANALYZE events").await?;
     Ok(())
 }
 
-fn build_mysql_insert(rows: &[EventRow]) -> (String, MyParams) {
-    let mut placeholders = Vec::with_capacity(rows.len());
-    let mut values: Vec<MyValue> = Vec::with_capacity(rows.len() * 6);
+fn current_batch_size(batch_size: BatchSize, tuner: Option<&BatchTuner>) -> usize {
+    match (batch_size, tuner) {
+        (_, Some(tuner)) => tuner.size(),
+        (BatchSize::Fixed(n), None) => n.min(FIXED_BATCH_CAP),
+        (BatchSize::Auto, None) => AUTO_BATCH_START,
+    }
+}
+
+/// auto 模式下单批行数上限：受 max_allowed_packet 与占位符数量双重约束
+async fn mysql_auto_batch_cap(conn: &mut MyConn, payload_size: usize, columns: usize) -> Result<usize> {
+    let packet: Option<u64> = conn.query_first("SELECT @@max_allowed_packet").await?;
+    Ok(mysql_batch_cap(packet.unwrap_or(4 * 1024 * 1024) as usize, payload_size, columns))
+}
+
+fn mysql_batch_cap(packet: usize, payload_size: usize, columns: usize) -> usize {
+    // 只用一半的包大小，给协议头和估算误差留余量
+    let by_packet = (packet / 2) / (payload_size + ROW_OVERHEAD_BYTES);
+    by_packet.clamp(1, (MYSQL_MAX_PARAMS / columns).min(AUTO_BATCH_MAX))
+}
+
+fn column_list(columns: &[Column]) -> String {
+    columns
+        .iter()
+        .map(|c| c.name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// COPY text 格式的一行，字段顺序与 columns 一致
+fn push_copy_line(buf: &mut String, row: &EventRow, columns: &[Column]) {
+    use std::fmt::Write;
+    for (i, col) in columns.iter().enumerate() {
+        if i > 0 {
+            buf.push('\t');
+        }
+        let _ = match col {
+            Column::Id => match row.id {
+                Some(RowKey::Int(id)) => write!(buf, "{}", id),
+                Some(RowKey::Uuid(id)) => write!(buf, "{}", id),
+                None => write!(buf, "\\N"),
+            },
+            Column::UserId => write!(buf, "{}", row.user_id),
+            Column::CreatedAt => write!(buf, "{}", row.created_at.format("%Y-%m-%d %H:%M:%S")),
+            Column::Amount => match row.amount {
+                Some(amount) => write!(buf, "{:.2}", amount),
+                None => write!(buf, "\\N"),
+            },
+            Column::Status => write!(buf, "{}", row.status),
+            Column::Category => match row.category {
+                Some(category) => write!(buf, "{}", category),
+                None => write!(buf, "\\N"),
+            },
+            Column::Payload => {
+                push_copy_text(buf, &row.payload);
+                Ok(())
+            }
+        };
+    }
+    buf.push('\n');
+}
+
+/// COPY text 格式需要转义反斜杠与分隔符；语料中的文本可能包含它们
+fn push_copy_text(buf: &mut String, text: &str) {
+    for ch in text.chars() {
+        match ch {
+            '\\' => buf.push_str("\\\\"),
+            '\t' => buf.push_str("\\t"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            _ => buf.push(ch),
+        }
+    }
+}
+
+fn mysql_column_value(row: &EventRow, col: Column) -> MyValue {
+    match col {
+        Column::Id => match row.id {
+            Some(RowKey::Int(id)) => MyValue::Int(id),
+            Some(RowKey::Uuid(id)) => MyValue::Bytes(id.as_bytes().to_vec()),
+            None => MyValue::NULL,
+        },
+        Column::UserId => MyValue::Int(row.user_id),
+        Column::CreatedAt => mysql_datetime(&row.created_at),
+        Column::Amount => row.amount.map_or(MyValue::NULL, mysql_amount),
+        Column::Status => MyValue::Int(row.status as i64),
+        Column::Category => row.category.map_or(MyValue::NULL, |c| MyValue::Int(c as i64)),
+        Column::Payload => MyValue::Bytes(row.payload.clone().into_bytes()),
+    }
+}
 
+/// 多行 INSERT 一次往返。用 query_typed 显式给出参数类型，不依赖服务端预处理语句，
+/// 事务级连接池下同样可用
+async fn insert_postgres_batch(
+    client: &PgClient,
+    rows: &[EventRow],
+    columns: &[Column],
+    payload_type: PayloadType,
+) -> Result<()> {
+    let mut tuples = Vec::with_capacity(rows.len());
+    let mut params: Vec<(&(dyn ToSql + Sync), PgType)> = Vec::with_capacity(rows.len() * columns.len());
     for row in rows {
-        placeholders.push("(?, ?, ?, ?, ?, ?)".to_string());
-        values.push(MyValue::Int(row.user_id));
+        let mut placeholders = Vec::with_capacity(columns.len());
+        for &col in columns {
+            let param: (&(dyn ToSql + Sync), PgType) = match col {
+                Column::Id => match &row.id {
+                    Some(RowKey::Int(id)) => (id, PgType::INT8),
+                    Some(RowKey::Uuid(id)) => (id, PgType::UUID),
+                    None => unreachable!("自增主键不写入 id 列"),
+                },
+                Column::UserId => (&row.user_id, PgType::INT8),
+                Column::CreatedAt => (&row.created_at, PgType::TIMESTAMP),
+                Column::Amount => (&row.amount, PgType::FLOAT8),
+                Column::Status => (&row.status, PgType::INT2),
+                Column::Category => (&row.category, PgType::INT4),
+                Column::Payload => (&row.payload, PgType::TEXT),
+            };
+            params.push(param);
+            // payload 按 TEXT 发送，JSONB 列需要显式转换
+            match (col, payload_type) {
+                (Column::Payload, PayloadType::Json) => placeholders.push(format!("${}::jsonb", params.len())),
+                _ => placeholders.push(format!("${}", params.len())),
+            }
+        }
+        tuples.push(format!("({})", placeholders.join(", ")));
+    }
+    let sql = format!(
+        "INSERT INTO public.events ({}) VALUES {}",
+        column_list(columns),
+        tuples.join(",")
+    );
+    client.query_typed(&sql, &params).await?;
+    Ok(())
+}
 
-        values.push(MyValue::Date(
-            row.created_at.year() as u16,
-            row.created_at.month() as u8,
-            row.created_at.day() as u8,
-            row.created_at.hour() as u8,
-            row.created_at.minute() as u8,
-            row.created_at.second() as u8,
-            row.created_at.timestamp_subsec_micros(),
-        ));
+fn build_mysql_insert(rows: &[EventRow], columns: &[Column]) -> (String, MyParams) {
+    let placeholder = format!("({})", vec!["?"; columns.len()].join(", "));
+    let mut placeholders = Vec::with_capacity(rows.len());
+    let mut values: Vec<MyValue> = Vec::with_capacity(rows.len() * columns.len());
 
-        values.push(MyValue::Bytes(format!("{:.2}", row.amount).into_bytes()));
-        values.push(MyValue::Int(row.status as i64));
-        values.push(MyValue::Int(row.category as i64));
-        values.push(MyValue::Bytes(row.payload.clone().into_bytes()));
+    for row in rows {
+        placeholders.push(placeholder.clone());
+        values.extend(columns.iter().map(|&c| mysql_column_value(row, c)));
     }
 
     let sql = format!(
-        "INSERT INTO events (user_id, created_at, amount, status, category, payload) VALUES {}",
+        "INSERT INTO events ({}) VALUES {}",
+        column_list(columns),
         placeholders.join(",")
     );
     (sql, MyParams::Positional(values))
 }
 
+/// 按顺序把 `?` 占位符替换为字面量。仅用于本工具自己拼出的 SQL（字面量里不会出现 `?`）
+pub fn inline_mysql_params(sql: &str, values: &[MyValue]) -> String {
+    let mut out = String::with_capacity(sql.len() + values.len() * 16);
+    let mut values = values.iter();
+    for ch in sql.chars() {
+        if ch == '?'
+            && let Some(v) = values.next()
+        {
+            out.push_str(&v.as_sql(false));
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+/// amount 以数值发送，不依赖字符串到 DECIMAL 的隐式转换。先舍入到两位小数，
+/// 规则里给出的更多位小数写入 DECIMAL(10,2) 时才不会产生截断警告
+pub fn mysql_amount(amount: f64) -> MyValue {
+    MyValue::Double((amount * 100.0).round() / 100.0)
+}
+
+pub fn mysql_datetime(dt: &NaiveDateTime) -> MyValue {
+    MyValue::Date(
+        dt.year() as u16,
+        dt.month() as u8,
+        dt.day() as u8,
+        dt.hour() as u8,
+        dt.minute() as u8,
+        dt.second() as u8,
+        dt.and_utc().timestamp_subsec_micros(),
+    )
+}
+
 async fn configure_mysql_indexes(conn: &mut MyConn, mode: IndexMode) -> Result<()> {
     match mode {
         IndexMode::On => {
-            ensure_mysql_index(
-                conn,
-                "idx_user_created",
-                "ALTER TABLE events ADD INDEX idx_user_created (user_id, created_at)",
-            )
-            .await?;
-            ensure_mysql_index(
-                conn,
-                "idx_status",
-                "ALTER TABLE events ADD INDEX idx_status (status)",
-            )
-            .await?;
-            ensure_mysql_index(
-                conn,
-                "idx_created_at",
-                "ALTER TABLE events ADD INDEX idx_created_at (created_at)",
-            )
-            .await?;
+            for index in SecondaryIndex::ALL {
+                let sql = format!(
+                    "ALTER TABLE events ADD INDEX {} ({})",
+                    index.name(),
+                    index.columns()
+                );
+                ensure_mysql_index(conn, index.name(), &sql).await?;
+            }
             tracing::info!("MySQL 索引已开启");
         }
         IndexMode::Off => {
-            drop_mysql_index(conn, "idx_user_created").await?;
-            drop_mysql_index(conn, "idx_status").await?;
-            drop_mysql_index(conn, "idx_created_at").await?;
+            for index in SecondaryIndex::ALL {
+                drop_mysql_index(conn, index.name()).await?;
+            }
             tracing::info!("MySQL 索引已关闭（仅保留主键）");
         }
     }
     Ok(())
 }
 
+// ER_DUP_KEYNAME：索引名已存在
+const MYSQL_DUP_KEYNAME: u16 = 1061;
+// ER_CANT_DROP_FIELD_OR_KEY：要删除的索引不存在
+const MYSQL_CANT_DROP_KEY: u16 = 1091;
+
+/// 先查后建；多个进程同时装载时可能都通过了存在性检查，
+/// 落败的一方收到 1061 时视为成功，效果等同 Postgres 的 IF NOT EXISTS
 async fn ensure_mysql_index(conn: &mut MyConn, name: &str, create_sql: &str) -> Result<()> {
     if mysql_index_exists(conn, name).await? {
         return Ok(());
     }
-    conn.exec_drop(create_sql, ()).await?;
-    Ok(())
+    match conn.exec_drop(create_sql, ()).await {
+        Err(e) if is_duplicate_key_name(&e) => {
+            tracing::info!("索引 {} 已被其他进程创建", name);
+            Ok(())
+        }
+        other => Ok(other?),
+    }
 }
 
-async fn drop_mysql_index(conn: &mut MyConn, name: &str) -> Result<()> {
+/// 与 ensure_mysql_index 对称：并发删除时 1091 视为成功
+pub async fn drop_mysql_index(conn: &mut MyConn, name: &str) -> Result<()> {
     if !mysql_index_exists(conn, name).await? {
         return Ok(());
     }
     let sql = format!("DROP INDEX {} ON events", name);
-    conn.exec_drop(sql, ()).await?;
-    Ok(())
+    match conn.exec_drop(sql, ()).await {
+        Err(e) if is_missing_key(&e) => {
+            tracing::info!("索引 {} 已被其他进程删除", name);
+            Ok(())
+        }
+        other => Ok(other?),
+    }
+}
+
+fn is_duplicate_key_name(e: &mysql_async::Error) -> bool {
+    matches!(e, mysql_async::Error::Server(e) if e.code == MYSQL_DUP_KEYNAME)
+}
+
+fn is_missing_key(e: &mysql_async::Error) -> bool {
+    matches!(e, mysql_async::Error::Server(e) if e.code == MYSQL_CANT_DROP_KEY)
 }
 
 async fn mysql_index_exists(conn: &mut MyConn, name: &str) -> Result<bool> {
@@ -278,53 +1208,69 @@ async fn mysql_index_exists(conn: &mut MyConn, name: &str) -> Result<bool> {
 async fn configure_postgres_indexes(client: &PgClient, mode: IndexMode) -> Result<()> {
     match mode {
         IndexMode::On => {
-            client
-                .execute(
-                    "CREATE INDEX IF NOT EXISTS idx_user_created ON public.events (user_id, created_at)",
-                    &[],
-                )
-                .await?;
-            client
-                .execute(
-                    "CREATE INDEX IF NOT EXISTS idx_status ON public.events (status)",
-                    &[],
-                )
-                .await?;
-            client
-                .execute(
-                    "CREATE INDEX IF NOT EXISTS idx_created_at ON public.events (created_at)",
-                    &[],
-                )
-                .await?;
+            for index in SecondaryIndex::ALL {
+                let sql = format!(
+                    "CREATE INDEX IF NOT EXISTS {} ON public.events ({})",
+                    index.name(),
+                    index.columns()
+                );
+                client.batch_execute(&sql).await?;
+            }
             tracing::info!("Postgres 索引已开启");
         }
         IndexMode::Off => {
-            client
-                .execute("DROP INDEX IF EXISTS idx_user_created", &[])
-                .await?;
-            client.execute("DROP INDEX IF EXISTS idx_status", &[]).await?;
-            client
-                .execute("DROP INDEX IF EXISTS idx_created_at", &[])
-                .await?;
+            for index in SecondaryIndex::ALL {
+                client
+                    .batch_execute(&format!("DROP INDEX IF EXISTS {}", index.name()))
+                    .await?;
+            }
             tracing::info!("Postgres 索引已关闭（仅保留主键）");
         }
     }
     Ok(())
 }
 
+/// 基准要求的 id 列：pk_hit 等场景在 1..=max(id) 中取值，写场景与 replica_lag 依赖数据库生成的 id
+const SERIAL_ID_REQUIRED: &str = "基准要求 events.id 为自增的整数主键，--pk uuid / random-int 装载的表不能用于 bench 与 compare";
+
+/// 读取 max(id) 之前先确认 id 是自增主键
 pub async fn fetch_mysql_max_id(pool: &MyPool) -> Result<u64> {
     let mut conn = pool.get_conn().await?;
-    let max_id: Option<u64> = conn
-        .exec_first("SELECT MAX(id) FROM events", ())
+    let id: Option<(String, String)> = conn
+        .query_first(
+            "SELECT data_type, extra FROM information_schema.columns \
+             WHERE table_schema = DATABASE() AND table_name = 'events' AND column_name = 'id'",
+        )
         .await?;
+    let (data_type, extra) = id.context("events 表没有 id 列")?;
+    if !extra.to_ascii_lowercase().contains("auto_increment") {
+        bail!("{}（当前 id 为 {}，不是 AUTO_INCREMENT）", SERIAL_ID_REQUIRED, data_type);
+    }
+    let max_id: Option<u64> = conn.query_first("SELECT MAX(id) FROM events").await?;
     conn.disconnect().await?;
     Ok(max_id.unwrap_or(0))
 }
 
 pub async fn fetch_postgres_max_id(client: &PgClient) -> Result<u64> {
+    let id = client
+        .query_typed(
+            "SELECT data_type::text, (column_default LIKE 'nextval(%' OR is_identity = 'YES') \
+             FROM information_schema.columns WHERE table_schema = 'public' AND table_name = 'events' AND column_name = 'id'",
+            &[],
+        )
+        .await?
+        .into_iter()
+        .next()
+        .context("events 表没有 id 列")?;
+    let (data_type, serial): (String, Option<bool>) = (id.get(0), id.get(1));
+    if data_type != "bigint" || serial != Some(true) {
+        bail!("{}（当前 id 为 {}{}）", SERIAL_ID_REQUIRED, data_type, if serial == Some(true) { "" } else { "，没有序列默认值" });
+    }
     let row = client
-        .query_opt("SELECT MAX(id) FROM events", &[])
-        .await?;
+        .query_typed("SELECT MAX(id) FROM events", &[])
+        .await?
+        .into_iter()
+        .next();
     if let Some(row) = row {
         let value: Option<i64> = row.get(0);
         Ok(value.unwrap_or(0).max(0) as u64)
@@ -333,27 +1279,133 @@ pub async fn fetch_postgres_max_id(client: &PgClient) -> Result<u64> {
     }
 }
 
-pub async fn count_mysql_rows(url: &str) -> Result<u64> {
-    let pool = MyPool::new(mysql_async::Opts::from_url(url)?);
+pub async fn fetch_mysql_max_user_id(pool: &MyPool) -> Result<u64> {
     let mut conn = pool.get_conn().await?;
-    let count: Option<u64> = conn.exec_first("SELECT COUNT(*) FROM events", ()).await?;
+    let max_user: Option<u64> = conn.query_first("SELECT MAX(user_id) FROM events").await?;
+    conn.disconnect().await?;
+    Ok(max_user.unwrap_or(0))
+}
+
+pub async fn fetch_postgres_max_user_id(client: &PgClient) -> Result<u64> {
+    let row = client
+        .query_typed("SELECT MAX(user_id) FROM events", &[])
+        .await?
+        .into_iter()
+        .next();
+    let value: Option<i64> = row.and_then(|r| r.get(0));
+    Ok(value.unwrap_or(0).max(0) as u64)
+}
+
+pub async fn count_mysql_rows(db: &DbConfig) -> Result<u64> {
+    let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+    let mut conn = pool.get_conn().await?;
+    let count: Option<u64> = conn.query_first("SELECT COUNT(*) FROM events").await?;
     conn.disconnect().await?;
     Ok(count.unwrap_or(0))
 }
 
-pub async fn count_postgres_rows(url: &str) -> Result<u64> {
-    let (client, connection) = tokio_postgres::connect(url, tokio_postgres::NoTls).await?;
+/// 把 events 的脏页刷到磁盘并关闭表；需要 RELOAD 权限
+async fn flush_mysql(db: &DbConfig) -> Result<()> {
+    let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+    let mut conn = pool.get_conn().await?;
+    conn.query_drop("FLUSH TABLES events")
+        .await
+        .context("FLUSH TABLES events 失败（需要 RELOAD 权限）")?;
+    conn.disconnect().await?;
+    Ok(())
+}
+
+/// 强制做一次检查点；需要超级用户或 pg_checkpoint 角色
+async fn flush_postgres(db: &DbConfig) -> Result<()> {
+    let (client, connection) = connect::postgres(&db.url, &db.connect).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("Postgres 连接任务出错: {}", e);
+        }
+    });
+    client
+        .batch_execute("CHECKPOINT")
+        .await
+        .context("CHECKPOINT 失败（需要超级用户或 pg_checkpoint 角色）")?;
+    Ok(())
+}
+
+pub async fn count_postgres_rows(db: &DbConfig) -> Result<u64> {
+    let (client, connection) = connect::postgres(&db.url, &db.connect).await?;
     tokio::spawn(async move {
         if let Err(e) = connection.await {
             tracing::error!("Postgres 连接任务出错: {}", e);
         }
     });
     let row = client
-        .query_opt("SELECT COUNT(*) FROM events", &[])
-        .await?;
+        .query_typed("SELECT COUNT(*) FROM events", &[])
+        .await?
+        .into_iter()
+        .next();
     let count = row
         .and_then(|r| r.get::<usize, Option<i64>>(0))
         .unwrap_or(0)
         .max(0) as u64;
     Ok(count)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_error(code: u16) -> mysql_async::Error {
+        mysql_async::Error::Server(mysql_async::ServerError {
+            code,
+            message: String::new(),
+            state: "42000".to_string(),
+        })
+    }
+
+    #[test]
+    fn index_races_match_only_their_error_code() {
+        assert!(is_duplicate_key_name(&server_error(MYSQL_DUP_KEYNAME)));
+        assert!(!is_duplicate_key_name(&server_error(MYSQL_CANT_DROP_KEY)));
+        assert!(is_missing_key(&server_error(MYSQL_CANT_DROP_KEY)));
+        assert!(!is_missing_key(&server_error(MYSQL_DUP_KEYNAME)));
+        let other = mysql_async::Error::Driver(mysql_async::DriverError::PoolDisconnected);
+        assert!(!is_duplicate_key_name(&other) && !is_missing_key(&other));
+    }
+
+    #[test]
+    fn mysql_batch_cap_counts_every_placeholder() {
+        let packet = 1 << 30;
+        assert_eq!(mysql_batch_cap(packet, 8, 6), 65_535 / 6);
+        // 客户端生成主键时每行 7 个占位符
+        assert_eq!(mysql_batch_cap(packet, 8, 7), 65_535 / 7);
+        assert!(mysql_batch_cap(packet, 8, 7) * 7 <= 65_535);
+        assert_eq!(mysql_batch_cap(4 * 1024 * 1024, 200, 6), 2 * 1024 * 1024 / 264);
+    }
+
+    #[test]
+    fn mysql_amount_rounds_to_cents() {
+        assert_eq!(mysql_amount(12.345), MyValue::Double(12.35));
+        assert_eq!(mysql_amount(0.004), MyValue::Double(0.0));
+        assert_eq!(mysql_amount(999.999), MyValue::Double(1000.0));
+    }
+
+    /// 需要一个 MySQL：MYSQL_URL=mysql://... cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn mysql_amount_round_trips_through_decimal() {
+        let url = std::env::var("MYSQL_URL").expect("MYSQL_URL 未设置");
+        let pool = connect::mysql_pool(&url, &Default::default()).await.unwrap();
+        let mut conn = pool.get_conn().await.unwrap();
+        conn.query_drop("CREATE TEMPORARY TABLE amount_check (id INT PRIMARY KEY, amount DECIMAL(10,2) NOT NULL)")
+            .await
+            .unwrap();
+        for (id, amount) in [(1, 12.345), (2, 0.1), (3, 999.999)] {
+            conn.exec_drop("INSERT INTO amount_check VALUES (?, ?)", (id, mysql_amount(amount))).await.unwrap();
+            // 舍入后再发送，DECIMAL(10,2) 不会产生截断警告
+            assert_eq!(conn.get_warnings(), 0, "amount {}", amount);
+        }
+        let stored: Vec<String> = conn.query("SELECT CAST(amount AS CHAR) FROM amount_check ORDER BY id").await.unwrap();
+        assert_eq!(stored, vec!["12.35", "0.10", "1000.00"]);
+        conn.disconnect().await.unwrap();
+        pool.disconnect().await.unwrap();
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use tracing::{Level, Subscriber};
+use tracing_appender::non_blocking::{NonBlockingBuilder, WorkerGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::{DefaultFields, Writer};
+use tracing_subscriber::fmt::{FormatFields, MakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::LogFormat;
+use crate::progress_bar;
+
+/// 控制台按 -v / RUST_LOG 过滤；指定 --log-file 时另写一份不受其影响的 TRACE 级日志
+pub fn init(verbose: u8, format: LogFormat, log_file: Option<&Path>) -> Result<Option<WorkerGuard>> {
+    let level = match verbose {
+        0 => Level::INFO,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+
+    let filter = EnvFilter::builder()
+        .with_default_directive(level.into())
+        .from_env_lossy();
+
+    let console = console_layer(format).with_filter(filter);
+    let (file, guard) = match log_file {
+        Some(path) => {
+            // 写文件放到后台线程；缓冲满时等待而不丢行，宁可拖慢也要完整
+            let (writer, guard) = NonBlockingBuilder::default().lossy(false).finish(rolling_file(path)?);
+            (Some(file_layer(format, writer).with_filter(LevelFilter::TRACE)), Some(guard))
+        }
+        None => (None, None),
+    };
+    tracing_subscriber::registry().with(console).with(file).init();
+    Ok(guard)
+}
+
+fn console_layer<S>(format: LogFormat) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(progress_bar::LogWriter);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        // span_list 带上外层的 round（库、场景、并发数）与内层的 worker，便于按场景关联进度日志
+        LogFormat::Json => layer.json().with_current_span(false).with_span_list(true).boxed(),
+    }
+}
+
+/// 文件日志不带颜色。span 字段按格式化器的类型缓存在 span 上、由各层共享，
+/// 文本格式下换用 PlainFields，避免沿用控制台层带 ANSI 转义的结果
+fn file_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(false);
+    match format {
+        LogFormat::Text => layer.fmt_fields(PlainFields(DefaultFields::new())).boxed(),
+        LogFormat::Json => layer.json().with_current_span(false).with_span_list(true).boxed(),
+    }
+}
+
+struct PlainFields(DefaultFields);
+
+impl<'w> FormatFields<'w> for PlainFields {
+    fn format_fields<R: RecordFields>(&self, writer: Writer<'w>, fields: R) -> fmt::Result {
+        self.0.format_fields(writer, fields)
+    }
+}
+
+/// 按天滚动：日期插在文件名与扩展名之间
+fn rolling_file(path: &Path) -> Result<RollingFileAppender> {
+    if path.is_dir() || path.as_os_str().to_string_lossy().ends_with(std::path::MAIN_SEPARATOR) {
+        bail!("--log-file {:?} 是目录，应为日志文件路径", path);
+    }
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = path.file_name().with_context(|| format!("--log-file {:?} 不是文件路径", path))?;
+    let name = Path::new(name);
+    let mut builder = RollingFileAppender::builder().rotation(Rotation::DAILY);
+    if let Some(stem) = name.file_stem().and_then(|s| s.to_str()) {
+        builder = builder.filename_prefix(stem);
+    }
+    if let Some(ext) = name.extension().and_then(|s| s.to_str()) {
+        builder = builder.filename_suffix(ext);
+    }
+    builder
+        .build(dir)
+        .with_context(|| format!("创建日志文件 {:?} 失败", path))
+}
@@ -1,21 +1,51 @@
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use anyhow::Result;
-use clap::{value_parser, ArgAction, Args, Parser, Subcommand};
-use tracing::Level;
-use tracing_subscriber::EnvFilter;
+use anyhow::{bail, Context, Result};
+use clap::{value_parser, ArgAction, Args, CommandFactory, Parser, Subcommand};
 
 mod bench;
+mod checkpoint;
 mod config;
+mod config_file;
+mod connect;
+mod diff;
+mod generate;
 mod generator;
+mod indexbuild;
 mod load;
+mod logging;
+mod metrics;
+mod pause;
+mod plan;
+mod preflight;
+mod progress_bar;
+mod resources;
+mod retry;
+mod schema;
+mod server;
+mod shutdown;
+mod storage;
+mod timeseries;
+mod userdist;
+mod verify;
 
-use config::{default_url, DbConfig, DbKind, Distribution, IndexMode};
+use config::{
+    default_url, AmountRules, BatchSize, BuildMode, Column, Concurrency, ConcurrencyRamp, ConnectOptions, DbConfig, DbKind, Distribution, ExportFormat,
+    IndexMode, Isolation, LatencyUnit, LoadMethod, LogFormat, MixSpec, OutputFormat, PayloadModel, PayloadType, Percentiles, PkStrategy, Pooler, SecondaryIndex, TimeMode,
+    TlsConfig, DEFAULT_TIME_SPAN_DAYS,
+};
 use load::LoadConfig;
+use retry::RetryPolicy;
+use userdist::{UserDistSpec, UserHistogram, DEFAULT_USERS, DEFAULT_ZIPF_THETA};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "DB performance observer CLI")]
 struct Cli {
+    /// TOML file supplying defaults for any flag; DBPO_* environment variables and then the command line override it
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
     /// Database kind to target (mysql or postgres)
     #[arg(long, value_enum, default_value_t = DbKind::Mysql)]
     db: DbKind,
@@ -24,10 +54,50 @@ struct Cli {
     #[arg(long)]
     url: Option<String>,
 
+    /// Connect the default URL over a Unix socket: the socket file for MySQL, the socket directory for Postgres
+    #[arg(long, conflicts_with_all = ["url", "tls"])]
+    socket: Option<PathBuf>,
+
+    /// Connection pooler in front of the database; `transaction` avoids server-side prepared statements
+    #[arg(long, value_enum, default_value_t = Pooler::None)]
+    pooler: Pooler,
+
+    /// Connect over TLS, verifying the server certificate against the bundled public roots
+    #[arg(long)]
+    tls: bool,
+
+    /// Extra CA certificate (PEM) to trust for --tls, e.g. a managed database's CA bundle
+    #[arg(long, requires = "tls")]
+    ca_cert: Option<PathBuf>,
+
+    /// Skip server certificate and hostname verification (development only)
+    #[arg(long, requires = "tls")]
+    tls_insecure: bool,
+
+    /// Max connections in the MySQL pool (default: pool_max from the URL, else 100); Postgres has no pool
+    #[arg(long, value_parser = value_parser!(u64).range(1..))]
+    pool_size: Option<u64>,
+
+    /// Give up connecting after this long instead of waiting on an unreachable host, e.g. `5s`
+    #[arg(long)]
+    connect_timeout: Option<humantime::Duration>,
+
+    /// Check flags, connectivity and the events table schema, then exit without running
+    #[arg(long)]
+    validate_only: bool,
+
     /// Enable verbose logging
     #[arg(long, short = 'v', action = ArgAction::Count)]
     verbose: u8,
 
+    /// Log output format; `json` emits one object per line with the enclosing span fields (scenario, worker)
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Also write logs at TRACE level to this file, rotated daily (`bench.log` becomes `bench.2026-01-31.log`)
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
     /// Subcommand to execute
     #[command(subcommand)]
     command: Command,
@@ -39,6 +109,18 @@ enum Command {
     Load(LoadArgs),
     /// Run benchmark scenarios against the target database
     Bench(BenchArgs),
+    /// Run the same bench scenarios and seed against MySQL and Postgres, and report per-scenario ratios
+    Compare(CompareArgs),
+    /// Compare two bench `--format json` result files and fail if any scenario regressed beyond --threshold
+    Diff(DiffArgs),
+    /// Print a scenario's SQL with a sampled parameter filled in, without connecting
+    ShowSql(ShowSqlArgs),
+    /// Drop a secondary index if present, then time rebuilding it on the loaded table
+    BuildIndex(BuildIndexArgs),
+    /// Generate the dataset into a CSV/JSON Lines/Parquet file without connecting to a database
+    Generate(GenerateArgs),
+    /// Empty the events table and reset its auto-increment counter (TRUNCATE), keeping the schema and indexes
+    Reset(ResetArgs),
 }
 
 #[derive(Args, Debug)]
@@ -46,21 +128,195 @@ struct LoadArgs {
     /// Target row count (e.g. 1000000 for 1m)
     #[arg(long, value_parser = value_parser!(u64).range(1..))]
     scale: u64,
-    /// Concurrent workers generating/loading data
+    /// Concurrent workers generating/loading data, or `auto` to derive it from the server's free connections
+    #[arg(long, default_value_t = Concurrency::Fixed(4))]
+    concurrency: Concurrency,
+    /// Rows per batch insert/COPY, or `auto` to tune it adaptively per worker
+    #[arg(long, default_value_t = BatchSize::Fixed(10_000))]
+    batch_size: BatchSize,
+    #[command(flatten)]
+    generator: GeneratorArgs,
+    /// Memory budget in MB for in-flight batches across all workers; shrinks rows per batch to fit
+    #[arg(long, value_name = "MB")]
+    max_memory: Option<u64>,
+    /// After loading, force the data to disk (MySQL FLUSH TABLES, Postgres CHECKPOINT) and report how long it took
+    #[arg(long)]
+    flush: bool,
+    /// Whether secondary indexes should exist during load/bench
+    #[arg(long, value_enum, default_value_t = IndexMode::On)]
+    indexes: IndexMode,
+    /// Sample this process's CPU and RSS during the load and log peak/average
+    #[arg(long)]
+    resource_stats: bool,
+    /// Comma-separated columns to leave out of INSERT/COPY so the DB fills them (defaults/generated)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    skip_columns: Vec<Column>,
+    #[command(flatten)]
+    retry: RetryArgs,
+    /// How rows are written: `insert` (multi-row INSERT), `copy` (Postgres only) or `load-data`
+    /// (MySQL LOAD DATA LOCAL INFILE, needs local_infile=1 on the server); default: insert for MySQL, copy for Postgres
+    #[arg(long, value_enum)]
+    load_method: Option<LoadMethod>,
+    /// JSON progress log of committed rows, rewritten every few seconds; a later load still resumes from the table's row count
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Create the events table (matching --pk and --payload-size) if it does not exist
+    #[arg(long)]
+    create_table: bool,
+    /// Drop the events table before loading and recreate it, for clean reruns
+    #[arg(long)]
+    drop_table: bool,
+    /// Also write the post-load table and index sizes as JSON to this file
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// After loading, log the exact row count and a table checksum, and fail if the count is not the target
+    #[arg(long)]
+    verify: bool,
+}
+
+#[derive(Args, Debug)]
+struct GenerateArgs {
+    /// Number of rows to generate
+    #[arg(long, value_parser = value_parser!(u64).range(1..))]
+    scale: u64,
+    /// Concurrent generator workers
     #[arg(long, default_value_t = 4)]
     concurrency: usize,
-    /// Rows per batch insert/COPY
-    #[arg(long, default_value_t = 10_000)]
-    batch_size: usize,
+    /// Rows per generated batch (one Parquet row group per batch)
+    #[arg(long, default_value_t = 10_000, value_parser = value_parser!(u64).range(1..))]
+    batch_size: u64,
+    /// Output file
+    #[arg(long)]
+    out: PathBuf,
+    /// Output file format (default: from the --out extension, else csv)
+    #[arg(long, value_enum)]
+    format: Option<ExportFormat>,
+    #[command(flatten)]
+    generator: GeneratorArgs,
+}
+
+/// 决定生成数据内容的参数，load 与 generate 共用
+#[derive(Args, Debug)]
+struct GeneratorArgs {
     /// Distribution of user_id values
     #[arg(long, value_enum, default_value_t = Distribution::Uniform)]
     distribution: Distribution,
+    /// Weighted user_id mixture, e.g. `zipf:0.7,uniform:0.3` or `zipf(1.2):0.7,uniform:0.3`; overrides --distribution
+    #[arg(long)]
+    user_dist: Option<UserDistSpec>,
+    /// Zipf exponent for `--distribution zipf`; larger is more skewed, <= 1 is flatter
+    #[arg(long, default_value_t = DEFAULT_ZIPF_THETA, conflicts_with = "user_dist")]
+    zipf_theta: f64,
+    /// Mean user_id for `--distribution normal` (default: users / 2)
+    #[arg(long)]
+    dist_mean: Option<f64>,
+    /// Standard deviation for `--distribution normal` (default: users / 10)
+    #[arg(long)]
+    dist_stddev: Option<f64>,
+    /// Number of distinct users; generated user_id values fall in 1..=users
+    #[arg(long, default_value_t = DEFAULT_USERS, value_parser = value_parser!(u64).range(1..))]
+    users: u64,
     /// Payload length for the payload column
     #[arg(long, default_value_t = 200)]
     payload_size: usize,
-    /// Whether secondary indexes should exist during load/bench
-    #[arg(long, value_enum, default_value_t = IndexMode::On)]
-    indexes: IndexMode,
+    /// Payload content: random alphanumerics (incompressible), lorem-ipsum words, small JSON documents, or all zeros
+    #[arg(long, value_enum, default_value_t = PayloadModel::Random)]
+    payload_model: PayloadModel,
+    /// Type of the payload column: text (VARCHAR) or json (MySQL JSON / Postgres JSONB, payloads generated as JSON documents)
+    #[arg(long, value_enum, default_value_t = PayloadType::Text)]
+    payload_type: PayloadType,
+    /// Derive each payload from a hash of user_id, so a user always gets the same payload
+    #[arg(long)]
+    payload_seeded_by_user: bool,
+    /// Pre-build N random payload templates and have each row pick one (controls duplication)
+    #[arg(long)]
+    payload_templates: Option<usize>,
+    /// With --payload-templates, replace the last N chars of each payload with random ones
+    #[arg(long, default_value_t = 0)]
+    payload_template_suffix: usize,
+    /// Sample payloads from a corpus: lines of a file, or whole files of a directory
+    #[arg(long)]
+    payload_corpus: Option<PathBuf>,
+    /// How events.id is produced; must match the strategy the table was created with (scripts/init_db.sh)
+    #[arg(long, value_enum, default_value_t = PkStrategy::AutoIncrement)]
+    pk: PkStrategy,
+    /// Derive amount from status, e.g. `0:0,3:-500..0` (fixed value or [lo, hi) range); other statuses stay random
+    #[arg(long)]
+    amount_by_status: Option<AmountRules>,
+    /// Print a histogram of the generated user_ids next to the distribution's theoretical shape
+    #[arg(long)]
+    dist_report: bool,
+    /// How created_at is generated: random within the last --time-span-days, or monotonically increasing
+    #[arg(long, value_enum, default_value_t = TimeMode::Random)]
+    time_mode: TimeMode,
+    /// Number of days created_at spans, ending now
+    #[arg(long, default_value_t = DEFAULT_TIME_SPAN_DAYS)]
+    time_span_days: u64,
+    /// Skew random created_at toward now with this half-life in days: density halves every HALF_LIFE_DAYS back
+    #[arg(long, value_name = "HALF_LIFE_DAYS")]
+    recency_bias: Option<f64>,
+    /// Probability that amount and category are each independently NULL (0 = never)
+    #[arg(long, default_value_t = 0.0)]
+    null_rate: f64,
+    /// Gap between consecutive created_at values in monotonic mode (default: spread the new rows over --time-span-days)
+    #[arg(long)]
+    time_interval_ms: Option<u64>,
+    /// RNG seed for generated rows; worker i uses seed + i, so the same seed and --concurrency reproduce the same data
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+impl GeneratorArgs {
+    /// 只填生成器相关的字段，写库相关的字段取默认值，由 load 按自己的参数覆盖
+    fn data_config(self, scale: u64, concurrency: usize, batch_size: BatchSize) -> Result<LoadConfig> {
+        Ok(LoadConfig {
+            scale,
+            concurrency,
+            batch_size,
+            distribution: self.distribution,
+            user_dist: self.user_dist,
+            zipf_theta: self.zipf_theta,
+            dist_mean: self.dist_mean,
+            dist_stddev: self.dist_stddev,
+            users: self.users,
+            payload_size: self.payload_size,
+            payload_seeded_by_user: self.payload_seeded_by_user,
+            payload_model: self.payload_model,
+            payload_type: self.payload_type,
+            payload_templates: self.payload_templates,
+            payload_template_suffix: self.payload_template_suffix,
+            payload_corpus: match &self.payload_corpus {
+                Some(path) => Some(Arc::new(generator::load_corpus(path)?)),
+                None => None,
+            },
+            pk: self.pk,
+            amount_by_status: self.amount_by_status,
+            user_histogram: self
+                .dist_report
+                .then(|| Arc::new(UserHistogram::new(self.users))),
+            max_memory_mb: None,
+            flush: false,
+            indexes: IndexMode::On,
+            resource_stats: false,
+            skip_columns: Vec::new(),
+            time_mode: self.time_mode,
+            time_span_days: self.time_span_days,
+            recency_half_life_days: self.recency_bias,
+            null_rate: self.null_rate,
+            time_interval_ms: self.time_interval_ms,
+            retry: RetryPolicy {
+                max_retries: 0,
+                backoff: std::time::Duration::ZERO,
+            },
+            load_method: None,
+            checkpoint: None,
+            create_table: false,
+            drop_table: false,
+            output: None,
+            verify: false,
+            seed: self.seed,
+        })
+    }
 }
 
 #[derive(Args, Debug)]
@@ -68,68 +324,491 @@ struct BenchArgs {
     /// Number of warmup operations per scenario
     #[arg(long, default_value_t = 1000)]
     warmup_ops: u64,
-    /// Number of measured operations per scenario
-    #[arg(long, default_value_t = 10_000)]
-    sample_ops: u64,
-    /// Maximum concurrent benchmark tasks
-    #[arg(long, default_value_t = 16)]
-    concurrency: usize,
+    /// Number of measured operations per scenario [default: 10000]
+    #[arg(long)]
+    sample_ops: Option<u64>,
+    /// Sample each scenario for this long instead of a fixed op count, e.g. `60s` or `5m`
+    #[arg(long)]
+    duration: Option<humantime::Duration>,
+    /// Maximum concurrent benchmark tasks, or `auto` to derive it from the server's free connections
+    #[arg(long, default_value_t = Concurrency::Fixed(16))]
+    concurrency: Concurrency,
+    /// Run each scenario once per concurrency level, e.g. `1,2,4,8,16,32`, to find the latency knee
+    #[arg(long, conflicts_with = "concurrency")]
+    ramp: Option<ConcurrencyRamp>,
+    /// Run each scenario N times (repeat i uses --seed + i) and report mean and stddev across repeats
+    #[arg(long, default_value_t = 1, value_parser = value_parser!(u64).range(1..))]
+    repeat: u64,
+    /// Cap each scenario's total throughput at this many ops/s, shared by all workers (default: unlimited)
+    #[arg(long)]
+    target_qps: Option<f64>,
     /// Output JSON file to write benchmark summary
     #[arg(long)]
     output: Option<PathBuf>,
     /// RNG seed to make benchmark parameters可复现
     #[arg(long, default_value_t = 42)]
     seed: u64,
+    /// Run each scenario twice: re-parsed every op vs prepared once and reused
+    #[arg(long)]
+    compare_prepared: bool,
+    /// Send the SQL text on every op instead of preparing once per connection, to include parse/plan cost
+    #[arg(long, conflicts_with = "compare_prepared")]
+    no_prepare: bool,
+    /// Sample this process's CPU and RSS during the run and add peak/average to the report
+    #[arg(long)]
+    resource_stats: bool,
+    /// Snapshot server counters (rows read, buffer hits, disk reads, temp files) around each scenario and report the deltas
+    #[arg(long)]
+    server_metrics: bool,
+    /// Log and skip errors during warmup instead of failing the scenario
+    #[arg(long)]
+    ignore_warmup_errors: bool,
+    /// Also report warmup latency percentiles per scenario, e.g. to see cold-cache behavior
+    #[arg(long)]
+    report_warmup: bool,
+    /// Query plan fingerprint baseline: written if missing, compared against otherwise
+    #[arg(long)]
+    plan_baseline: Option<PathBuf>,
+    /// Fail before benchmarking if any scenario's plan differs from --plan-baseline
+    #[arg(long)]
+    abort_on_plan_regression: bool,
+    /// EXPLAIN each scenario once with a representative parameter and include the plan in the results
+    #[arg(long)]
+    explain: bool,
+    /// Weighted user_id mixture for user_id lookups (same syntax as load's --user-dist);
+    /// defaults to the distribution the last load recorded in the table comment, else uniform
+    #[arg(long, visible_alias = "user-dist")]
+    lookup_dist: Option<UserDistSpec>,
+    /// user_id range sampled by lookups, 1..=users; should match load's --users
+    /// (default: the users recorded by the last load, else the table's max(user_id), capped at 1000000)
+    #[arg(long, value_parser = value_parser!(u64).range(1..))]
+    users: Option<u64>,
+    /// Unit of all reported latencies; timings are captured in nanoseconds either way
+    #[arg(long, value_enum, default_value_t = LatencyUnit::Ms)]
+    unit: LatencyUnit,
+    /// Comma-separated percentiles to report, e.g. `50,90,99,99.9`
+    #[arg(long, default_value_t = Percentiles::default())]
+    percentiles: Percentiles,
+    /// Also run scenarios that modify data (hot_update, update_amount, insert)
+    #[arg(long)]
+    allow_writes: bool,
+    /// Also run delete_pk, which permanently deletes random rows (requires --allow-writes)
+    #[arg(long)]
+    allow_deletes: bool,
+    /// Number of hot rows (lowest ids) that hot_update contends on
+    #[arg(long, default_value_t = 100)]
+    hot_rows: u64,
+    /// Days of created_at the data spans; range_small/range_large windows scale with it. Should match load's --time-span-days
+    #[arg(long, default_value_t = DEFAULT_TIME_SPAN_DAYS, value_parser = value_parser!(u64).range(1..))]
+    time_span_days: u64,
+    /// Type of the payload column as loaded; json adds the json_event scenario and writes JSON documents
+    #[arg(long, value_enum, default_value_t = PayloadType::Text)]
+    payload_type: PayloadType,
+    /// Set sample ops to this fraction of the table (by max id) and have pk_hit probe distinct ids
+    #[arg(long, conflicts_with_all = ["sample_ops", "duration"])]
+    sample_fraction: Option<f64>,
+    /// Server-side statement timeout in milliseconds for worker connections (default: server setting)
+    #[arg(long, value_name = "MS")]
+    statement_timeout: Option<u64>,
+    /// Wrap each op of write scenarios in BEGIN/COMMIT at this isolation level (default: autocommit)
+    #[arg(long, value_enum)]
+    isolation: Option<Isolation>,
+    /// Output format for stdout and --output
+    #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+    format: OutputFormat,
+    /// Also write the results in Prometheus text exposition format to this file
+    #[arg(long)]
+    prometheus: Option<PathBuf>,
+    /// Append one JSONL line per second per scenario: {t, ops_completed, interval_ops_per_sec}
+    #[arg(long, value_name = "PATH")]
+    timeseries: Option<PathBuf>,
+    /// Also POST the Prometheus metrics to this Pushgateway (job `db_bench` unless the URL has /metrics/job/...)
+    #[arg(long)]
+    pushgateway: Option<String>,
+    /// Send N scenario queries per round trip (MySQL multi-statement, Postgres pipelining)
+    #[arg(long, default_value_t = 1)]
+    queries_per_roundtrip: usize,
+    /// Free-form label stored in the report metadata next to the generated run_id
+    #[arg(long)]
+    tag: Option<String>,
+    /// On a connection-level error while sampling, reconnect up to N times and keep going (0 = fail the run)
+    #[arg(long, default_value_t = 0)]
+    reconnect_attempts: u32,
+    /// Wait before the first reconnect attempt; doubles after each failed attempt (max 10s)
+    #[arg(long, default_value_t = 200)]
+    reconnect_backoff_ms: u64,
+    /// Read replica URL; with --allow-writes, adds a replica_lag scenario measuring read-after-write lag
+    #[arg(long)]
+    read_url: Option<String>,
+    /// How long replica_lag waits for a written row to appear on the replica before counting a timeout
+    #[arg(long, default_value_t = 5_000)]
+    replica_lag_timeout_ms: u64,
+    /// TOML file of custom `[[scenario]]` entries to run instead of the built-in scenarios
+    #[arg(long, value_name = "PATH")]
+    scenarios: Option<PathBuf>,
+    /// Only run the named scenario (repeatable); applies to built-in and --scenarios entries
+    #[arg(long = "scenario", value_name = "NAME", conflicts_with = "mix")]
+    include: Vec<String>,
+    /// Skip the named scenario (repeatable)
+    #[arg(long, value_name = "NAME", conflicts_with = "mix")]
+    exclude: Vec<String>,
+    /// Mixed workload: pick a scenario per op by weight, e.g. `pk_hit=70,insert=20,user_lookup=10`
+    #[arg(long)]
+    mix: Option<MixSpec>,
+    #[command(flatten)]
+    retry: RetryArgs,
+    /// Count failed ops (timeouts, dropped connections, query errors) and keep going instead of failing the run
+    #[arg(long)]
+    continue_on_error: bool,
+}
+
+#[derive(Args, Debug)]
+struct RetryArgs {
+    /// Retry transient errors (deadlocks, serialization failures; for load also dropped connections) up to N times
+    #[arg(long, default_value_t = 0)]
+    max_retries: u32,
+    /// Wait before the first retry; doubles after each retry of the same operation (max 10s)
+    #[arg(long, default_value = "100ms")]
+    retry_backoff: humantime::Duration,
+}
+
+impl RetryArgs {
+    fn policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            backoff: self.retry_backoff.into(),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct CompareArgs {
+    /// MySQL connection URL (default: the same default as `--db mysql`)
+    #[arg(long)]
+    mysql_url: Option<String>,
+    /// Postgres connection URL (default: the same default as `--db postgres`)
+    #[arg(long)]
+    postgres_url: Option<String>,
+    #[command(flatten)]
+    bench: BenchArgs,
+}
+
+#[derive(Args, Debug)]
+struct DiffArgs {
+    /// Earlier bench result file, e.g. from the main branch
+    baseline: PathBuf,
+    /// Result file to check against the baseline
+    current: PathBuf,
+    /// Percentage by which throughput may drop or p95/p99 may grow before counting as a regression
+    #[arg(long, default_value_t = 10.0)]
+    threshold: f64,
+}
+
+#[derive(Args, Debug)]
+struct ShowSqlArgs {
+    /// Scenario name, e.g. pk_hit
+    scenario: String,
+    /// Assumed max(id) of the events table, used in place of querying it
+    #[arg(long, default_value_t = 1_000_000)]
+    max_id: u64,
+    /// RNG seed for the sampled parameter (matches the first parameter of bench worker 0 with the same --seed)
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    /// Weighted user_id mixture, same syntax as bench
+    #[arg(long)]
+    user_dist: Option<UserDistSpec>,
+    /// Assumed number of distinct users, used in place of querying max(user_id)
+    #[arg(long, default_value_t = DEFAULT_USERS, value_parser = value_parser!(u64).range(1..))]
+    users: u64,
+    /// Number of hot rows for hot_update
+    #[arg(long, default_value_t = 100)]
+    hot_rows: u64,
+    /// Assumed days of created_at the data spans, as in bench
+    #[arg(long, default_value_t = DEFAULT_TIME_SPAN_DAYS, value_parser = value_parser!(u64).range(1..))]
+    time_span_days: u64,
+    /// Assumed type of the payload column, as in bench
+    #[arg(long, value_enum, default_value_t = PayloadType::Text)]
+    payload_type: PayloadType,
+}
+
+#[derive(Args, Debug)]
+struct BuildIndexArgs {
+    /// Secondary index to rebuild
+    #[arg(long, value_enum)]
+    index: SecondaryIndex,
+    /// Blocking build, online build (INPLACE / CONCURRENTLY), or both one after another
+    #[arg(long, value_enum, default_value_t = BuildMode::Offline)]
+    mode: BuildMode,
+}
+
+#[derive(Args, Debug)]
+struct ResetArgs {
+    /// Confirm that all rows in the events table should be deleted
+    #[arg(long)]
+    yes: bool,
+    /// Load checkpoint file to delete along with the rows, so a later `load --checkpoint` starts fresh
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
-    init_tracing(cli.verbose)?;
+    let cli = Cli::parse_from(config_file::resolve_args(&Cli::command(), std::env::args_os().collect())?);
+    // 守卫在 main 返回时把文件日志的缓冲写完
+    let _log_guard = logging::init(cli.verbose, cli.log_format, cli.log_file.as_deref())?;
 
     let db = DbConfig {
         kind: cli.db,
-        url: cli.url.unwrap_or_else(|| default_url(cli.db)),
+        url: cli.url.clone().unwrap_or_else(|| default_url(cli.db, cli.socket.as_deref())),
+        pooler: cli.pooler,
+        connect: ConnectOptions {
+            tls: TlsConfig {
+                enabled: cli.tls,
+                ca_cert: cli.ca_cert,
+                insecure: cli.tls_insecure,
+            },
+            pool_size: cli.pool_size.map(|n| n as usize),
+            connect_timeout: cli.connect_timeout.map(Into::into),
+        },
     };
+    if db.kind == DbKind::Postgres && db.connect.pool_size.is_some() {
+        tracing::warn!("Postgres 每个 worker 各自建立连接，没有连接池，--pool-size 不生效");
+    }
 
     match cli.command {
         Command::Load(args) => {
+            let concurrency = server::resolve_concurrency(&db, args.concurrency).await?;
             let cfg = LoadConfig {
-                scale: args.scale,
-                concurrency: args.concurrency,
-                batch_size: args.batch_size,
-                distribution: args.distribution,
-                payload_size: args.payload_size,
+                max_memory_mb: args.max_memory,
+                flush: args.flush,
                 indexes: args.indexes,
+                resource_stats: args.resource_stats,
+                skip_columns: args.skip_columns,
+                retry: args.retry.policy(),
+                load_method: args.load_method,
+                checkpoint: args.checkpoint,
+                create_table: args.create_table,
+                drop_table: args.drop_table,
+                output: args.output,
+                verify: args.verify,
+                ..args.generator.data_config(args.scale, concurrency, args.batch_size)?
             };
+            cfg.validate()?;
+            if cli.validate_only {
+                let expect = preflight::Expectations {
+                    payload_size: Some(cfg.payload_size),
+                    require_rows: false,
+                    skip_columns: cfg.skip_columns.clone(),
+                    pk: Some(cfg.pk),
+                    nullable_columns: cfg.nullable_columns(),
+                    payload_type: Some(cfg.payload_type),
+                };
+                return validate_only(&db, &expect).await;
+            }
             load::run_load(db, cfg).await?;
         }
         Command::Bench(args) => {
-            let cfg = bench::BenchConfig {
-                warmup_ops: args.warmup_ops,
-                sample_ops: args.sample_ops,
-                concurrency: args.concurrency,
-                output: args.output,
+            let concurrency = server::resolve_concurrency(&db, args.concurrency).await?;
+            let cfg = bench_config(args, concurrency)?;
+            cfg.validate()?;
+            if cli.validate_only {
+                let expect = preflight::Expectations {
+                    payload_size: None,
+                    require_rows: true,
+                    skip_columns: Vec::new(),
+                    pk: None,
+                    nullable_columns: Vec::new(),
+                    payload_type: None,
+                };
+                return validate_only(&db, &expect).await;
+            }
+            bench::run_bench(db, cfg).await?;
+        }
+        Command::Compare(args) => {
+            if cli.url.is_some() {
+                bail!("compare 用 --mysql-url 与 --postgres-url 指定两个库，不接受 --url");
+            }
+            if cli.socket.is_some() {
+                bail!("compare 的两个库各有自己的 socket，请在 --mysql-url 与 --postgres-url 中分别指定，不接受 --socket");
+            }
+            let mysql = DbConfig {
+                kind: DbKind::Mysql,
+                url: args.mysql_url.unwrap_or_else(|| default_url(DbKind::Mysql, None)),
+                ..db.clone()
+            };
+            let postgres = DbConfig {
+                kind: DbKind::Postgres,
+                url: args.postgres_url.unwrap_or_else(|| default_url(DbKind::Postgres, None)),
+                ..db
+            };
+            // 两边必须用同样的并发才有可比性，auto 时取两者中较小的
+            let concurrency = server::resolve_concurrency(&mysql, args.bench.concurrency)
+                .await?
+                .min(server::resolve_concurrency(&postgres, args.bench.concurrency).await?);
+            let cfg = bench_config(args.bench, concurrency)?;
+            cfg.validate()?;
+            cfg.validate_compare()?;
+            if cli.validate_only {
+                let expect = preflight::Expectations {
+                    payload_size: None,
+                    require_rows: true,
+                    skip_columns: Vec::new(),
+                    pk: None,
+                    nullable_columns: Vec::new(),
+                    payload_type: None,
+                };
+                validate_only(&mysql, &expect).await?;
+                return validate_only(&postgres, &expect).await;
+            }
+            bench::run_compare(mysql, postgres, cfg).await?;
+        }
+        Command::Diff(args) => {
+            let cfg = diff::DiffConfig {
+                baseline: args.baseline,
+                current: args.current,
+                threshold_pct: args.threshold,
+            };
+            cfg.validate()?;
+            // 只读两个文件，--validate-only 只检查参数
+            if cli.validate_only {
+                return Ok(());
+            }
+            diff::run_diff(cfg)?;
+        }
+        Command::ShowSql(args) => {
+            let cfg = bench::ShowSqlConfig {
+                scenario: args.scenario,
+                max_id: args.max_id,
                 seed: args.seed,
+                user_dist: args
+                    .user_dist
+                    .unwrap_or_else(|| UserDistSpec::single(Distribution::Uniform)),
+                users: args.users,
+                hot_rows: args.hot_rows,
+                time_span_days: args.time_span_days,
+                payload_type: args.payload_type,
             };
-            bench::run_bench(db, cfg).await?;
+            println!("{}", bench::show_sql(db.kind, &cfg)?);
+        }
+        Command::BuildIndex(args) => {
+            let cfg = indexbuild::IndexBuildConfig {
+                index: args.index,
+                mode: args.mode,
+            };
+            if cli.validate_only {
+                let expect = preflight::Expectations {
+                    payload_size: None,
+                    require_rows: true,
+                    skip_columns: Vec::new(),
+                    pk: None,
+                    nullable_columns: Vec::new(),
+                    payload_type: None,
+                };
+                return validate_only(&db, &expect).await;
+            }
+            indexbuild::run_build_index(db, cfg).await?;
+        }
+        Command::Generate(args) => {
+            let cfg = generate::GenerateConfig {
+                format: args.format.unwrap_or_else(|| ExportFormat::from_path(&args.out)),
+                out: args.out,
+                data: args
+                    .generator
+                    .data_config(args.scale, args.concurrency, BatchSize::Fixed(args.batch_size as usize))?,
+            };
+            cfg.data.validate()?;
+            // 不连接数据库，--validate-only 只检查参数
+            if cli.validate_only {
+                return Ok(());
+            }
+            generate::run_generate(cfg).await?;
+        }
+        Command::Reset(args) => {
+            if !args.yes {
+                bail!("reset 会删除 events 表中的全部数据，确认后加 --yes 重新运行");
+            }
+            if cli.validate_only {
+                let expect = preflight::Expectations {
+                    payload_size: None,
+                    require_rows: false,
+                    skip_columns: Vec::new(),
+                    pk: None,
+                    nullable_columns: Vec::new(),
+                    payload_type: None,
+                };
+                return validate_only(&db, &expect).await;
+            }
+            schema::truncate_table(&db).await?;
+            // 表已清空，检查点记录的进度不再成立
+            if let Some(path) = &args.checkpoint
+                && path.exists()
+            {
+                std::fs::remove_file(path).with_context(|| format!("删除检查点 {:?} 失败", path))?;
+                tracing::info!("已删除检查点 {:?}", path);
+            }
         }
     }
 
     Ok(())
 }
 
-fn init_tracing(verbose: u8) -> Result<()> {
-    let level = match verbose {
-        0 => Level::INFO,
-        1 => Level::DEBUG,
-        _ => Level::TRACE,
-    };
-
-    let filter = EnvFilter::builder()
-        .with_default_directive(level.into())
-        .from_env_lossy();
+/// bench 与 compare 共用：把命令行参数转换成基准配置，concurrency 为已解析的并发数
+fn bench_config(args: BenchArgs, concurrency: usize) -> Result<bench::BenchConfig> {
+    if args.duration.is_some() && args.sample_ops.is_some() {
+        tracing::warn!("同时指定了 --duration 与 --sample-ops，按 --duration 计时采样，忽略 --sample-ops");
+    }
+    Ok(bench::BenchConfig {
+        warmup_ops: args.warmup_ops,
+        sample_ops: args.sample_ops.unwrap_or(bench::DEFAULT_SAMPLE_OPS),
+        duration: args.duration.map(Into::into),
+        concurrency,
+        target_qps: args.target_qps,
+        output: args.output,
+        seed: args.seed,
+        compare_prepared: args.compare_prepared,
+        no_prepare: args.no_prepare,
+        resource_stats: args.resource_stats,
+        server_metrics: args.server_metrics,
+        ignore_warmup_errors: args.ignore_warmup_errors,
+        report_warmup: args.report_warmup,
+        plan_baseline: args.plan_baseline,
+        abort_on_plan_regression: args.abort_on_plan_regression,
+        explain: args.explain,
+        lookup_dist: args.lookup_dist,
+        users: args.users,
+        unit: args.unit,
+        percentiles: args.percentiles,
+        allow_writes: args.allow_writes,
+        allow_deletes: args.allow_deletes,
+        hot_rows: args.hot_rows,
+        time_span_days: args.time_span_days,
+        payload_type: args.payload_type,
+        sample_fraction: args.sample_fraction,
+        statement_timeout_ms: args.statement_timeout,
+        isolation: args.isolation,
+        format: args.format,
+        prometheus: args.prometheus,
+        timeseries: args.timeseries,
+        pushgateway: args.pushgateway,
+        queries_per_roundtrip: args.queries_per_roundtrip,
+        tag: args.tag,
+        reconnect_attempts: args.reconnect_attempts,
+        reconnect_backoff_ms: args.reconnect_backoff_ms,
+        retry: args.retry.policy(),
+        continue_on_error: args.continue_on_error,
+        read_url: args.read_url,
+        replica_lag_timeout_ms: args.replica_lag_timeout_ms,
+        scenarios: args.scenarios.as_deref().map(bench::load_scenarios).transpose()?,
+        include: args.include,
+        exclude: args.exclude,
+        mix: args.mix,
+        ramp: args.ramp,
+        repeat: args.repeat as usize,
+    })
+}
 
-    tracing_subscriber::fmt().with_env_filter(filter).init();
+async fn validate_only(db: &DbConfig, expect: &preflight::Expectations) -> Result<()> {
+    preflight::check(db, expect).await?;
+    tracing::info!("校验通过");
     Ok(())
 }
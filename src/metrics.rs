@@ -0,0 +1,245 @@
+use hdrhistogram::Histogram;
+
+// 经验规则：至少要有这么多样本落在 p 分位之后，数值才不至于由一两个离群点决定
+const TAIL_SAMPLES: f64 = 10.0;
+
+// 直方图的有效数字位数：分位数的相对误差不超过 0.1%
+const SIGNIFICANT_DIGITS: u8 = 3;
+
+/// 一组延迟样本的统计摘要，单位与输入一致（bench 中为纳秒）
+#[derive(Debug, Clone, Default)]
+pub struct LatencySummary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    /// 总体标准差
+    pub stddev: f64,
+    /// (百分数, 分位值)，顺序与 --percentiles 一致
+    pub percentiles: Vec<(f64, f64)>,
+}
+
+/// 延迟样本的累加器：分位数取自 HdrHistogram，内存只与取值范围有关、与样本数无关；
+/// 次数、总和、平方和与极值另行精确累计，avg/stddev/min/max 不受直方图分桶误差影响
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    hist: Histogram<u64>,
+    sum: u128,
+    sum_sq: f64,
+    min: u64,
+    max: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        // 自动扩展上界，任意大的延迟都能记下
+        let hist = Histogram::new(SIGNIFICANT_DIGITS).expect("有效数字位数在 0..=5 之内");
+        Self {
+            hist,
+            sum: 0,
+            sum_sq: 0.0,
+            min: u64::MAX,
+            max: 0,
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.hist.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hist.is_empty()
+    }
+
+    pub fn record(&mut self, value: u64) {
+        // saturating_record 不会触发自动扩展，只会把值截到当前上界；record 才会按需扩展
+        if self.hist.record(value).is_err() {
+            self.hist.saturating_record(value);
+        }
+        self.sum += value as u128;
+        self.sum_sq += value as f64 * value as f64;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        self.hist
+            .add(&other.hist)
+            .expect("自动扩展的直方图合并不会越界");
+        self.sum += other.sum;
+        self.sum_sq += other.sum_sq;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// percentiles 为百分数（如 99.9）；为空时各项均为 0
+    pub fn summary(&self, percentiles: &[f64]) -> LatencySummary {
+        if self.is_empty() {
+            return LatencySummary {
+                percentiles: percentiles.iter().map(|&p| (p, 0.0)).collect(),
+                ..LatencySummary::default()
+            };
+        }
+        let count = self.len();
+        let avg = self.sum as f64 / count as f64;
+        let variance = (self.sum_sq / count as f64 - avg * avg).max(0.0);
+        LatencySummary {
+            count: count as usize,
+            min: self.min as f64,
+            max: self.max as f64,
+            avg,
+            stddev: variance.sqrt(),
+            percentiles: percentiles
+                .iter()
+                .map(|&p| (p, self.percentile(p / 100.0)))
+                .collect(),
+        }
+    }
+
+    /// p 分位（0 < p <= 1）：先按最近秩取第 ceil(p * n) 个样本（至少为第 1 个）所在的直方图桶，再返回该桶内等价值的上界，
+    /// 并截到实际最大值。结果只精确到 SIGNIFICANT_DIGITS 位有效数字（相对误差不超过 0.1%），不一定是某个实际样本值
+    fn percentile(&self, p: f64) -> f64 {
+        self.hist.value_at_quantile(p).min(self.max) as f64
+    }
+}
+
+impl LatencySummary {
+    /// 各统计量除以 divisor，用于把一次往返的延迟摊到其中每条语句
+    pub fn amortized(&self, divisor: f64) -> Self {
+        Self {
+            count: self.count,
+            min: self.min / divisor,
+            max: self.max / divisor,
+            avg: self.avg / divisor,
+            stddev: self.stddev / divisor,
+            percentiles: self
+                .percentiles
+                .iter()
+                .map(|&(p, v)| (p, v / divisor))
+                .collect(),
+        }
+    }
+}
+
+/// 报告中的字段名：50 -> p50，99.9 -> p999
+pub fn percentile_name(p: f64) -> String {
+    format!("p{}", p.to_string().replace('.', ""))
+}
+
+/// 可靠估计 p 分位（0 < p < 1）所需的最少样本数，即 TAIL_SAMPLES / (1 - p)：p99 需要 1000 个
+pub fn min_samples_for(p: f64) -> u64 {
+    // 99.9 / 100 得到的 1 - p 略小于 0.001，直接向上取整会多出 1
+    (TAIL_SAMPLES / (1.0 - p) - 1e-6).ceil() as u64
+}
+
+/// 样本数为 count 时不足以可靠估计的分位点（百分数）及其所需样本数
+pub fn undersampled_percentiles(count: u64, percentiles: &[f64]) -> Vec<(String, u64)> {
+    percentiles
+        .iter()
+        .map(|&p| (percentile_name(p), min_samples_for(p / 100.0)))
+        .filter(|&(_, needed)| count < needed)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn within(actual: f64, expected: f64, tolerance: f64) -> bool {
+        (actual - expected).abs() <= expected * tolerance
+    }
+
+    #[test]
+    fn histogram_percentiles_within_significant_digits() {
+        let mut hist = LatencyHistogram::new();
+        for nanos in 1..=1_000_000 {
+            hist.record(nanos);
+        }
+        let summary = hist.summary(&[50.0, 99.0]);
+        assert_eq!(summary.count, 1_000_000);
+        for (&(p, actual), expected) in summary.percentiles.iter().zip([500_000.0, 990_000.0]) {
+            assert!(within(actual, expected, 0.001), "p{} = {}，应在 {} 的 0.1% 以内", p, actual, expected);
+        }
+    }
+
+    #[test]
+    fn nearest_rank_on_small_values() {
+        // 2048 以内每个整数各占一个桶，最近秩的结果是精确的
+        let mut hist = LatencyHistogram::new();
+        for nanos in 1..=100 {
+            hist.record(nanos);
+        }
+        let summary = hist.summary(&[50.0, 95.0, 99.0, 100.0]);
+        assert_eq!(summary.percentiles, vec![(50.0, 50.0), (95.0, 95.0), (99.0, 99.0), (100.0, 100.0)]);
+    }
+
+    #[test]
+    fn summary_moments_are_exact() {
+        let mut hist = LatencyHistogram::new();
+        for nanos in [2, 4, 4, 4, 5, 5, 7, 9] {
+            hist.record(nanos);
+        }
+        let summary = hist.summary(&[50.0]);
+        assert_eq!((summary.count, summary.min, summary.max), (8, 2.0, 9.0));
+        assert_eq!((summary.avg, summary.stddev), (5.0, 2.0));
+    }
+
+    #[test]
+    fn empty_summary_is_zero() {
+        let summary = LatencyHistogram::new().summary(&[50.0, 99.0]);
+        assert_eq!((summary.count, summary.min, summary.max, summary.avg), (0, 0.0, 0.0, 0.0));
+        assert_eq!(summary.percentiles, vec![(50.0, 0.0), (99.0, 0.0)]);
+    }
+
+    #[test]
+    fn merge_matches_single_histogram() {
+        let (mut left, mut right, mut all) = (LatencyHistogram::new(), LatencyHistogram::new(), LatencyHistogram::new());
+        for nanos in 1..=1000 {
+            if nanos % 3 == 0 {
+                left.record(nanos);
+            } else {
+                right.record(nanos);
+            }
+            all.record(nanos);
+        }
+        left.merge(&right);
+        let percentiles = [50.0, 99.0];
+        let (merged, expected) = (left.summary(&percentiles), all.summary(&percentiles));
+        assert_eq!((merged.count, merged.min, merged.max), (expected.count, expected.min, expected.max));
+        assert_eq!((merged.avg, merged.percentiles), (expected.avg, expected.percentiles));
+        assert!(within(merged.stddev, expected.stddev, 1e-9));
+    }
+
+    #[test]
+    fn percentile_names() {
+        assert_eq!(percentile_name(50.0), "p50");
+        assert_eq!(percentile_name(99.9), "p999");
+        assert_eq!(percentile_name(99.99), "p9999");
+        // 两者同名，由 Percentiles::from_str 拒绝同时指定
+        assert_eq!(percentile_name(9.9), percentile_name(99.0));
+    }
+
+    #[test]
+    fn min_samples_follow_tail_rule() {
+        assert_eq!(min_samples_for(0.5), 20);
+        assert_eq!(min_samples_for(0.99), 1000);
+        assert_eq!(min_samples_for(99.9 / 100.0), 10_000);
+    }
+
+    #[test]
+    fn undersampled_lists_only_short_tails() {
+        let percentiles = [50.0, 95.0, 99.0, 99.9];
+        assert_eq!(
+            undersampled_percentiles(1000, &percentiles),
+            vec![("p999".to_string(), 10_000)]
+        );
+        assert_eq!(undersampled_percentiles(100, &percentiles).len(), 3);
+        assert!(undersampled_percentiles(10_000, &percentiles).is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::shutdown;
+
+#[derive(Debug, Default)]
+struct PauseState {
+    /// 已结束的暂停累计时长
+    total: Duration,
+    /// 当前这次暂停的开始时刻
+    since: Option<Instant>,
+}
+
+/// 采样暂停开关：SIGUSR1 暂停、SIGUSR2 恢复。worker 每次操作前检查，
+/// 暂停期间停在这里等待、连接保持不动，便于手工 EXPLAIN 或查看服务端状态
+#[derive(Clone)]
+pub struct PauseGate {
+    rx: watch::Receiver<bool>,
+    state: Arc<Mutex<PauseState>>,
+}
+
+impl PauseGate {
+    /// 安装信号处理；非 unix 平台返回一个永不暂停的开关
+    pub fn install() -> Self {
+        let (tx, rx) = watch::channel(false);
+        let state = Arc::new(Mutex::new(PauseState::default()));
+        #[cfg(unix)]
+        {
+            match listen(tx, state.clone()) {
+                Ok(()) => tracing::info!(
+                    "可用 kill -USR1 {} 暂停采样，kill -USR2 {} 恢复",
+                    std::process::id(),
+                    std::process::id()
+                ),
+                Err(e) => tracing::warn!("注册 SIGUSR1/SIGUSR2 失败，暂停功能不可用: {}", e),
+            }
+        }
+        #[cfg(not(unix))]
+        drop(tx);
+        Self { rx, state }
+    }
+
+    /// 暂停中则等到恢复；暂停期间收到 Ctrl-C 也直接放行，由调用方检查停止请求
+    pub async fn wait(&mut self) {
+        if *self.rx.borrow() {
+            // 发送端随信号任务一起存活，只有进程退出时才会关闭
+            tokio::select! {
+                _ = self.rx.wait_for(|paused| !paused) => {}
+                _ = shutdown::wait() => {}
+            }
+        }
+    }
+
+    /// 截至此刻的暂停总时长，包含尚未结束的这一次；两次读数之差即区间内的暂停时间
+    pub fn paused_total(&self) -> Duration {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.total + state.since.map(|t| t.elapsed()).unwrap_or_default()
+    }
+}
+
+#[cfg(unix)]
+fn listen(tx: watch::Sender<bool>, state: Arc<Mutex<PauseState>>) -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut pause = signal(SignalKind::user_defined1())?;
+    let mut resume = signal(SignalKind::user_defined2())?;
+    tokio::spawn(async move {
+        loop {
+            let paused = tokio::select! {
+                Some(()) = pause.recv() => true,
+                Some(()) = resume.recv() => false,
+                else => break,
+            };
+            let mut st = state.lock().unwrap_or_else(|e| e.into_inner());
+            match (paused, st.since) {
+                (true, None) => {
+                    st.since = Some(Instant::now());
+                    tracing::info!("收到 SIGUSR1，采样已暂停（连接保持），发送 SIGUSR2 恢复");
+                }
+                (false, Some(since)) => {
+                    let d = since.elapsed();
+                    st.total += d;
+                    st.since = None;
+                    tracing::info!("收到 SIGUSR2，恢复采样，本次暂停 {:.1}s", d.as_secs_f64());
+                }
+                _ => continue,
+            }
+            drop(st);
+            let _ = tx.send(paused);
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+/// 场景名 -> 执行计划指纹
+pub type PlanBaseline = BTreeMap<String, Vec<String>>;
+
+/// 场景名 -> EXPLAIN 输出的原始 JSON
+pub type ScenarioPlans = BTreeMap<String, Value>;
+
+/// 从 MySQL `EXPLAIN FORMAT=JSON` 中提取访问方式：每张表的 access_type 与所选索引，以及是否 filesort。
+/// 代价、行数估算等会随统计信息波动的字段不参与指纹。
+pub fn mysql_fingerprint(plan: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    walk_mysql(plan, &mut out);
+    out
+}
+
+fn walk_mysql(v: &Value, out: &mut Vec<String>) {
+    match v {
+        Value::Object(map) => {
+            if let Some(access) = map.get("access_type").and_then(Value::as_str) {
+                let table = map.get("table_name").and_then(Value::as_str).unwrap_or("?");
+                let key = map.get("key").and_then(Value::as_str).unwrap_or("-");
+                out.push(format!("{}:{}:{}", table, access, key));
+            }
+            if map.get("using_filesort").and_then(Value::as_bool) == Some(true) {
+                out.push("filesort".to_string());
+            }
+            for child in map.values() {
+                walk_mysql(child, out);
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| walk_mysql(item, out)),
+        _ => {}
+    }
+}
+
+/// 从 Postgres `EXPLAIN (FORMAT JSON)` 中按先序提取节点类型、索引名和表名
+pub fn postgres_fingerprint(plan: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    let root = plan
+        .as_array()
+        .and_then(|a| a.first())
+        .and_then(|p| p.get("Plan"))
+        .unwrap_or(plan);
+    walk_postgres(root, &mut out);
+    out
+}
+
+fn walk_postgres(node: &Value, out: &mut Vec<String>) {
+    let Some(node_type) = node.get("Node Type").and_then(Value::as_str) else {
+        return;
+    };
+    let mut entry = node_type.to_string();
+    if let Some(index) = node.get("Index Name").and_then(Value::as_str) {
+        entry.push_str(&format!("[{}]", index));
+    }
+    if let Some(rel) = node.get("Relation Name").and_then(Value::as_str) {
+        entry.push_str(&format!("({})", rel));
+    }
+    out.push(entry);
+    if let Some(children) = node.get("Plans").and_then(Value::as_array) {
+        children.iter().for_each(|c| walk_postgres(c, out));
+    }
+}
+
+pub fn load_baseline(path: &Path) -> Result<Option<PlanBaseline>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("读取执行计划基线 {:?} 失败", path))?;
+    let baseline = serde_json::from_str(&text)
+        .with_context(|| format!("解析执行计划基线 {:?} 失败", path))?;
+    Ok(Some(baseline))
+}
+
+pub fn save_baseline(path: &Path, baseline: &PlanBaseline) -> Result<()> {
+    let json = serde_json::to_string_pretty(baseline)?;
+    std::fs::write(path, json).with_context(|| format!("写入执行计划基线 {:?} 失败", path))
+}
+
+/// 返回与基线不一致的场景描述；基线里没有的场景不算回归
+pub fn diff(baseline: &PlanBaseline, current: &PlanBaseline) -> Vec<String> {
+    current
+        .iter()
+        .filter_map(|(name, plan)| match baseline.get(name) {
+            Some(expected) if expected != plan => Some(format!(
+                "{}: 基线 [{}] -> 当前 [{}]",
+                name,
+                expected.join(", "),
+                plan.join(", ")
+            )),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,257 @@
+use anyhow::{anyhow, bail, Context, Result};
+use mysql_async::prelude::Queryable;
+
+use crate::config::{Column, DbConfig, DbKind, PayloadType, PkStrategy};
+use crate::connect;
+
+/// 装载与基准依赖的 events 列
+const EXPECTED_COLUMNS: &[&str] = &[
+    "id",
+    "user_id",
+    "created_at",
+    "amount",
+    "status",
+    "category",
+    "payload",
+];
+
+/// 各列可接受的 information_schema.data_type。类型不对时 INSERT/COPY 往往依然成功，
+/// 但会被隐式转换（如 amount 是 VARCHAR 时存成字符串），所以在装载前拦下来
+const MYSQL_COLUMN_TYPES: &[(&str, &[&str])] = &[
+    ("id", &["bigint"]),
+    ("user_id", &["bigint"]),
+    ("created_at", &["timestamp", "datetime"]),
+    ("amount", &["decimal", "double", "float"]),
+    ("status", &["tinyint", "smallint", "mediumint", "int", "bigint"]),
+    ("category", &["smallint", "mediumint", "int", "bigint"]),
+    ("payload", &["varchar", "char", "text", "mediumtext", "longtext"]),
+];
+
+const POSTGRES_COLUMN_TYPES: &[(&str, &[&str])] = &[
+    ("id", &["bigint"]),
+    ("user_id", &["bigint"]),
+    ("created_at", &["timestamp without time zone"]),
+    ("amount", &["numeric", "double precision", "real"]),
+    ("status", &["smallint", "integer", "bigint"]),
+    ("category", &["smallint", "integer", "bigint"]),
+    ("payload", &["character varying", "character", "text"]),
+];
+
+/// 预检要求：装载时需要 payload 列放得下生成的字符串、跳过的列能由数据库补值，基准时需要表里有数据
+pub struct Expectations {
+    pub payload_size: Option<usize>,
+    pub require_rows: bool,
+    pub skip_columns: Vec<Column>,
+    /// 装载时的主键策略：决定 id 列应有的类型、以及是否必须自增
+    pub pk: Option<PkStrategy>,
+    /// 装载时会写入 NULL 的列（--null-rate）
+    pub nullable_columns: Vec<Column>,
+    /// 装载时 payload 列应有的类型；None 时文本与 JSON 类型都接受
+    pub payload_type: Option<PayloadType>,
+}
+
+/// --payload-type json 时 payload 列应有的类型
+fn json_payload_types(kind: DbKind) -> &'static [&'static str] {
+    match kind {
+        DbKind::Mysql => &["json"],
+        DbKind::Postgres => &["jsonb", "json"],
+    }
+}
+
+/// 非自增主键时 id 列应有的类型；None 表示沿用默认的 bigint
+fn id_types(kind: DbKind, pk: Option<PkStrategy>) -> Option<&'static [&'static str]> {
+    match (kind, pk?) {
+        (_, PkStrategy::AutoIncrement | PkStrategy::RandomInt) => None,
+        (DbKind::Mysql, PkStrategy::Uuid) => Some(&["binary"]),
+        (DbKind::Postgres, PkStrategy::Uuid) => Some(&["uuid"]),
+    }
+}
+
+#[derive(Debug)]
+struct ColumnInfo {
+    name: String,
+    data_type: String,
+    max_len: Option<u64>,
+    /// 有默认值、可为 NULL、或是生成/自增列：不写入也不会违反约束
+    has_default: bool,
+    nullable: bool,
+}
+
+/// 连接数据库并检查 events 表结构，任何一项不满足都返回错误
+pub async fn check(db: &DbConfig, expect: &Expectations) -> Result<()> {
+    let (columns, has_rows) = match db.kind {
+        DbKind::Mysql => fetch_mysql(db).await?,
+        DbKind::Postgres => fetch_postgres(db).await?,
+    };
+    tracing::info!("数据库连接正常");
+
+    if columns.is_empty() {
+        bail!("events 表不存在（或当前用户无权访问）");
+    }
+    let missing: Vec<&str> = EXPECTED_COLUMNS
+        .iter()
+        .copied()
+        .filter(|name| !columns.iter().any(|c| c.name.eq_ignore_ascii_case(name)))
+        .collect();
+    if !missing.is_empty() {
+        bail!("events 表缺少列: {}", missing.join(", "));
+    }
+
+    let expected_types = match db.kind {
+        DbKind::Mysql => MYSQL_COLUMN_TYPES,
+        DbKind::Postgres => POSTGRES_COLUMN_TYPES,
+    };
+    let mismatches: Vec<String> = expected_types
+        .iter()
+        .filter_map(|&(name, accepted)| {
+            let accepted: Vec<&str> = match (name, expect.payload_type) {
+                ("id", _) => id_types(db.kind, expect.pk).unwrap_or(accepted).to_vec(),
+                ("payload", Some(PayloadType::Json)) => json_payload_types(db.kind).to_vec(),
+                // 基准只读 payload，文本与 JSON 列都可以
+                ("payload", None) => [accepted, json_payload_types(db.kind)].concat(),
+                _ => accepted.to_vec(),
+            };
+            let info = columns.iter().find(|c| c.name.eq_ignore_ascii_case(name))?;
+            let actual = info.data_type.to_ascii_lowercase();
+            (!accepted.contains(&actual.as_str()))
+                .then(|| format!("{}: 实际 {}，期望 {}", name, actual, accepted.join("/")))
+        })
+        .collect();
+    if !mismatches.is_empty() {
+        for m in &mismatches {
+            tracing::error!("列类型不匹配 {}", m);
+        }
+        bail!("events 表有 {} 列类型不匹配: {}", mismatches.len(), mismatches.join("; "));
+    }
+
+    if let Some(payload_size) = expect.payload_size {
+        let payload = columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case("payload"))
+            .ok_or_else(|| anyhow!("events 表缺少 payload 列"))?;
+        if let Some(max_len) = payload.max_len
+            && payload_size as u64 > max_len
+        {
+            bail!(
+                "--payload-size {} 超过 payload 列长度上限 {}",
+                payload_size,
+                max_len
+            );
+        }
+    }
+
+    if let Some(pk) = expect.pk {
+        let id = columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case("id"))
+            .ok_or_else(|| anyhow!("events 表缺少 id 列"))?;
+        // has_default 对主键列来说就是是否自增（主键不可为 NULL）
+        match (pk, id.has_default) {
+            (PkStrategy::AutoIncrement, false) => {
+                bail!("--pk auto-increment 要求 id 列自增，但该列没有自增/默认值；建表时的主键策略是否不同？")
+            }
+            (PkStrategy::Uuid | PkStrategy::RandomInt, true) => tracing::warn!(
+                "id 列带有自增/默认值，--pk {:?} 会用客户端生成的值覆盖它",
+                pk
+            ),
+            _ => {}
+        }
+    }
+
+    for col in &expect.skip_columns {
+        let info = columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(col.name()))
+            .ok_or_else(|| anyhow!("events 表缺少列 {}", col.name()))?;
+        if !info.has_default {
+            bail!(
+                "--skip-columns 包含 {}，但该列 NOT NULL 且既无默认值也不是生成列",
+                col.name()
+            );
+        }
+    }
+
+    for col in &expect.nullable_columns {
+        let info = columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(col.name()))
+            .ok_or_else(|| anyhow!("events 表缺少列 {}", col.name()))?;
+        if !info.nullable {
+            bail!(
+                "--null-rate 大于 0 时 {} 列需要允许 NULL，但该列为 NOT NULL；可用 --drop-table 重建，或手动去掉 NOT NULL 约束",
+                col.name()
+            );
+        }
+    }
+
+    if expect.require_rows && !has_rows {
+        bail!("events 表为空，无法基准测试");
+    }
+    tracing::info!("events 表结构检查通过");
+    Ok(())
+}
+
+async fn fetch_mysql(db: &DbConfig) -> Result<(Vec<ColumnInfo>, bool)> {
+    let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+    let mut conn = pool.get_conn().await.context("连接 MySQL 失败")?;
+    let rows: Vec<(String, String, Option<u64>, bool, bool)> = conn
+        .query(
+            "SELECT column_name, data_type, character_maximum_length, (column_default IS NOT NULL OR is_nullable = 'YES' OR extra LIKE '%GENERATED%' OR extra LIKE '%auto_increment%'), is_nullable = 'YES' FROM information_schema.columns WHERE table_schema = DATABASE() AND table_name = 'events'",
+        )
+        .await?;
+    let has_rows = if rows.is_empty() {
+        false
+    } else {
+        let one: Option<u8> = conn.query_first("SELECT 1 FROM events LIMIT 1").await?;
+        one.is_some()
+    };
+    conn.disconnect().await?;
+    pool.disconnect().await?;
+    let columns = rows
+        .into_iter()
+        .map(|(name, data_type, max_len, has_default, nullable)| ColumnInfo {
+            name,
+            data_type,
+            max_len,
+            has_default,
+            nullable,
+        })
+        .collect();
+    Ok((columns, has_rows))
+}
+
+async fn fetch_postgres(db: &DbConfig) -> Result<(Vec<ColumnInfo>, bool)> {
+    let (client, connection) = connect::postgres(&db.url, &db.connect)
+        .await
+        .context("连接 Postgres 失败")?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("Postgres 连接任务出错: {}", e);
+        }
+    });
+    let rows = client
+        .query_typed(
+            "SELECT column_name::text, data_type::text, character_maximum_length::int8, (column_default IS NOT NULL OR is_nullable = 'YES' OR is_generated = 'ALWAYS' OR is_identity = 'YES'), is_nullable = 'YES' FROM information_schema.columns WHERE table_schema = 'public' AND table_name = 'events'",
+            &[],
+        )
+        .await?;
+    let columns: Vec<ColumnInfo> = rows
+        .iter()
+        .map(|r| ColumnInfo {
+            name: r.get(0),
+            data_type: r.get(1),
+            max_len: r.get::<_, Option<i64>>(2).map(|v| v.max(0) as u64),
+            has_default: r.get(3),
+            nullable: r.get(4),
+        })
+        .collect();
+    let has_rows = if columns.is_empty() {
+        false
+    } else {
+        !client
+            .query_typed("SELECT 1 FROM public.events LIMIT 1", &[])
+            .await?
+            .is_empty()
+    };
+    Ok((columns, has_rows))
+}
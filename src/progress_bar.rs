@@ -0,0 +1,70 @@
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::sync::LazyLock;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressFinish, ProgressState, ProgressStyle};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// 所有进度条挂在同一个 MultiProgress 上，日志输出时先把进度条收起，避免和日志行交错
+static BARS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// stdout 是终端时返回一个进度条；否则返回 None，调用方沿用定期打印的日志行。
+/// len 为 None 时不显示进度和 ETA，只显示计数与速率（如按时长采样）。
+/// 最后一个句柄释放时进度条自动清除，出错提前返回也不会残留
+pub fn new(len: Option<u64>, prefix: &str, unit: &str) -> Option<ProgressBar> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    let (bar, template) = match len {
+        Some(len) => (
+            ProgressBar::new(len),
+            format!("{{prefix}} [{{bar:40}}] {{human_pos}}/{{human_len}} {} {{rate}} ETA {{eta}}", unit),
+        ),
+        None => (
+            ProgressBar::no_length(),
+            format!("{{spinner}} {{prefix}} {{human_pos}} {} {{rate}} 已用 {{elapsed}}", unit),
+        ),
+    };
+    let style = ProgressStyle::with_template(&template)
+        .expect("进度条模板有效")
+        .with_key("rate", |state: &ProgressState, w: &mut dyn fmt::Write| {
+            let _ = write!(w, "{:.0}/s", state.per_sec());
+        })
+        .progress_chars("=> ");
+    let bar = bar
+        .with_style(style)
+        .with_prefix(prefix.to_string())
+        .with_finish(ProgressFinish::AndClear);
+    let bar = BARS.add(bar);
+    // 进度条画在 stderr 上，stderr 被重定向时同样退回日志行
+    if bar.is_hidden() {
+        BARS.remove(&bar);
+        return None;
+    }
+    Some(bar)
+}
+
+/// 日志的 writer：写入 stdout 前收起进度条，写完再重画
+pub struct LogWriter;
+
+impl<'a> MakeWriter<'a> for LogWriter {
+    type Writer = LogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LogWriter
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        BARS.suspend(|| io::stdout().write(buf))
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        BARS.suspend(|| io::stdout().write_all(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
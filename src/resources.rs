@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+// Linux 上 /proc/self/stat 的 utime/stime 单位为 clock tick，绝大多数内核为 100Hz
+const CLOCK_TICKS_PER_SEC: f64 = 100.0;
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 客户端进程自身的 CPU/内存占用，用来判断瓶颈是否在压测端
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceSummary {
+    pub samples: u64,
+    /// 单核为 100%，多核打满可超过 100%
+    pub cpu_avg_pct: f64,
+    pub cpu_peak_pct: f64,
+    pub rss_avg_mb: f64,
+    pub rss_peak_mb: f64,
+}
+
+/// 后台采样任务，`finish` 时停止并汇总
+pub struct ResourceSampler {
+    stop: Arc<Notify>,
+    handle: JoinHandle<ResourceSummary>,
+}
+
+impl ResourceSampler {
+    /// 当前平台无 /proc 时返回 None
+    pub fn start() -> Option<Self> {
+        if read_cpu_ticks().is_none() || read_rss_kb().is_none() {
+            tracing::warn!("当前平台无法读取 /proc/self，跳过客户端资源采样");
+            return None;
+        }
+        let stop = Arc::new(Notify::new());
+        let handle = tokio::spawn(sample_loop(stop.clone()));
+        Some(Self { stop, handle })
+    }
+
+    pub async fn finish(self) -> Option<ResourceSummary> {
+        self.stop.notify_one();
+        self.handle.await.ok()
+    }
+}
+
+async fn sample_loop(stop: Arc<Notify>) -> ResourceSummary {
+    let mut ticker = tokio::time::interval(SAMPLE_INTERVAL);
+    ticker.tick().await;
+    let mut last_ticks = read_cpu_ticks().unwrap_or(0);
+    let mut last_at = Instant::now();
+
+    let mut samples = 0u64;
+    let (mut cpu_sum, mut cpu_peak) = (0.0f64, 0.0f64);
+    let (mut rss_sum, mut rss_peak) = (0.0f64, 0.0f64);
+    loop {
+        tokio::select! {
+            _ = stop.notified() => break,
+            _ = ticker.tick() => {}
+        }
+        let (Some(ticks), Some(rss_kb)) = (read_cpu_ticks(), read_rss_kb()) else {
+            continue;
+        };
+        let now = Instant::now();
+        let wall = now.duration_since(last_at).as_secs_f64().max(1e-6);
+        let cpu_pct = (ticks.saturating_sub(last_ticks)) as f64 / CLOCK_TICKS_PER_SEC / wall * 100.0;
+        let rss_mb = rss_kb as f64 / 1024.0;
+        last_ticks = ticks;
+        last_at = now;
+
+        samples += 1;
+        cpu_sum += cpu_pct;
+        cpu_peak = cpu_peak.max(cpu_pct);
+        rss_sum += rss_mb;
+        rss_peak = rss_peak.max(rss_mb);
+    }
+
+    let n = samples.max(1) as f64;
+    ResourceSummary {
+        samples,
+        cpu_avg_pct: cpu_sum / n,
+        cpu_peak_pct: cpu_peak,
+        rss_avg_mb: rss_sum / n,
+        rss_peak_mb: rss_peak,
+    }
+}
+
+/// utime + stime（第 14、15 个字段）；comm 字段可能含空格，从最后一个 ')' 之后开始数
+fn read_cpu_ticks() -> Option<u64> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    let rest = &stat[stat.rfind(')')? + 1..];
+    let mut fields = rest.split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(utime + stime)
+}
+
+fn read_rss_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
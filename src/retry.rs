@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use tokio_postgres::error::SqlState;
+
+// 退避等待的上限
+const RETRY_BACKOFF_MAX: Duration = Duration::from_secs(10);
+// 服务端主动断开连接的 MySQL 错误码：ER_SERVER_SHUTDOWN、ER_CLIENT_INTERACTION_TIMEOUT
+const MYSQL_CONNECTION_ERRORS: &[u16] = &[1053, 4031];
+const PG_CONNECTION_ERRORS: &[SqlState] = &[
+    SqlState::ADMIN_SHUTDOWN,
+    SqlState::CRASH_SHUTDOWN,
+    SqlState::CANNOT_CONNECT_NOW,
+];
+// ER_LOCK_DEADLOCK：InnoDB 已回滚整个事务，重做即可
+const MYSQL_TRANSIENT_ERRORS: &[u16] = &[1213];
+const PG_TRANSIENT_ERRORS: &[SqlState] = &[SqlState::T_R_SERIALIZATION_FAILURE, SqlState::T_R_DEADLOCK_DETECTED];
+// ER_QUERY_TIMEOUT（超过 max_execution_time）、ER_LOCK_WAIT_TIMEOUT
+const MYSQL_TIMEOUT_ERRORS: &[u16] = &[3024, 1205];
+// statement_timeout 触发时报 query_canceled；lock_timeout 报 lock_not_available
+const PG_TIMEOUT_ERRORS: &[SqlState] = &[SqlState::QUERY_CANCELED, SqlState::LOCK_NOT_AVAILABLE];
+
+/// 操作失败的大类，--continue-on-error 按它分类计数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// 语句超时或等锁超时
+    Timeout,
+    /// 连接断开
+    Connection,
+    /// 死锁或序列化失败，服务端已回滚事务
+    Rollback,
+    /// 其余语句级错误
+    Query,
+}
+
+/// --max-retries / --retry-backoff：可重试错误的重试次数与首次退避，之后每次翻倍
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_retries > 0 && self.backoff.is_zero() {
+            bail!("--retry-backoff 必须大于 0");
+        }
+        Ok(())
+    }
+
+    /// 第 attempt 次（从 1 开始）重试前的等待时间
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        self.backoff.saturating_mul(factor).min(RETRY_BACKOFF_MAX)
+    }
+}
+
+/// 连接级错误（连接断开、服务端关闭），区别于语句本身出错；只有前者值得重连
+pub fn is_connection_error(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| {
+        if let Some(err) = cause.downcast_ref::<mysql_async::Error>() {
+            return match err {
+                mysql_async::Error::Io(_) => true,
+                mysql_async::Error::Driver(mysql_async::DriverError::ConnectionClosed) => true,
+                mysql_async::Error::Server(err) => MYSQL_CONNECTION_ERRORS.contains(&err.code),
+                _ => false,
+            };
+        }
+        if let Some(err) = cause.downcast_ref::<tokio_postgres::Error>() {
+            return err.is_closed()
+                || err.code().is_some_and(|code| PG_CONNECTION_ERRORS.contains(code))
+                || std::error::Error::source(err).is_some_and(|src| src.is::<std::io::Error>());
+        }
+        false
+    })
+}
+
+/// 死锁、序列化失败等语句级的瞬时错误：连接仍然可用，原样重做即可。语法、权限等错误不在此列
+pub fn is_transient_error(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| {
+        if let Some(mysql_async::Error::Server(err)) = cause.downcast_ref::<mysql_async::Error>() {
+            return MYSQL_TRANSIENT_ERRORS.contains(&err.code);
+        }
+        if let Some(err) = cause.downcast_ref::<tokio_postgres::Error>() {
+            return err.code().is_some_and(|code| PG_TRANSIENT_ERRORS.contains(code));
+        }
+        false
+    })
+}
+
+fn is_timeout_error(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| {
+        if let Some(mysql_async::Error::Server(err)) = cause.downcast_ref::<mysql_async::Error>() {
+            return MYSQL_TIMEOUT_ERRORS.contains(&err.code);
+        }
+        if let Some(err) = cause.downcast_ref::<tokio_postgres::Error>() {
+            return err.code().is_some_and(|code| PG_TIMEOUT_ERRORS.contains(code));
+        }
+        false
+    })
+}
+
+impl ErrorKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ErrorKind::Timeout => "超时",
+            ErrorKind::Connection => "连接",
+            ErrorKind::Rollback => "回滚",
+            ErrorKind::Query => "语句",
+        }
+    }
+}
+
+pub fn classify_error(e: &anyhow::Error) -> ErrorKind {
+    if is_connection_error(e) {
+        ErrorKind::Connection
+    } else if is_timeout_error(e) {
+        ErrorKind::Timeout
+    } else if is_transient_error(e) {
+        ErrorKind::Rollback
+    } else {
+        ErrorKind::Query
+    }
+}
+
+/// 装载时值得重试的错误：瞬时错误，或连接断开（换一条连接重做）
+pub fn is_retryable(e: &anyhow::Error) -> bool {
+    is_transient_error(e) || is_connection_error(e)
+}
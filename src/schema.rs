@@ -0,0 +1,252 @@
+use anyhow::{Context, Result};
+use mysql_async::prelude::Queryable;
+
+use crate::config::{DbConfig, DbKind, PayloadType, PkStrategy};
+use crate::connect;
+use crate::userdist::UserDistSpec;
+
+/// payload 列的最小长度，与 init/ 下的建表脚本一致
+const MIN_PAYLOAD_LEN: usize = 200;
+
+/// 表注释中记录装载分布的前缀，注释不以它开头时视为没有记录
+const DIST_COMMENT_PREFIX: &str = "db-performance-obvser:";
+
+/// events 表的建表语句。列类型与装载写入的参数类型对应；二级索引由 --indexes 另行处理。
+/// nullable 时 amount、category 允许 NULL（--null-rate 大于 0）
+fn create_table_sql(kind: DbKind, pk: PkStrategy, payload: PayloadColumn, nullable: bool) -> String {
+    let payload_len = payload.size.max(MIN_PAYLOAD_LEN);
+    let not_null = if nullable { "" } else { " NOT NULL" };
+    match kind {
+        DbKind::Mysql => {
+            let id = match pk {
+                PkStrategy::AutoIncrement => "BIGINT PRIMARY KEY AUTO_INCREMENT",
+                PkStrategy::Uuid => "BINARY(16) PRIMARY KEY",
+                PkStrategy::RandomInt => "BIGINT PRIMARY KEY",
+            };
+            format!(
+                "CREATE TABLE IF NOT EXISTS events (\n  \
+                 id {},\n  \
+                 user_id BIGINT NOT NULL,\n  \
+                 created_at TIMESTAMP NOT NULL,\n  \
+                 amount DECIMAL(10,2){},\n  \
+                 status SMALLINT NOT NULL,\n  \
+                 category INT{},\n  \
+                 payload {} NOT NULL\n\
+                 ) ENGINE=InnoDB",
+                id,
+                not_null,
+                not_null,
+                match payload.kind {
+                    PayloadType::Text => format!("VARCHAR({})", payload_len),
+                    PayloadType::Json => "JSON".to_string(),
+                }
+            )
+        }
+        DbKind::Postgres => {
+            let id = match pk {
+                PkStrategy::AutoIncrement => "BIGSERIAL PRIMARY KEY",
+                PkStrategy::Uuid => "UUID PRIMARY KEY",
+                PkStrategy::RandomInt => "BIGINT PRIMARY KEY",
+            };
+            format!(
+                "CREATE TABLE IF NOT EXISTS public.events (\n  \
+                 id {},\n  \
+                 user_id BIGINT NOT NULL,\n  \
+                 created_at TIMESTAMP NOT NULL,\n  \
+                 amount NUMERIC(10,2){},\n  \
+                 status SMALLINT NOT NULL,\n  \
+                 category INT{},\n  \
+                 payload {} NOT NULL\n\
+                 )",
+                id,
+                not_null,
+                not_null,
+                match payload.kind {
+                    PayloadType::Text => format!("VARCHAR({})", payload_len),
+                    PayloadType::Json => "JSONB".to_string(),
+                }
+            )
+        }
+    }
+}
+
+fn drop_table_sql(kind: DbKind) -> &'static str {
+    match kind {
+        DbKind::Mysql => "DROP TABLE IF EXISTS events",
+        DbKind::Postgres => "DROP TABLE IF EXISTS public.events",
+    }
+}
+
+/// payload 列的类型与（文本类型时的）长度
+#[derive(Clone, Copy)]
+pub struct PayloadColumn {
+    pub kind: PayloadType,
+    pub size: usize,
+}
+
+/// 按需删表、建表；表已存在时 CREATE TABLE IF NOT EXISTS 不做任何改动，结构仍由预检把关
+pub async fn prepare_table(db: &DbConfig, pk: PkStrategy, payload: PayloadColumn, nullable: bool, drop: bool) -> Result<()> {
+    let create = create_table_sql(db.kind, pk, payload, nullable);
+    let drop = drop.then(|| drop_table_sql(db.kind));
+    match db.kind {
+        DbKind::Mysql => {
+            let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+            let mut conn = pool
+                .get_conn()
+                .await
+                .with_context(|| format!("连接 MySQL 失败: {}", connect::redact_url(&db.url)))?;
+            if let Some(sql) = drop {
+                conn.query_drop(sql).await.context("删除 events 表失败")?;
+                tracing::info!("已删除 events 表");
+            }
+            conn.query_drop(&create).await.context("创建 events 表失败")?;
+            conn.disconnect().await?;
+            pool.disconnect().await?;
+        }
+        DbKind::Postgres => {
+            let (client, connection) = connect::postgres(&db.url, &db.connect).await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    tracing::error!("Postgres 连接任务出错: {}", e);
+                }
+            });
+            if let Some(sql) = drop {
+                client.batch_execute(sql).await.context("删除 events 表失败")?;
+                tracing::info!("已删除 events 表");
+            }
+            client.batch_execute(&create).await.context("创建 events 表失败")?;
+        }
+    }
+    tracing::info!("events 表已就绪（主键策略 {:?}）", pk);
+    Ok(())
+}
+
+/// 清空 events 表并把自增计数器归位，表结构与索引保持不变
+pub async fn truncate_table(db: &DbConfig) -> Result<()> {
+    match db.kind {
+        DbKind::Mysql => {
+            let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+            let mut conn = pool
+                .get_conn()
+                .await
+                .with_context(|| format!("连接 MySQL 失败: {}", connect::redact_url(&db.url)))?;
+            // TRUNCATE 在 InnoDB 中是删表重建，AUTO_INCREMENT 随之回到 1
+            conn.query_drop("TRUNCATE TABLE events")
+                .await
+                .context("清空 events 表失败")?;
+            conn.disconnect().await?;
+            pool.disconnect().await?;
+        }
+        DbKind::Postgres => {
+            let (client, connection) = connect::postgres(&db.url, &db.connect).await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    tracing::error!("Postgres 连接任务出错: {}", e);
+                }
+            });
+            client
+                .batch_execute("TRUNCATE public.events RESTART IDENTITY")
+                .await
+                .context("清空 events 表失败")?;
+        }
+    }
+    tracing::info!("已清空 events 表，自增计数器已重置");
+    Ok(())
+}
+
+/// 装载时的 user_id 分布与基数，记在 events 的表注释里，bench 据此让 user_id 查询与数据的分布一致
+#[derive(Debug, Clone)]
+pub struct RecordedUserDist {
+    pub spec: UserDistSpec,
+    pub users: u64,
+}
+
+fn dist_comment(dist: &RecordedUserDist) -> String {
+    format!("{} user_dist={} users={}", DIST_COMMENT_PREFIX, dist.spec, dist.users)
+}
+
+fn parse_dist_comment(comment: &str) -> Option<RecordedUserDist> {
+    let rest = comment.strip_prefix(DIST_COMMENT_PREFIX)?;
+    let (mut spec, mut users) = (None, None);
+    for item in rest.split_whitespace() {
+        match item.split_once('=') {
+            Some(("user_dist", value)) => spec = value.parse().ok(),
+            Some(("users", value)) => users = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Some(RecordedUserDist {
+        spec: spec?,
+        users: users?,
+    })
+}
+
+/// 把本次装载的分布写进表注释；需要 ALTER（MySQL）或表所有者（Postgres）权限，失败只警告
+pub async fn record_user_dist(db: &DbConfig, dist: &RecordedUserDist) -> Result<()> {
+    let comment = dist_comment(dist).replace('\'', "''");
+    let result = match db.kind {
+        DbKind::Mysql => {
+            let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+            let mut conn = pool.get_conn().await?;
+            // 只改元数据，不重建表
+            let result = conn
+                .query_drop(format!("ALTER TABLE events COMMENT = '{}'", comment))
+                .await
+                .map_err(anyhow::Error::from);
+            conn.disconnect().await?;
+            pool.disconnect().await?;
+            result
+        }
+        DbKind::Postgres => {
+            let (client, connection) = connect::postgres(&db.url, &db.connect).await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    tracing::error!("Postgres 连接任务出错: {}", e);
+                }
+            });
+            client
+                .batch_execute(&format!("COMMENT ON TABLE public.events IS '{}'", comment))
+                .await
+                .map_err(anyhow::Error::from)
+        }
+    };
+    match result {
+        Ok(()) => tracing::info!("已在 events 表注释中记录 user_id 分布 {}（users={}）", dist.spec, dist.users),
+        Err(e) => tracing::warn!("记录 user_id 分布到表注释失败，bench 将无法自动沿用: {}", e),
+    }
+    Ok(())
+}
+
+/// 读取最近一次装载记录的分布；没有记录或注释不是本工具写的时返回 None
+pub async fn recorded_user_dist(db: &DbConfig) -> Result<Option<RecordedUserDist>> {
+    let comment: Option<String> = match db.kind {
+        DbKind::Mysql => {
+            let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+            let mut conn = pool.get_conn().await?;
+            let comment = conn
+                .query_first(
+                    "SELECT table_comment FROM information_schema.tables \
+                     WHERE table_schema = DATABASE() AND table_name = 'events'",
+                )
+                .await?;
+            conn.disconnect().await?;
+            pool.disconnect().await?;
+            comment
+        }
+        DbKind::Postgres => {
+            let (client, connection) = connect::postgres(&db.url, &db.connect).await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    tracing::error!("Postgres 连接任务出错: {}", e);
+                }
+            });
+            client
+                .query_typed("SELECT obj_description('public.events'::regclass, 'pg_class')", &[])
+                .await?
+                .into_iter()
+                .next()
+                .and_then(|row| row.get(0))
+        }
+    };
+    Ok(comment.as_deref().and_then(parse_dist_comment))
+}
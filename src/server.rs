@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use mysql_async::prelude::Queryable;
+use serde::Serialize;
+use tokio_postgres::Client as PgClient;
+
+use crate::config::{Concurrency, DbConfig, DbKind, Pooler};
+use crate::connect;
+
+// auto 模式只占用剩余连接的一半，给其他客户端和管理连接留余量
+const AUTO_CONCURRENCY_SHARE: f64 = 0.5;
+const AUTO_CONCURRENCY_MAX: usize = 64;
+
+/// 服务端连接上限与当前占用
+#[derive(Debug)]
+struct ConnectionUsage {
+    max_connections: u64,
+    /// 为超级用户等保留、普通客户端用不到的连接
+    reserved: u64,
+    current: u64,
+}
+
+/// 把 `--concurrency` 解析成具体的 worker 数，auto 时查询服务端连接情况
+pub async fn resolve_concurrency(db: &DbConfig, concurrency: Concurrency) -> Result<usize> {
+    let Concurrency::Fixed(n) = concurrency else {
+        return auto_concurrency(db).await;
+    };
+    Ok(n)
+}
+
+async fn auto_concurrency(db: &DbConfig) -> Result<usize> {
+    let usage = match db.kind {
+        DbKind::Mysql => fetch_mysql_usage(db).await?,
+        DbKind::Postgres => fetch_postgres_usage(db).await?,
+    };
+    let available = usage
+        .max_connections
+        .saturating_sub(usage.reserved)
+        .saturating_sub(usage.current);
+    let chosen =
+        ((available as f64 * AUTO_CONCURRENCY_SHARE) as usize).clamp(1, AUTO_CONCURRENCY_MAX);
+    tracing::info!(
+        "--concurrency auto: max_connections={}，保留 {}，当前已用 {}，剩余 {}，取剩余的 {:.0}%（上限 {}）→ 并发 {}",
+        usage.max_connections,
+        usage.reserved,
+        usage.current,
+        available,
+        AUTO_CONCURRENCY_SHARE * 100.0,
+        AUTO_CONCURRENCY_MAX,
+        chosen
+    );
+    if db.pooler != Pooler::None {
+        tracing::warn!("经连接池访问时看到的是后端的连接数，连接池自身的客户端上限可能更低");
+    }
+    Ok(chosen)
+}
+
+async fn fetch_mysql_usage(db: &DbConfig) -> Result<ConnectionUsage> {
+    let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+    let mut conn = pool.get_conn().await.context("连接 MySQL 失败")?;
+    let max_connections: Option<u64> = conn.query_first("SELECT @@max_connections").await?;
+    let threads: Option<(String, u64)> = conn
+        .query_first("SHOW GLOBAL STATUS LIKE 'Threads_connected'")
+        .await?;
+    conn.disconnect().await?;
+    pool.disconnect().await?;
+    Ok(ConnectionUsage {
+        max_connections: max_connections.unwrap_or(0),
+        // MySQL 为 SUPER 用户额外多留 1 个连接（在 max_connections 之外），无需扣除
+        reserved: 0,
+        current: threads.map(|(_, v)| v).unwrap_or(0),
+    })
+}
+
+async fn fetch_postgres_usage(db: &DbConfig) -> Result<ConnectionUsage> {
+    let (client, connection) = connect::postgres(&db.url, &db.connect)
+        .await
+        .context("连接 Postgres 失败")?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("Postgres 连接任务出错: {}", e);
+        }
+    });
+    let row = client
+        .query_typed(
+            "SELECT current_setting('max_connections')::int8, current_setting('superuser_reserved_connections')::int8, (SELECT count(*) FROM pg_stat_activity WHERE backend_type = 'client backend')",
+            &[],
+        )
+        .await?
+        .into_iter()
+        .next()
+        .context("查询 max_connections 没有返回结果")?;
+    let get = |i: usize| row.get::<_, i64>(i).max(0) as u64;
+    Ok(ConnectionUsage {
+        max_connections: get(0),
+        reserved: get(1),
+        current: get(2),
+    })
+}
+
+/// --server-metrics：场景前后各取一次的服务端累计计数器，结果中输出两者之差。
+/// 计数器是全局的（Postgres 为当前库），同时在跑的其他客户端也会计入
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ServerCounters {
+    /// MySQL Innodb_rows_read；Postgres tup_returned
+    pub rows_read: u64,
+    /// 缓冲池命中的页读取：MySQL Innodb_buffer_pool_read_requests - Innodb_buffer_pool_reads；Postgres blks_hit
+    pub buffer_hits: u64,
+    /// 缓冲池未命中、需要读盘的页：MySQL Innodb_buffer_pool_reads；Postgres blks_read
+    pub disk_reads: u64,
+    /// MySQL Created_tmp_files 与 Created_tmp_disk_tables 之和；Postgres temp_files
+    pub temp_files: u64,
+    /// 以下只有 Postgres 有：temp_bytes 与 pg_stat_bgwriter 的 buffers_clean、buffers_alloc
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temp_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bgwriter_buffers_clean: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bgwriter_buffers_alloc: Option<u64>,
+}
+
+impl ServerCounters {
+    /// 本次取值减去 before；统计被重置等导致计数回退时记为 0
+    pub fn since(&self, before: &ServerCounters) -> ServerCounters {
+        let sub = |after: Option<u64>, before: Option<u64>| Some(after?.saturating_sub(before?));
+        ServerCounters {
+            rows_read: self.rows_read.saturating_sub(before.rows_read),
+            buffer_hits: self.buffer_hits.saturating_sub(before.buffer_hits),
+            disk_reads: self.disk_reads.saturating_sub(before.disk_reads),
+            temp_files: self.temp_files.saturating_sub(before.temp_files),
+            temp_bytes: sub(self.temp_bytes, before.temp_bytes),
+            bgwriter_buffers_clean: sub(self.bgwriter_buffers_clean, before.bgwriter_buffers_clean),
+            bgwriter_buffers_alloc: sub(self.bgwriter_buffers_alloc, before.bgwriter_buffers_alloc),
+        }
+    }
+}
+
+pub async fn fetch_mysql_counters(conn: &mut mysql_async::Conn) -> Result<ServerCounters> {
+    let rows: Vec<(String, String)> = conn
+        .query(
+            "SHOW GLOBAL STATUS WHERE Variable_name IN ('Innodb_rows_read', 'Innodb_buffer_pool_read_requests', \
+             'Innodb_buffer_pool_reads', 'Created_tmp_files', 'Created_tmp_disk_tables')",
+        )
+        .await?;
+    let status: HashMap<String, u64> = rows
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value.parse().ok()?)))
+        .collect();
+    let get = |name: &str| status.get(name).copied().unwrap_or(0);
+    Ok(ServerCounters {
+        rows_read: get("Innodb_rows_read"),
+        buffer_hits: get("Innodb_buffer_pool_read_requests").saturating_sub(get("Innodb_buffer_pool_reads")),
+        disk_reads: get("Innodb_buffer_pool_reads"),
+        temp_files: get("Created_tmp_files") + get("Created_tmp_disk_tables"),
+        ..ServerCounters::default()
+    })
+}
+
+/// pg_stat_* 由各后端异步上报（通常在事务结束或空闲时，间隔约 1 秒），刚结束的场景可能有少量计数还没计入
+pub async fn fetch_postgres_counters(client: &PgClient) -> Result<ServerCounters> {
+    let row = client
+        .query_typed(
+            "SELECT d.tup_returned, d.blks_hit, d.blks_read, d.temp_files, d.temp_bytes, b.buffers_clean, b.buffers_alloc \
+             FROM pg_stat_database d, pg_stat_bgwriter b WHERE d.datname = current_database()",
+            &[],
+        )
+        .await?
+        .into_iter()
+        .next()
+        .context("查询 pg_stat_database 没有返回当前库")?;
+    let get = |i: usize| row.get::<_, i64>(i).max(0) as u64;
+    Ok(ServerCounters {
+        rows_read: get(0),
+        buffer_hits: get(1),
+        disk_reads: get(2),
+        temp_files: get(3),
+        temp_bytes: Some(get(4)),
+        bgwriter_buffers_clean: Some(get(5)),
+        bgwriter_buffers_alloc: Some(get(6)),
+    })
+}
@@ -0,0 +1,31 @@
+use std::sync::LazyLock;
+
+use tokio::sync::watch;
+
+/// Ctrl-C 后置为 true；worker 在每次操作前检查，收到后停止采样/装载，已收集的结果照常输出
+static STOP: LazyLock<watch::Sender<bool>> = LazyLock::new(|| watch::channel(false).0);
+
+/// 注册 Ctrl-C 处理：第一次只请求停止，正在执行的操作会做完；第二次立即退出进程
+pub fn install() {
+    tokio::spawn(async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!("注册 Ctrl-C 处理失败，中断时不会输出部分结果: {}", e);
+            return;
+        }
+        STOP.send_replace(true);
+        tracing::warn!("收到 Ctrl-C，等待进行中的操作结束后停止并输出已收集的结果；再按一次立即退出");
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+}
+
+pub fn requested() -> bool {
+    *STOP.borrow()
+}
+
+/// 等到收到停止请求为止
+pub async fn wait() {
+    let mut rx = STOP.subscribe();
+    let _ = rx.wait_for(|stop| *stop).await;
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use mysql_async::prelude::Queryable;
+use serde::Serialize;
+
+use crate::config::{DbConfig, DbKind};
+use crate::connect;
+
+/// 装载后 events 表与各索引占用的空间
+#[derive(Debug, Serialize)]
+struct TableSizes {
+    db: DbKind,
+    /// MySQL data_length（含聚簇的主键）；Postgres pg_relation_size，只算堆表
+    table_bytes: u64,
+    /// MySQL index_length（只含二级索引）；Postgres pg_indexes_size（含主键索引）
+    indexes_bytes: u64,
+    indexes: Vec<IndexSize>,
+}
+
+#[derive(Debug, Serialize)]
+struct IndexSize {
+    name: String,
+    /// MySQL 拿不到 mysql.innodb_index_stats 的读权限时为空
+    size_bytes: Option<u64>,
+}
+
+/// 查询并打印 events 的表与索引大小，指定 output 时另写一份 JSON
+pub async fn report_sizes(db: &DbConfig, output: Option<&Path>) -> Result<()> {
+    let sizes = match db.kind {
+        DbKind::Mysql => fetch_mysql_sizes(db).await?,
+        DbKind::Postgres => fetch_postgres_sizes(db).await?,
+    };
+    tracing::info!(
+        "events 表大小: 数据 {}，索引合计 {}",
+        fmt_mb(Some(sizes.table_bytes)),
+        fmt_mb(Some(sizes.indexes_bytes))
+    );
+    for index in &sizes.indexes {
+        tracing::info!("  索引 {}: {}", index.name, fmt_mb(index.size_bytes));
+    }
+    if let Some(path) = output {
+        std::fs::write(path, serde_json::to_string_pretty(&sizes)?)
+            .with_context(|| format!("写入 {:?} 失败", path))?;
+        tracing::info!("表与索引大小已写入 {:?}", path);
+    }
+    Ok(())
+}
+
+fn fmt_mb(bytes: Option<u64>) -> String {
+    bytes
+        .map(|b| format!("{:.1}MB", b as f64 / 1024.0 / 1024.0))
+        .unwrap_or_else(|| "未知".to_string())
+}
+
+async fn fetch_mysql_sizes(db: &DbConfig) -> Result<TableSizes> {
+    let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+    let mut conn = pool.get_conn().await?;
+    // 大小来自持久化统计信息，刚装载完可能还是旧值；MySQL 8 还会缓存 information_schema 的统计，5.7 没有这个变量
+    conn.query_drop("ANALYZE TABLE events").await?;
+    let _ = conn.query_drop("SET SESSION information_schema_stats_expiry = 0").await;
+    let (table_bytes, indexes_bytes): (u64, u64) = conn
+        .query_first(
+            "SELECT data_length, index_length FROM information_schema.tables \
+             WHERE table_schema = DATABASE() AND table_name = 'events'",
+        )
+        .await?
+        .context("information_schema.tables 中没有 events 表")?;
+    let names: Vec<String> = conn
+        .query(
+            "SELECT DISTINCT index_name FROM information_schema.statistics \
+             WHERE table_schema = DATABASE() AND table_name = 'events' ORDER BY index_name",
+        )
+        .await?;
+    let mut indexes = Vec::with_capacity(names.len());
+    for name in names {
+        let size: Result<Option<u64>, _> = conn
+            .exec_first(
+                "SELECT stat_value * @@innodb_page_size FROM mysql.innodb_index_stats \
+                 WHERE database_name = DATABASE() AND table_name = 'events' AND index_name = ? AND stat_name = 'size'",
+                (&name,),
+            )
+            .await;
+        let size_bytes = match size {
+            Ok(size) => size,
+            Err(e) => {
+                tracing::warn!("读取 mysql.innodb_index_stats 失败，无法得到索引 {} 的大小: {}", name, e);
+                None
+            }
+        };
+        indexes.push(IndexSize { name, size_bytes });
+    }
+    conn.disconnect().await?;
+    pool.disconnect().await?;
+    Ok(TableSizes {
+        db: db.kind,
+        table_bytes,
+        indexes_bytes,
+        indexes,
+    })
+}
+
+async fn fetch_postgres_sizes(db: &DbConfig) -> Result<TableSizes> {
+    let (client, connection) = connect::postgres(&db.url, &db.connect).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("Postgres 连接任务出错: {}", e);
+        }
+    });
+    let row = client
+        .query_typed(
+            "SELECT pg_relation_size('public.events'::regclass), pg_indexes_size('public.events'::regclass)",
+            &[],
+        )
+        .await?
+        .into_iter()
+        .next()
+        .context("查询 events 表大小没有返回结果")?;
+    let table_bytes = row.get::<_, i64>(0).max(0) as u64;
+    let indexes_bytes = row.get::<_, i64>(1).max(0) as u64;
+    let indexes = client
+        .query_typed(
+            "SELECT c.relname::text, pg_relation_size(i.indexrelid) FROM pg_index i \
+             JOIN pg_class c ON c.oid = i.indexrelid WHERE i.indrelid = 'public.events'::regclass ORDER BY c.relname",
+            &[],
+        )
+        .await?
+        .into_iter()
+        .map(|r| IndexSize {
+            name: r.get(0),
+            size_bytes: Some(r.get::<_, i64>(1).max(0) as u64),
+        })
+        .collect();
+    Ok(TableSizes {
+        db: db.kind,
+        table_bytes,
+        indexes_bytes,
+        indexes,
+    })
+}
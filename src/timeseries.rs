@@ -0,0 +1,110 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 一行 JSONL 所属的场景轮次；concurrency、repeat 只在 --ramp / --repeat 下输出
+#[derive(Debug, Clone, Serialize)]
+pub struct SeriesLabel {
+    pub db: &'static str,
+    pub scenario: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Point<'a> {
+    #[serde(flatten)]
+    label: &'a SeriesLabel,
+    /// 距场景开始（含预热）的秒数
+    t: f64,
+    ops_completed: u64,
+    interval_ops_per_sec: f64,
+}
+
+/// 清空（或新建）时序文件；之后每个场景以追加方式写入
+pub fn create(path: &Path) -> Result<()> {
+    File::create(path).with_context(|| format!("创建 --timeseries 文件 {:?} 失败", path))?;
+    Ok(())
+}
+
+/// 后台每秒读一次采样计数并追加一行，`finish` 时写出最后一段不足一秒的区间后停止。
+/// 出错提前返回时 drop 会直接结束后台任务
+pub struct SeriesRecorder {
+    stop: Arc<Notify>,
+    handle: JoinHandle<Result<()>>,
+}
+
+impl SeriesRecorder {
+    pub fn start(path: &Path, label: SeriesLabel, progress: Arc<AtomicU64>, start: Instant) -> Result<Self> {
+        let file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .with_context(|| format!("打开 --timeseries 文件 {:?} 失败", path))?;
+        let stop = Arc::new(Notify::new());
+        let handle = tokio::spawn(sample_loop(BufWriter::new(file), label, progress, start, stop.clone()));
+        Ok(Self { stop, handle })
+    }
+
+    pub async fn finish(mut self) -> Result<()> {
+        self.stop.notify_one();
+        (&mut self.handle).await.context("时序采样任务异常退出")?
+    }
+}
+
+impl Drop for SeriesRecorder {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn sample_loop(
+    mut out: BufWriter<File>,
+    label: SeriesLabel,
+    progress: Arc<AtomicU64>,
+    start: Instant,
+    stop: Arc<Notify>,
+) -> Result<()> {
+    let mut ticker = tokio::time::interval_at(start + SAMPLE_INTERVAL, SAMPLE_INTERVAL);
+    let mut last_ops = 0;
+    let mut last_at = start;
+    loop {
+        let stopping = tokio::select! {
+            _ = stop.notified() => true,
+            _ = ticker.tick() => false,
+        };
+        let now = Instant::now();
+        let ops = progress.load(Ordering::Relaxed);
+        // 结束时只在最后一段区间里有新完成的操作才补一行
+        if !stopping || ops > last_ops {
+            let point = Point {
+                label: &label,
+                t: now.duration_since(start).as_secs_f64(),
+                ops_completed: ops,
+                interval_ops_per_sec: (ops - last_ops) as f64 / now.duration_since(last_at).as_secs_f64().max(1e-6),
+            };
+            serde_json::to_writer(&mut out, &point)?;
+            out.write_all(b"\n")?;
+            // 每行立刻落盘，运行中可以 tail -f 观察
+            out.flush().context("写入 --timeseries 文件失败")?;
+            last_ops = ops;
+            last_at = now;
+        }
+        if stopping {
+            return Ok(());
+        }
+    }
+}
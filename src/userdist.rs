@@ -0,0 +1,417 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{anyhow, bail, Result};
+use rand::Rng;
+use rand_distr::{Distribution as RandDistribution, Normal, Zipf};
+
+use crate::config::Distribution;
+
+// 未显式给出时沿用的 Zipf 指数
+pub const DEFAULT_ZIPF_THETA: f64 = 1.03;
+pub const DEFAULT_USERS: u64 = 1_000_000;
+
+/// user_id 的单个子分布
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Component {
+    Uniform,
+    Zipf { theta: f64 },
+    /// 未给出均值/标准差时按 user_id 基数取默认值，见 normal_params
+    Normal { mean: Option<f64>, stddev: Option<f64> },
+}
+
+/// 加权混合分布，如 `zipf:0.7,uniform:0.3`、`zipf(1.2):0.7,uniform:0.3` 或 `normal(500000,50000):1`。
+/// 权重必须为正，不要求和为 1，会按总和归一化。
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserDistSpec {
+    parts: Vec<(f64, Component)>,
+}
+
+impl UserDistSpec {
+    /// 把单一分布枚举表示成只有一个分量的混合
+    pub fn single(distribution: Distribution) -> Self {
+        let component = match distribution {
+            Distribution::Uniform => Component::Uniform,
+            Distribution::Zipf => Component::Zipf {
+                theta: DEFAULT_ZIPF_THETA,
+            },
+            Distribution::Normal => Component::Normal {
+                mean: None,
+                stddev: None,
+            },
+        };
+        Self {
+            parts: vec![(1.0, component)],
+        }
+    }
+
+    /// 指定指数的单一 Zipf 分布
+    pub fn zipf(theta: f64) -> Self {
+        Self {
+            parts: vec![(1.0, Component::Zipf { theta })],
+        }
+    }
+
+    /// 单一正态分布；未给出的参数按 user_id 基数取默认值
+    pub fn normal(mean: Option<f64>, stddev: Option<f64>, users: u64) -> Self {
+        let (mean, stddev) = normal_params(mean, stddev, users);
+        Self {
+            parts: vec![(
+                1.0,
+                Component::Normal {
+                    mean: Some(mean),
+                    stddev: Some(stddev),
+                },
+            )],
+        }
+    }
+}
+
+/// 正态分量的均值与标准差，默认以 users/2 为中心、标准差 users/10
+fn normal_params(mean: Option<f64>, stddev: Option<f64>, users: u64) -> (f64, f64) {
+    (
+        mean.unwrap_or(users as f64 / 2.0),
+        stddev.unwrap_or((users as f64 / 10.0).max(1.0)),
+    )
+}
+
+pub fn check_normal_params(mean: Option<f64>, stddev: Option<f64>) -> Result<(), String> {
+    if let Some(mean) = mean
+        && !mean.is_finite()
+    {
+        return Err(format!("normal 均值必须为有限数，得到 {}", mean));
+    }
+    if let Some(stddev) = stddev
+        && !(stddev > 0.0 && stddev.is_finite())
+    {
+        return Err(format!("normal 标准差必须为正的有限数，得到 {}", stddev));
+    }
+    Ok(())
+}
+
+/// Zipf 指数只要求非负有限：θ 越大越集中在小 id 上，θ <= 1 时分布较平缓
+pub fn check_zipf_theta(theta: f64) -> Result<(), String> {
+    if !(theta >= 0.0 && f64::is_finite(theta)) {
+        return Err(format!("zipf 指数必须为非负有限数，得到 {}", theta));
+    }
+    Ok(())
+}
+
+impl FromStr for UserDistSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        for item in split_top_level(s).map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, weight) = item
+                .rsplit_once(':')
+                .ok_or_else(|| format!("{} 缺少权重（格式 name[:weight]，如 zipf:0.7）", item))?;
+            let weight: f64 = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("{} 的权重不是数字", item))?;
+            if !weight.is_finite() || weight <= 0.0 {
+                return Err(format!("{} 的权重必须为正数", item));
+            }
+            parts.push((weight, parse_component(name.trim())?));
+        }
+        if parts.is_empty() {
+            return Err("user 分布不能为空".to_string());
+        }
+        Ok(Self { parts })
+    }
+}
+
+/// 按括号外的逗号切分，normal(mean,stddev) 的参数里也有逗号
+fn split_top_level(s: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0i32;
+    s.split(move |ch| {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        ch == ',' && depth == 0
+    })
+}
+
+fn parse_component(name: &str) -> Result<Component, String> {
+    let (kind, arg) = match name.split_once('(') {
+        Some((kind, rest)) => {
+            let arg = rest
+                .strip_suffix(')')
+                .ok_or_else(|| format!("{} 缺少右括号", name))?;
+            (kind.trim(), Some(arg.trim()))
+        }
+        None => (name, None),
+    };
+    match (kind.to_ascii_lowercase().as_str(), arg) {
+        ("uniform", None) => Ok(Component::Uniform),
+        ("uniform", Some(_)) => Err("uniform 不接受参数".to_string()),
+        ("zipf", arg) => {
+            let theta = match arg {
+                Some(a) => a.parse().map_err(|_| format!("zipf 参数 {} 不是数字", a))?,
+                None => DEFAULT_ZIPF_THETA,
+            };
+            check_zipf_theta(theta)?;
+            Ok(Component::Zipf { theta })
+        }
+        ("normal", None) => Ok(Component::Normal {
+            mean: None,
+            stddev: None,
+        }),
+        ("normal", Some(arg)) => {
+            let (mean, stddev) = arg
+                .split_once(',')
+                .ok_or_else(|| format!("normal 参数应为 normal(均值,标准差)，得到 {}", name))?;
+            let mean: f64 = mean
+                .trim()
+                .parse()
+                .map_err(|_| format!("normal 均值 {} 不是数字", mean.trim()))?;
+            let stddev: f64 = stddev
+                .trim()
+                .parse()
+                .map_err(|_| format!("normal 标准差 {} 不是数字", stddev.trim()))?;
+            check_normal_params(Some(mean), Some(stddev))?;
+            Ok(Component::Normal {
+                mean: Some(mean),
+                stddev: Some(stddev),
+            })
+        }
+        _ => Err(format!("未知分布 {}（支持 uniform、zipf、normal）", kind)),
+    }
+}
+
+impl fmt::Display for UserDistSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (weight, component)) in self.parts.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            match component {
+                Component::Uniform => write!(f, "uniform:{}", weight)?,
+                Component::Zipf { theta } => write!(f, "zipf({}):{}", theta, weight)?,
+                Component::Normal {
+                    mean: Some(mean),
+                    stddev: Some(stddev),
+                } => write!(f, "normal({},{}):{}", mean, stddev, weight)?,
+                Component::Normal { .. } => write!(f, "normal:{}", weight)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Sampler {
+    Uniform,
+    Zipf(Zipf<f64>),
+    Normal(Normal<f64>),
+}
+
+/// 根据 UserDistSpec 构造好的采样器，生成器与基准参数共用
+pub struct UserSampler {
+    users: u64,
+    // (累计权重, 子分布)，累计权重已归一化到 (0, 1]
+    parts: Vec<(f64, Sampler)>,
+}
+
+impl UserSampler {
+    /// users 为 user_id 的基数，取值范围 1..=users；为 1 时所有分量都退化为常数 1
+    pub fn new(spec: &UserDistSpec, users: u64) -> Result<Self> {
+        if users == 0 {
+            bail!("user_id 基数必须至少为 1");
+        }
+        let total: f64 = spec.parts.iter().map(|(w, _)| w).sum();
+        let mut acc = 0.0;
+        let mut parts = Vec::with_capacity(spec.parts.len());
+        for (weight, component) in &spec.parts {
+            acc += weight / total;
+            let sampler = match component {
+                Component::Uniform => Sampler::Uniform,
+                Component::Zipf { theta } => Sampler::Zipf(
+                    Zipf::new(users, *theta).map_err(|e| anyhow!("Zipf 参数无效: {}", e))?,
+                ),
+                Component::Normal { mean, stddev } => {
+                    let (mean, stddev) = normal_params(*mean, *stddev, users);
+                    Sampler::Normal(Normal::new(mean, stddev).map_err(|e| anyhow!("normal 参数无效: {}", e))?)
+                }
+            };
+            parts.push((acc, sampler));
+        }
+        Ok(Self { users, parts })
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> i64 {
+        // 只有一个分量时不额外消耗随机数，保证与单一分布时的序列一致
+        let sampler = if self.parts.len() == 1 {
+            &self.parts[0].1
+        } else {
+            let u: f64 = rng.r#gen();
+            self.parts
+                .iter()
+                .find(|(cum, _)| u < *cum)
+                .map(|(_, s)| s)
+                .unwrap_or(&self.parts[self.parts.len() - 1].1)
+        };
+        let id = match sampler {
+            Sampler::Uniform => rng.gen_range(1..=self.users as i64),
+            Sampler::Zipf(zipf) => zipf.sample(rng) as i64,
+            Sampler::Normal(normal) => normal.sample(rng).round() as i64,
+        };
+        // Zipf 以浮点计算，n 很大时舍入可能越界；正态分布的两侧尾部也落在范围外，都钳回合法范围
+        id.clamp(1, self.users as i64)
+    }
+}
+
+impl UserDistSpec {
+    /// 理论上 user_id 落在 [lo, hi] 的概率；Zipf 分量的概率正比于 k^-theta
+    fn expected_shares(&self, users: u64, buckets: &[(u64, u64)]) -> Vec<f64> {
+        let total: f64 = self.parts.iter().map(|(w, _)| w).sum();
+        let mut shares = vec![0.0; buckets.len()];
+        for (weight, component) in &self.parts {
+            let weight = weight / total;
+            match component {
+                Component::Uniform => {
+                    for (share, &(lo, hi)) in shares.iter_mut().zip(buckets) {
+                        *share += weight * (hi - lo + 1) as f64 / users as f64;
+                    }
+                }
+                Component::Zipf { theta } => {
+                    let mass: Vec<f64> = buckets
+                        .iter()
+                        .map(|&(lo, hi)| (lo..=hi).map(|k| (k as f64).powf(-theta)).sum())
+                        .collect();
+                    let norm: f64 = mass.iter().sum();
+                    for (share, m) in shares.iter_mut().zip(mass) {
+                        *share += weight * m / norm;
+                    }
+                }
+                Component::Normal { mean, stddev } => {
+                    // 采样时四舍五入并钳到 1..=users，两端的桶包含对应一侧的全部尾部
+                    let (mean, stddev) = normal_params(*mean, *stddev, users);
+                    let cdf = |x: f64| normal_cdf((x - mean) / stddev);
+                    for (share, &(lo, hi)) in shares.iter_mut().zip(buckets) {
+                        let below = if lo <= 1 { 0.0 } else { cdf(lo as f64 - 0.5) };
+                        let upto = if hi >= users { 1.0 } else { cdf(hi as f64 + 0.5) };
+                        *share += weight * (upto - below);
+                    }
+                }
+            }
+        }
+        shares
+    }
+}
+
+/// 标准正态分布的累积分布函数，erf 用 Abramowitz-Stegun 7.1.26 近似（误差 < 1.5e-7）
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 { 0.5 * (1.0 + erf) } else { 0.5 * (1.0 - erf) }
+}
+
+/// 装载期间实际生成的 user_id 直方图，按 2 的幂分桶：[1,1]、[2,3]、[4,7]……
+/// Zipf 下绝大多数取值集中在小 id，等宽分桶会全部挤在第一个桶里
+#[derive(Debug)]
+pub struct UserHistogram {
+    users: u64,
+    buckets: Vec<AtomicU64>,
+}
+
+impl UserHistogram {
+    pub fn new(users: u64) -> Self {
+        let n = Self::bucket_of(users as i64) + 1;
+        Self {
+            users,
+            buckets: (0..n).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    pub fn bucket_count(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn bucket_of(user_id: i64) -> usize {
+        (63 - (user_id.max(1) as u64).leading_zeros()) as usize
+    }
+
+    /// 合并 worker 本地计数并清零，避免每行都争抢同一个原子变量
+    pub fn merge(&self, local: &mut [u64]) {
+        for (bucket, count) in self.buckets.iter().zip(local.iter_mut()) {
+            if *count > 0 {
+                bucket.fetch_add(*count, Ordering::Relaxed);
+                *count = 0;
+            }
+        }
+    }
+
+    /// 逐桶打印实际占比与 spec 的理论占比
+    pub fn report(&self, spec: &UserDistSpec) {
+        let counts: Vec<u64> = self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return;
+        }
+        let ranges: Vec<(u64, u64)> = (0..counts.len())
+            .map(|i| (1u64 << i, ((1u64 << (i + 1)) - 1).min(self.users)))
+            .collect();
+        let expected = spec.expected_shares(self.users, &ranges);
+        tracing::info!("user_id 分布（{}，共 {} 行）: 区间 实际占比 / 理论占比", spec, total);
+        for ((lo, hi), (count, share)) in ranges.iter().zip(counts.iter().zip(expected)) {
+            tracing::info!(
+                "  [{}, {}] {:>7.3}% / {:>7.3}%",
+                lo,
+                hi,
+                *count as f64 * 100.0 / total as f64,
+                share * 100.0
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn samples(spec: &str, users: u64) -> Vec<i64> {
+        let spec: UserDistSpec = spec.parse().unwrap();
+        let sampler = UserSampler::new(&spec, users).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        (0..10_000).map(|_| sampler.sample(&mut rng)).collect()
+    }
+
+    #[test]
+    fn single_user_always_samples_one() {
+        for spec in ["uniform:1", "zipf:1", "zipf(0):1", "normal:1", "zipf:0.7,uniform:0.3"] {
+            assert!(samples(spec, 1).iter().all(|&id| id == 1), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn two_users_stay_in_range_and_hit_both() {
+        for spec in ["uniform:1", "zipf:1", "zipf(2.5):1", "normal:1", "zipf:0.7,uniform:0.3"] {
+            let ids = samples(spec, 2);
+            assert!(ids.iter().all(|&id| (1..=2).contains(&id)), "{}", spec);
+            assert!(ids.contains(&1) && ids.contains(&2), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn zipf_favours_small_ids() {
+        let ids = samples("zipf:1", 1000);
+        assert!(ids.iter().all(|&id| (1..=1000).contains(&id)));
+        let ones = ids.iter().filter(|&&id| id == 1).count();
+        let tail = ids.iter().filter(|&&id| id > 500).count();
+        assert!(ones > tail, "id=1 {} 次，id>500 {} 次", ones, tail);
+    }
+
+    #[test]
+    fn users_must_be_positive() {
+        assert!(UserSampler::new(&UserDistSpec::single(Distribution::Uniform), 0).is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::{bail, Context, Result};
+use mysql_async::prelude::Queryable;
+
+use crate::config::{DbConfig, DbKind};
+use crate::connect;
+
+/// 装载后的完整性校验：精确计数与整表校验和，行数与 expected 不符时报错
+pub async fn check(db: &DbConfig, expected: u64) -> Result<()> {
+    let (count, checksum) = match db.kind {
+        DbKind::Mysql => mysql_checksum(db).await?,
+        DbKind::Postgres => postgres_checksum(db).await?,
+    };
+    tracing::info!("校验: events 共 {} 行，校验和 {}", count, checksum);
+    if count != expected {
+        bail!(
+            "校验失败: events 实有 {} 行，应为 {} 行（相差 {}），可能有批次写入失败未被发现",
+            count,
+            expected,
+            count as i64 - expected as i64
+        );
+    }
+    Ok(())
+}
+
+async fn mysql_checksum(db: &DbConfig) -> Result<(u64, String)> {
+    let pool = connect::mysql_pool(&db.url, &db.connect).await?;
+    let mut conn = pool.get_conn().await?;
+    let count: Option<u64> = conn.query_first("SELECT COUNT(*) FROM events").await?;
+    // 返回 (表名, 校验和)；表不存在时校验和为 NULL
+    let (_, checksum): (String, Option<u64>) = conn
+        .query_first("CHECKSUM TABLE events")
+        .await?
+        .context("CHECKSUM TABLE events 没有返回结果")?;
+    conn.disconnect().await?;
+    pool.disconnect().await?;
+    let checksum = checksum.context("CHECKSUM TABLE events 返回 NULL")?;
+    Ok((count.unwrap_or(0), checksum.to_string()))
+}
+
+async fn postgres_checksum(db: &DbConfig) -> Result<(u64, String)> {
+    let (client, connection) = connect::postgres(&db.url, &db.connect).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("Postgres 连接任务出错: {}", e);
+        }
+    });
+    // 对每行文本的 md5 取前 64 位求和：与行的物理顺序无关，也不必像 string_agg 那样把整表拼成一个串
+    let row = client
+        .query_typed(
+            "SELECT COUNT(*), COALESCE(SUM(('x' || left(md5(e::text), 16))::bit(64)::bigint::numeric), 0)::text \
+             FROM events e",
+            &[],
+        )
+        .await?
+        .into_iter()
+        .next()
+        .context("计算 events 校验和没有返回结果")?;
+    Ok((row.get::<_, i64>(0).max(0) as u64, row.get(1)))
+}